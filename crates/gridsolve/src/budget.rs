@@ -0,0 +1,135 @@
+//! Limits on how much work the solver may do before giving up.
+
+use alloc::sync::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Limits on solving, none of which are set by default.
+/// When one is reached, the solver stops and returns the steps it made so far.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    /// Maximum number of steps to make.
+    pub max_steps: Option<usize>,
+
+    /// Maximum number of passes over the clues and deduction rules.
+    pub max_passes: Option<usize>,
+
+    /// Maximum time to spend solving.
+    /// Ignored without the `std` feature and on `wasm32-unknown-unknown`,
+    /// which have no clock.
+    pub time_limit: Option<Duration>,
+
+    /// Stops the solver when set to `true`, possibly from another thread.
+    pub cancel: Option<Arc<AtomicBool>>,
+
+    /// Check complete solutions against every clue with `verify`, panicking
+    /// if the solver made a mistake. Meant for debugging the solver.
+    pub self_check: bool,
+}
+
+/// How far the solver got.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Every label was matched.
+    Solved,
+
+    /// The solver got stuck before matching every label.
+    Partial,
+
+    /// The solver stopped after making `Budget::max_steps` steps.
+    StepLimit,
+
+    /// The solver stopped after `Budget::max_passes` passes.
+    PassLimit,
+
+    /// The solver stopped after `Budget::time_limit`.
+    TimeLimit,
+
+    /// The solver was cancelled through `Budget::cancel`.
+    Cancelled,
+}
+
+impl Status {
+    /// Return whether the solver stopped because its budget ran out.
+    pub fn is_out_of_budget(self) -> bool {
+        !matches!(self, Status::Solved | Status::Partial)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Status::Solved => "solved",
+            Status::Partial => "stuck before matching every label",
+            Status::StepLimit => "step limit reached",
+            Status::PassLimit => "pass limit reached",
+            Status::TimeLimit => "time limit reached",
+            Status::Cancelled => "cancelled",
+        })
+    }
+}
+
+/// Measures the time spent solving, where there is a clock.
+pub(crate) struct Timer {
+    #[cfg(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    start: std::time::Instant,
+}
+
+impl Timer {
+    pub fn start() -> Timer {
+        Timer {
+            #[cfg(all(
+                feature = "std",
+                not(all(target_arch = "wasm32", target_os = "unknown"))
+            ))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn elapsed(&self) -> Option<Duration> {
+        Some(self.start.elapsed())
+    }
+
+    #[cfg(any(
+        not(feature = "std"),
+        all(target_arch = "wasm32", target_os = "unknown")
+    ))]
+    pub fn elapsed(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Budget {
+    /// Return why the budget is spent after making `steps` steps, if it is.
+    /// Passes are counted by the caller.
+    pub(crate) fn spent(&self, steps: usize, timer: &Timer) -> Option<Status> {
+        if self.max_steps.is_some_and(|max| steps >= max) {
+            Some(Status::StepLimit)
+        } else if self
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        {
+            Some(Status::Cancelled)
+        } else if self
+            .time_limit
+            .zip(timer.elapsed())
+            .is_some_and(|(limit, elapsed)| elapsed >= limit)
+        {
+            Some(Status::TimeLimit)
+        } else {
+            None
+        }
+    }
+}
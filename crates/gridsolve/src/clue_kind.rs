@@ -0,0 +1,254 @@
+//! Registry of the kinds of clue, describing how each is written so that the
+//! parser, the documentation and user interfaces agree on them.
+
+use crate::constraint::ConstraintKind;
+use crate::puzzle::*;
+use alloc::vec::Vec;
+use serde::Serialize;
+
+/// Type of an argument of a clue.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+    Label,
+    Category,
+
+    /// A distance between two labels, at least the `min` of its param and
+    /// less than the number of labels per category.
+    Number,
+}
+
+/// An argument of a clue.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub struct Param {
+    /// Name of the argument, as used in the description of the clue.
+    pub name: &'static str,
+
+    pub kind: ParamKind,
+
+    /// Smallest value of a `ParamKind::Number` argument.
+    pub min: usize,
+}
+
+impl Param {
+    /// Check that `n` is a valid value of this number argument in a puzzle
+    /// with `labels` labels per category.
+    pub(crate) fn check_number(&self, n: usize, labels: usize) -> Result<usize, PuzzleError> {
+        if n < self.min || n >= labels {
+            Err(PuzzleError::InvalidDistance { distance: n })
+        } else {
+            Ok(n)
+        }
+    }
+}
+
+/// Resolved argument of a clue.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Arg {
+    Label(Label),
+    Category(Category),
+    Number(usize),
+}
+
+/// A kind of clue, as written in the `[Clues]` section of a puzzle file.
+#[derive(Debug, Serialize)]
+pub struct ClueKind {
+    /// The keyword naming the kind, which follows the name of the clue.
+    pub keyword: &'static str,
+
+    pub params: &'static [Param],
+
+    /// Whether `params` may be repeated, in which case they must be given at
+    /// least twice.
+    pub variadic: bool,
+
+    /// What the clue means, referring to the params by name.
+    pub description: &'static str,
+
+    /// Build the constraint from arguments matching `params`.
+    #[serde(skip)]
+    pub(crate) build: fn(&[Arg]) -> ConstraintKind,
+}
+
+impl ClueKind {
+    /// Return the arguments expected when `count` are given.
+    /// Variadic params are repeated as often as needed to cover them.
+    pub fn params_for(&self, count: usize) -> Vec<Param> {
+        let repeat = if self.variadic {
+            count.div_ceil(self.params.len()).max(2)
+        } else {
+            1
+        };
+        self.params
+            .iter()
+            .copied()
+            .cycle()
+            .take(self.params.len() * repeat)
+            .collect()
+    }
+
+    /// Return the types of the arguments expected when `count` are given.
+    pub fn param_kinds(&self, count: usize) -> Vec<ParamKind> {
+        self.params_for(count).iter().map(|p| p.kind).collect()
+    }
+}
+
+const fn label(name: &'static str) -> Param {
+    Param {
+        name,
+        kind: ParamKind::Label,
+        min: 0,
+    }
+}
+
+const fn category(name: &'static str) -> Param {
+    Param {
+        name,
+        kind: ParamKind::Category,
+        min: 0,
+    }
+}
+
+const fn number(name: &'static str) -> Param {
+    Param {
+        name,
+        kind: ParamKind::Number,
+        min: 0,
+    }
+}
+
+/// A number which can't be 0.
+const fn positive(name: &'static str) -> Param {
+    Param {
+        min: 1,
+        ..number(name)
+    }
+}
+
+fn unexpected(args: &[Arg]) -> ! {
+    unreachable!("arguments don't match their params: {:?}", args)
+}
+
+/// Every kind of clue, in the order in which they are documented.
+pub static CLUE_KINDS: &[ClueKind] = &[
+    ClueKind {
+        keyword: "yes",
+        params: &[label("x"), label("y")],
+        variadic: false,
+        description: "x is y.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Label(y)] => ConstraintKind::Yes(x, y),
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "no",
+        params: &[label("x"), label("y")],
+        variadic: false,
+        description: "x is not y.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Label(y)] => ConstraintKind::No(x, y),
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "or",
+        params: &[label("x"), label("y"), label("z")],
+        variadic: false,
+        description: "x is y or z.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Label(y), Arg::Label(z)] => ConstraintKind::Or(x, y, z),
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "xor",
+        params: &[label("x"), label("y"), label("z")],
+        variadic: false,
+        description: "x is y or z, but not both.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Label(y), Arg::Label(z)] => ConstraintKind::Xor(x, y, z),
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "after",
+        params: &[label("x"), category("C"), label("y")],
+        variadic: false,
+        description: "In category C, x appears somewhere after y.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Category(c), Arg::Label(y)] => ConstraintKind::After(x, c, y),
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "afteratleast",
+        params: &[label("x"), category("C"), label("y"), number("n")],
+        variadic: false,
+        description: "In category C, x appears at least n labels after y.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Category(c), Arg::Label(y), Arg::Number(n)] => {
+                ConstraintKind::AfterAtLeast(x, c, y, n)
+            }
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "afterexactly",
+        params: &[label("x"), category("C"), label("y"), number("n")],
+        variadic: false,
+        description: "In category C, x appears exactly n labels after y.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Category(c), Arg::Label(y), Arg::Number(n)] => {
+                ConstraintKind::AfterExactly(x, c, y, n)
+            }
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "distance",
+        // Two labels can't be no distance apart.
+        params: &[label("x"), category("C"), label("y"), positive("n")],
+        variadic: false,
+        description: "In category C, x appears exactly n labels before or after y.",
+        build: |args| match *args {
+            [Arg::Label(x), Arg::Category(c), Arg::Label(y), Arg::Number(n)] => {
+                ConstraintKind::Distance(x, c, y, n)
+            }
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "twobytwo",
+        params: &[label("x1"), label("x2"), label("y1"), label("y2")],
+        variadic: false,
+        description: "Either x1 is y1 and x2 is y2, or x1 is y2 and x2 is y1.",
+        build: |args| match *args {
+            [Arg::Label(x1), Arg::Label(x2), Arg::Label(y1), Arg::Label(y2)] => {
+                ConstraintKind::TwoByTwo(x1, x2, y1, y2)
+            }
+            _ => unexpected(args),
+        },
+    },
+    ClueKind {
+        keyword: "exactlyone",
+        params: &[label("x"), label("y")],
+        variadic: true,
+        description: "Of the pairs given, x is y for exactly one.",
+        build: |args| {
+            ConstraintKind::ExactlyOne(
+                args.chunks(2)
+                    .map(|pair| match *pair {
+                        [Arg::Label(x), Arg::Label(y)] => (x, y),
+                        _ => unexpected(args),
+                    })
+                    .collect(),
+            )
+        },
+    },
+];
+
+/// Return the kind of clue named by `keyword`.
+pub fn clue_kind(keyword: &str) -> Option<&'static ClueKind> {
+    CLUE_KINDS.iter().find(|kind| kind.keyword == keyword)
+}
@@ -0,0 +1,169 @@
+//! Runs puzzle files which declare their solution in a `[Solution]` section,
+//! and compares what the solver finds with it.
+
+use crate::format::PuzzleFormat;
+use crate::puzzle::*;
+use crate::solver::{solve, Cell};
+use crate::verify::{verify, Answer, Violation};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A label of the declared solution which the solver matched differently.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Difference {
+    /// Label of the first category of the entity.
+    pub entity: String,
+
+    pub category: String,
+
+    /// The label declared by the solution.
+    pub expected: String,
+
+    /// The label found by the solver, if it found one.
+    pub found: Option<String>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}, {}: expected {}, found {}",
+            self.entity,
+            self.category,
+            self.expected,
+            self.found.as_deref().unwrap_or("nothing")
+        )
+    }
+}
+
+/// Outcome of checking a puzzle against its declared solution.
+#[derive(Debug)]
+pub enum Outcome {
+    /// The solver found exactly the declared solution.
+    Passed,
+
+    /// The puzzle doesn't declare a solution, so there was nothing to check.
+    Skipped,
+
+    /// The declared solution breaks some of the clues.
+    InvalidSolution(Vec<Violation>),
+
+    /// The solver found a contradiction in the clues.
+    Contradiction,
+
+    /// The solver's result differs from the declared solution.
+    Mismatch(Vec<Difference>),
+
+    /// The file couldn't be read or parsed.
+    Error(PuzzleError),
+}
+
+impl Outcome {
+    /// Return whether the outcome counts as a failure.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, Outcome::Passed | Outcome::Skipped)
+    }
+}
+
+/// The outcome of checking one puzzle file.
+#[derive(Debug)]
+pub struct TestResult {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+}
+
+/// Solve `puzzle` and compare the result with the solution it declares.
+pub fn check_solution(puzzle: &Puzzle) -> Outcome {
+    let expected = match puzzle.solution() {
+        Some(answer) => answer,
+        None => return Outcome::Skipped,
+    };
+    let violations = verify(puzzle, expected);
+    if !violations.is_empty() {
+        return Outcome::InvalidSolution(violations);
+    }
+    let solution = match solve(puzzle) {
+        Some(solution) => solution,
+        None => return Outcome::Contradiction,
+    };
+    let grid = solution.grid(solution.steps.len());
+    let differences = diff(puzzle, expected, |x, y| *grid.at(x, y) == Cell::Yes);
+    if differences.is_empty() {
+        Outcome::Passed
+    } else {
+        Outcome::Mismatch(differences)
+    }
+}
+
+/// Compare `expected` with the labels which `matched` says belong together.
+fn diff(
+    puzzle: &Puzzle,
+    expected: &Answer,
+    matched: impl Fn(Label, Label) -> bool,
+) -> Vec<Difference> {
+    let n = puzzle.labels_per_category();
+    let mut differences = vec![];
+    for entity in 0..expected.num_entities() {
+        let primary = expected.label(entity, Category(0));
+        for category in puzzle.categories().skip(1) {
+            let label = expected.label(entity, category);
+            if matched(primary, label) {
+                continue;
+            }
+            let found = (0..n)
+                .map(|l| Label::new(category, l))
+                .find(|&l| matched(primary, l));
+            differences.push(Difference {
+                entity: puzzle.label_reference(primary),
+                category: puzzle.lookup_category(category).to_string(),
+                expected: puzzle.label_reference(label),
+                found: found.map(|l| puzzle.label_reference(l)),
+            });
+        }
+    }
+    differences
+}
+
+/// Read the puzzle at `path` and check it against its declared solution.
+pub fn test_file(path: &Path) -> TestResult {
+    let outcome = match Puzzle::from_file_as(path, PuzzleFormat::from_path(path)) {
+        Ok(puzzle) => check_solution(&puzzle),
+        Err(error) => Outcome::Error(error),
+    };
+    TestResult {
+        path: path.to_path_buf(),
+        outcome,
+    }
+}
+
+/// Return every puzzle file in `dir` and its subdirectories, in order.
+/// Puzzle files are recognized by their extension.
+pub fn puzzle_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    let mut files = vec![];
+    for path in entries {
+        if path.is_dir() {
+            files.extend(puzzle_files(&path)?);
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(PuzzleFormat::from_extension)
+            .is_some()
+        {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Check every puzzle file in `dir` against its declared solution.
+pub fn test_dir(dir: &Path) -> io::Result<Vec<TestResult>> {
+    Ok(puzzle_files(dir)?
+        .iter()
+        .map(|path| test_file(path))
+        .collect())
+}
@@ -1,11 +1,18 @@
 #![allow(clippy::many_single_char_names)]
 
+use crate::clue_kind::Arg;
+use crate::locale::Locale;
+use crate::message::{Message, MessageId};
+use crate::parse::quote;
 use crate::puzzle::*;
 use crate::rule::Rule;
 use crate::solver::{Cell, Grid};
-use anyhow::{Context, Result};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ConstraintKind {
     /// Yes(x, y) ==> (x, y) == No
     Yes(Label, Label),
@@ -40,6 +47,42 @@ pub enum ConstraintKind {
     ExactlyOne(Vec<(Label, Label)>),
 }
 
+impl ConstraintKind {
+    /// The keyword naming this kind of constraint in a puzzle file.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ConstraintKind::Yes(..) => "yes",
+            ConstraintKind::No(..) => "no",
+            ConstraintKind::Or(..) => "or",
+            ConstraintKind::Xor(..) => "xor",
+            ConstraintKind::After(..) => "after",
+            ConstraintKind::AfterAtLeast(..) => "afteratleast",
+            ConstraintKind::AfterExactly(..) => "afterexactly",
+            ConstraintKind::Distance(..) => "distance",
+            ConstraintKind::TwoByTwo(..) => "twobytwo",
+            ConstraintKind::ExactlyOne(..) => "exactlyone",
+        }
+    }
+
+    /// Return the arguments from which the constraint is built by its kind
+    /// in `CLUE_KINDS`.
+    pub(crate) fn args(&self) -> Vec<Arg> {
+        use Arg::{Category as C, Label as L, Number as N};
+        match self {
+            &ConstraintKind::Yes(x, y) | &ConstraintKind::No(x, y) => vec![L(x), L(y)],
+            &ConstraintKind::Or(x, y, z) | &ConstraintKind::Xor(x, y, z) => vec![L(x), L(y), L(z)],
+            &ConstraintKind::After(x, c, y) => vec![L(x), C(c), L(y)],
+            &ConstraintKind::AfterAtLeast(x, c, y, n)
+            | &ConstraintKind::AfterExactly(x, c, y, n)
+            | &ConstraintKind::Distance(x, c, y, n) => vec![L(x), C(c), L(y), N(n)],
+            &ConstraintKind::TwoByTwo(x1, x2, y1, y2) => vec![L(x1), L(x2), L(y1), L(y2)],
+            ConstraintKind::ExactlyOne(pairs) => {
+                pairs.iter().flat_map(|&(x, y)| vec![L(x), L(y)]).collect()
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Constraint {
     pub kind: ConstraintKind,
@@ -52,7 +95,6 @@ impl Constraint {
     fn apply_after_at_least<'p>(
         &self,
         grid: &mut Grid<'p>,
-        puzzle: &Puzzle,
         x: Label,
         c: Category,
         y: Label,
@@ -61,41 +103,30 @@ impl Constraint {
         let mut changed = false;
         // No overlap if x is after y.
         changed |= grid.set_with_callback(x, y, Cell::No, || {
-            format!(
-                "    Constraint {} => {} ({}) must appear after {} ({})\n",
-                self.name,
-                puzzle.lookup_label(x),
-                puzzle.lookup_category(x.category),
-                puzzle.lookup_label(y),
-                puzzle.lookup_category(y.category),
-            )
+            Message::new(MessageId::AfterDistinct)
+                .clue(&self.name)
+                .label("x", x)
+                .label("y", y)
         })?;
 
         // x must appear `n` after the appearance of y.
         for i in 0..grid.labels_per_category {
             if i < n {
                 changed |= grid.set_with_callback(x, Label::new(c, i), Cell::No, || {
-                    format!(
-                        "    Constraint {} => There must be {} in ({}) before {} ({})\n",
-                        self.name,
-                        n,
-                        puzzle.lookup_category(c),
-                        puzzle.lookup_label(x),
-                        puzzle.lookup_category(x.category),
-                    )
+                    Message::new(MessageId::RoomBefore)
+                        .clue(&self.name)
+                        .number("n", n)
+                        .category("category", c)
+                        .label("x", x)
                 })?;
             } else {
                 let l = Label::new(c, i - n);
                 if *grid.at(y, l) == Cell::No {
                     grid.set_with_callback(x, Label::new(c, i), Cell::No, || {
-                        format!(
-                            "    Constraint {} => {} ({}) conflicts with {} ({}) due to distance\n",
-                            self.name,
-                            puzzle.lookup_label(x),
-                            puzzle.lookup_category(x.category),
-                            puzzle.lookup_label(y),
-                            puzzle.lookup_category(y.category),
-                        )
+                        Message::new(MessageId::DistanceConflict)
+                            .clue(&self.name)
+                            .label("x", x)
+                            .label("y", y)
                     })?;
                 } else {
                     break;
@@ -107,27 +138,20 @@ impl Constraint {
         for i in (0..grid.labels_per_category).rev() {
             if i + n >= grid.labels_per_category {
                 changed |= grid.set_with_callback(y, Label::new(c, i), Cell::No, || {
-                    format!(
-                        "    Constraint {} => There must be {} in ({}) after {} ({})\n",
-                        self.name,
-                        n,
-                        puzzle.lookup_category(c),
-                        puzzle.lookup_label(y),
-                        puzzle.lookup_category(y.category),
-                    )
+                    Message::new(MessageId::RoomAfter)
+                        .clue(&self.name)
+                        .number("n", n)
+                        .category("category", c)
+                        .label("x", y)
                 })?;
             } else {
                 let l = Label::new(c, i + n);
                 if *grid.at(x, l) == Cell::No {
                     grid.set_with_callback(y, Label::new(c, i), Cell::No, || {
-                        format!(
-                            "    Constraint {} => {} ({}) conflicts with {} ({}) due to distance\n",
-                            self.name,
-                            puzzle.lookup_label(y),
-                            puzzle.lookup_category(y.category),
-                            puzzle.lookup_label(x),
-                            puzzle.lookup_category(x.category),
-                        )
+                        Message::new(MessageId::DistanceConflict)
+                            .clue(&self.name)
+                            .label("x", y)
+                            .label("y", x)
                     })?;
                 } else {
                     break;
@@ -139,83 +163,51 @@ impl Constraint {
     }
 
     #[must_use]
-    fn apply_xor<'p>(
-        &self,
-        grid: &mut Grid<'p>,
-        puzzle: &Puzzle,
-        x: Label,
-        y: Label,
-        z: Label,
-    ) -> Option<bool> {
+    fn apply_xor<'p>(&self, grid: &mut Grid<'p>, x: Label, y: Label, z: Label) -> Option<bool> {
         let mut changed = false;
         changed |= grid.set_with_callback(y, z, Cell::No, || {
-            format!(
-                "    Constraint {} => {} ({}) requires {} ({}) xor {} ({}), can't be the same\n",
-                self.name,
-                puzzle.lookup_label(x),
-                puzzle.lookup_category(x.category),
-                puzzle.lookup_label(y),
-                puzzle.lookup_category(y.category),
-                puzzle.lookup_label(z),
-                puzzle.lookup_category(z.category),
-            )
+            Message::new(MessageId::XorDistinct)
+                .clue(&self.name)
+                .label("x", x)
+                .label("y", y)
+                .label("z", z)
         })?;
 
         // If one of them is No, the other must be Yes.
         if *grid.at(x, y) == Cell::No {
             changed |= grid.set_with_callback(x, z, Cell::Yes, || {
-                format!(
-                    "    Constraint {} => {} ({}) not with {} ({}), so must be with {} ({})\n",
-                    self.name,
-                    puzzle.lookup_label(x),
-                    puzzle.lookup_category(x.category),
-                    puzzle.lookup_label(y),
-                    puzzle.lookup_category(y.category),
-                    puzzle.lookup_label(z),
-                    puzzle.lookup_category(z.category),
-                )
+                Message::new(MessageId::XorNotWith)
+                    .clue(&self.name)
+                    .label("x", x)
+                    .label("y", y)
+                    .label("z", z)
             })?;
         } else if *grid.at(x, z) == Cell::No {
             changed |= grid.set_with_callback(x, y, Cell::Yes, || {
-                format!(
-                    "    Constraint {} => {} ({}) not with {} ({}), so must be with {} ({})\n",
-                    self.name,
-                    puzzle.lookup_label(x),
-                    puzzle.lookup_category(x.category),
-                    puzzle.lookup_label(z),
-                    puzzle.lookup_category(z.category),
-                    puzzle.lookup_label(y),
-                    puzzle.lookup_category(y.category),
-                )
+                Message::new(MessageId::XorNotWith)
+                    .clue(&self.name)
+                    .label("x", x)
+                    .label("y", z)
+                    .label("z", y)
             })?;
         }
 
         // If one of them is Yes, the other must be No.
         if *grid.at(x, y) == Cell::Yes {
             changed |= grid.set_with_callback(x, z, Cell::No, || {
-                format!(
-                    "    Constraint {} => {} ({}) with {} ({}), so can't be with {} ({})\n",
-                    self.name,
-                    puzzle.lookup_label(x),
-                    puzzle.lookup_category(x.category),
-                    puzzle.lookup_label(y),
-                    puzzle.lookup_category(y.category),
-                    puzzle.lookup_label(z),
-                    puzzle.lookup_category(z.category),
-                )
+                Message::new(MessageId::XorWith)
+                    .clue(&self.name)
+                    .label("x", x)
+                    .label("y", y)
+                    .label("z", z)
             })?;
         } else if *grid.at(x, z) == Cell::Yes {
             changed |= grid.set_with_callback(x, y, Cell::No, || {
-                format!(
-                    "    Constraint {} => {} ({}) with {} ({}), so can't be with {} ({})\n",
-                    self.name,
-                    puzzle.lookup_label(x),
-                    puzzle.lookup_category(x.category),
-                    puzzle.lookup_label(z),
-                    puzzle.lookup_category(z.category),
-                    puzzle.lookup_label(y),
-                    puzzle.lookup_category(y.category),
-                )
+                Message::new(MessageId::XorWith)
+                    .clue(&self.name)
+                    .label("x", x)
+                    .label("y", z)
+                    .label("z", y)
             })?;
         }
 
@@ -227,239 +219,170 @@ impl Constraint {
             }
             if *grid.at(y, w) == Cell::No && *grid.at(z, w) == Cell::No {
                 changed |= grid.set_with_callback(x, w, Cell::No, || {
-                    format!(
-                        "    Constraint {} => {} ({}) must be either {} ({}) or {} ({}), \
-conflicts with {} ({}) which is with neither\n",
-                        self.name,
-                        puzzle.lookup_label(x),
-                        puzzle.lookup_category(x.category),
-                        puzzle.lookup_label(z),
-                        puzzle.lookup_category(z.category),
-                        puzzle.lookup_label(y),
-                        puzzle.lookup_category(y.category),
-                        puzzle.lookup_label(w),
-                        puzzle.lookup_category(y.category),
-                    )
+                    Message::new(MessageId::EitherNeither)
+                        .clue(&self.name)
+                        .label("x", x)
+                        .label("y", z)
+                        .label("z", y)
+                        .label("w", w)
                 })?;
             }
             if *grid.at(y, w) == Cell::Yes && *grid.at(z, w) == Cell::Yes {
                 changed |= grid.set_with_callback(x, w, Cell::No, || {
-                    format!(
-                        "    Constraint {} => {} ({}) must be one of {} ({}) or {} ({}), \
-conflicts with {} ({}) which is with both\n",
-                        self.name,
-                        puzzle.lookup_label(x),
-                        puzzle.lookup_category(x.category),
-                        puzzle.lookup_label(z),
-                        puzzle.lookup_category(z.category),
-                        puzzle.lookup_label(y),
-                        puzzle.lookup_category(y.category),
-                        puzzle.lookup_label(w),
-                        puzzle.lookup_category(y.category),
-                    )
+                    Message::new(MessageId::EitherBoth)
+                        .clue(&self.name)
+                        .label("x", x)
+                        .label("y", z)
+                        .label("z", y)
+                        .label("w", w)
                 })?;
             }
         }
         Some(changed)
     }
 
-    pub fn from_str(puzzle: &Puzzle, line: &str) -> Result<Self, PuzzleError> {
-        let parts_vec = line
-            .trim()
-            .split(',')
-            .map(|s| s.trim())
-            .collect::<Vec<&str>>();
-        let found = parts_vec.len();
-        if parts_vec.len() < 3 {
-            return Err(PuzzleError::InvalidClueCount { expected: 3, found });
-        }
-        let (name, parts) = parts_vec.as_slice().split_first().unwrap();
-        let kind = match parts[0] {
-            "yes" => {
-                if parts.len() < 3 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 3, found });
-                }
-                ConstraintKind::Yes(puzzle.label(parts[1])?, puzzle.label(parts[2])?)
-            }
-            "no" => {
-                if parts.len() < 3 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 3, found });
-                }
-                ConstraintKind::No(puzzle.label(parts[1])?, puzzle.label(parts[2])?)
-            }
-            "after" => {
-                if parts.len() < 4 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                ConstraintKind::After(
-                    puzzle.label(parts[1])?,
-                    puzzle.category(parts[2])?,
-                    puzzle.label(parts[3])?,
-                )
-            }
-            "afteratleast" => {
-                if parts.len() < 4 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                let n: usize = parts[4]
-                    .parse()
-                    .with_context(|| "invalid integer")
-                    .map_err(|e| PuzzleError::InvalidInteger { source: e })?;
-                if n > puzzle.labels_per_category() - 1 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                ConstraintKind::AfterAtLeast(
-                    puzzle.label(parts[1])?,
-                    puzzle.category(parts[2])?,
-                    puzzle.label(parts[3])?,
-                    n,
-                )
-            }
-            "afterexactly" => {
-                if parts.len() < 4 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                let n: usize = parts[4]
-                    .parse()
-                    .with_context(|| "invalid integer")
-                    .map_err(|e| PuzzleError::InvalidInteger { source: e })?;
-                if n > puzzle.labels_per_category() - 1 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                ConstraintKind::AfterExactly(
-                    puzzle.label(parts[1])?,
-                    puzzle.category(parts[2])?,
-                    puzzle.label(parts[3])?,
-                    n,
-                )
-            }
-            "or" => {
-                if parts.len() < 4 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                ConstraintKind::Or(
-                    puzzle.label(parts[1])?,
-                    puzzle.label(parts[2])?,
-                    puzzle.label(parts[3])?,
-                )
-            }
-            "xor" => {
-                if parts.len() < 4 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 4, found });
-                }
-                ConstraintKind::Xor(
-                    puzzle.label(parts[1])?,
-                    puzzle.label(parts[2])?,
-                    puzzle.label(parts[3])?,
-                )
+    /// Return the arguments of the constraint as they are written in a puzzle
+    /// file, with labels and categories replaced by references to them.
+    pub fn args(&self, puzzle: &Puzzle) -> Vec<String> {
+        self.kind
+            .args()
+            .into_iter()
+            .map(|arg| match arg {
+                Arg::Label(l) => puzzle.label_reference(l),
+                Arg::Category(c) => puzzle.lookup_category(c).to_string(),
+                Arg::Number(n) => n.to_string(),
+            })
+            .collect()
+    }
+
+    /// Describe what the constraint means in `locale`, naming its labels and
+    /// categories, e.g. "Leo (First Name) is not Germany (Country)."
+    pub fn describe(&self, puzzle: &Puzzle, locale: Locale) -> String {
+        let message = match &self.kind {
+            &ConstraintKind::Yes(x, y) => {
+                Message::new(MessageId::ClueYes).label("x", x).label("y", y)
             }
-            "twobytwo" => {
-                if parts.len() < 5 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 5, found });
-                }
-                ConstraintKind::TwoByTwo(
-                    puzzle.label(parts[1])?,
-                    puzzle.label(parts[2])?,
-                    puzzle.label(parts[3])?,
-                    puzzle.label(parts[4])?,
-                )
+            &ConstraintKind::No(x, y) => {
+                Message::new(MessageId::ClueNo).label("x", x).label("y", y)
             }
-            "exactlyone" => {
-                if parts.len() < 5 || parts.len() % 2 != 1 {
-                    return Err(PuzzleError::InvalidClueCount { expected: 5, found });
+            &ConstraintKind::Or(x, y, z) => Message::new(MessageId::ClueOr)
+                .label("x", x)
+                .label("y", y)
+                .label("z", z),
+            &ConstraintKind::Xor(x, y, z) => Message::new(MessageId::ClueXor)
+                .label("x", x)
+                .label("y", y)
+                .label("z", z),
+            &ConstraintKind::After(x, c, y) => Message::new(MessageId::ClueAfter)
+                .label("x", x)
+                .category("category", c)
+                .label("y", y),
+            &ConstraintKind::AfterAtLeast(x, c, y, n) => Message::new(MessageId::ClueAfterAtLeast)
+                .label("x", x)
+                .category("category", c)
+                .label("y", y)
+                .number("n", n),
+            &ConstraintKind::AfterExactly(x, c, y, n) => Message::new(MessageId::ClueAfterExactly)
+                .label("x", x)
+                .category("category", c)
+                .label("y", y)
+                .number("n", n),
+            &ConstraintKind::Distance(x, c, y, n) => Message::new(MessageId::ClueDistance)
+                .label("x", x)
+                .category("category", c)
+                .label("y", y)
+                .number("n", n),
+            &ConstraintKind::TwoByTwo(x1, x2, y1, y2) => Message::new(MessageId::ClueTwoByTwo)
+                .label("x1", x1)
+                .label("x2", x2)
+                .label("y1", y1)
+                .label("y2", y2),
+            ConstraintKind::ExactlyOne(pairs) => {
+                // The pairs are listed after the message, one sentence each.
+                let mut text = locale.render(&Message::new(MessageId::ClueExactlyOne), puzzle);
+                for &(x, y) in pairs {
+                    let pair = Message::new(MessageId::ClueYes).label("x", x).label("y", y);
+                    text.push(' ');
+                    text.push_str(&locale.render(&pair, puzzle));
                 }
-                let mut constraints = vec![];
-                for i in 0..parts.len() / 2 {
-                    constraints.push((
-                        puzzle.label(parts[i * 2 + 1])?,
-                        puzzle.label(parts[i * 2 + 2])?,
-                    ));
-                }
-                ConstraintKind::ExactlyOne(constraints)
+                return text;
             }
-            s => return Err(PuzzleError::InvalidClueName { name: s.to_owned() }),
         };
-        Ok(Constraint {
-            kind,
-            name: name.to_string(),
-        })
+        locale.render(&message, puzzle)
+    }
+
+    /// Write the constraint as a line of the `[Clues]` section,
+    /// without a trailing newline.
+    pub fn write_line(&self, puzzle: &Puzzle, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", quote(&self.name, true), self.kind.keyword())?;
+        for arg in self.args(puzzle) {
+            write!(f, ",{}", quote(&arg, true))?;
+        }
+        Ok(())
     }
 }
 
 impl Rule for Constraint {
-    fn apply<'p>(&self, grid: &mut Grid<'p>, puzzle: &'p Puzzle) -> Option<bool> {
+    fn name(&self) -> &'static str {
+        self.kind.keyword()
+    }
+
+    fn apply<'p>(&self, grid: &mut Grid<'p>, _puzzle: &'p Puzzle) -> Option<bool> {
         let mut changed = false;
         match &self.kind {
             &ConstraintKind::Yes(x, y) => {
                 changed |= grid.set_with_callback(x, y, Cell::Yes, || {
-                    format!(
-                        "Constraint {} => Direct confirmation on {} ({}) and {} ({})",
-                        self.name,
-                        puzzle.lookup_label(x),
-                        puzzle.lookup_category(x.category),
-                        puzzle.lookup_label(y),
-                        puzzle.lookup_category(y.category),
-                    )
+                    Message::new(MessageId::DirectYes)
+                        .clue(&self.name)
+                        .label("x", x)
+                        .label("y", y)
                 })?;
             }
 
             &ConstraintKind::No(x, y) => {
                 changed |= grid.set_with_callback(x, y, Cell::No, || {
-                    format!(
-                        "Constraint {} => Direct elimination on {} ({}) and {} ({})",
-                        self.name,
-                        puzzle.lookup_label(x),
-                        puzzle.lookup_category(x.category),
-                        puzzle.lookup_label(y),
-                        puzzle.lookup_category(y.category),
-                    )
+                    Message::new(MessageId::DirectNo)
+                        .clue(&self.name)
+                        .label("x", x)
+                        .label("y", y)
                 })?;
             }
 
             &ConstraintKind::After(x, c, y) => {
-                changed |= self.apply_after_at_least(grid, puzzle, x, c, y, 1)?;
+                changed |= self.apply_after_at_least(grid, x, c, y, 1)?;
             }
 
             &ConstraintKind::AfterAtLeast(x, c, y, n) => {
-                changed |= self.apply_after_at_least(grid, puzzle, x, c, y, n)?;
+                changed |= self.apply_after_at_least(grid, x, c, y, n)?;
             }
 
             &ConstraintKind::AfterExactly(x, c, y, n) => {
                 changed |= grid.set_with_callback(x, y, Cell::No, || {
-                    format!(
-                        "    Constraint {} => {} ({}) after {} ({}), so must different\n",
-                        self.name,
-                        puzzle.lookup_label(x),
-                        puzzle.lookup_category(x.category),
-                        puzzle.lookup_label(y),
-                        puzzle.lookup_category(y.category),
-                    )
+                    Message::new(MessageId::ExactlyAfterDistinct)
+                        .clue(&self.name)
+                        .label("x", x)
+                        .label("y", y)
                 })?;
                 for i in 0..grid.labels_per_category {
                     if i < n {
                         changed |= grid.set_with_callback(x, Label::new(c, i), Cell::No, || {
-                            format!(
-                                "    Constraint {} => {} ({}) must have {} before in ({})\n",
-                                self.name,
-                                puzzle.lookup_label(x),
-                                puzzle.lookup_category(x.category),
-                                n,
-                                puzzle.lookup_category(c),
-                            )
+                            Message::new(MessageId::ExactlyRoomBefore)
+                                .clue(&self.name)
+                                .label("x", x)
+                                .number("n", n)
+                                .category("category", c)
                         })?;
                         continue;
                     }
                     let l = Label::new(c, i - n);
                     if *grid.at(y, l) == Cell::No {
                         changed |= grid.set_with_callback(x, Label::new(c, i), Cell::No, || {
-                            format!(
-                                "    Constraint {} => {} ({}) must have {} after in ({})\n",
-                                self.name,
-                                puzzle.lookup_label(x),
-                                puzzle.lookup_category(x.category),
-                                n,
-                                puzzle.lookup_category(c),
-                            )
+                            Message::new(MessageId::ExactlyRoomAfter)
+                                .clue(&self.name)
+                                .label("x", x)
+                                .label("y", y)
+                                .number("n", n)
+                                .category("category", c)
                         })?;
                     }
                 }
@@ -479,7 +402,12 @@ impl Rule for Constraint {
                 changed |= grid.set(x, y, Cell::No)?;
                 for i in 0..grid.labels_per_category {
                     let cur = Label::new(c, i);
-                    if i < n {
+                    if i < n && i + n >= grid.labels_per_category {
+                        // Neither direction is possible, so neither x nor y
+                        // can be here.
+                        changed |= grid.set(x, cur, Cell::No)?;
+                        changed |= grid.set(y, cur, Cell::No)?;
+                    } else if i < n {
                         // Can't go `n` lower, so just check higher.
                         let hi_x = *grid.at(x, Label::new(c, i + n));
                         let hi_y = *grid.at(y, Label::new(c, i + n));
@@ -530,15 +458,15 @@ impl Rule for Constraint {
             }
 
             &ConstraintKind::Xor(x, y, z) => {
-                changed |= self.apply_xor(grid, puzzle, x, y, z)?;
+                changed |= self.apply_xor(grid, x, y, z)?;
             }
 
             &ConstraintKind::TwoByTwo(x1, x2, y1, y2) => {
                 // TwoByTwo is equivalent to having two Xors and a No.
                 changed |= grid.set(x1, x2, Cell::No)?;
                 changed |= grid.set(y1, y2, Cell::No)?;
-                changed |= self.apply_xor(grid, puzzle, x1, y1, y2)?;
-                changed |= self.apply_xor(grid, puzzle, x2, y1, y2)?;
+                changed |= self.apply_xor(grid, x1, y1, y2)?;
+                changed |= self.apply_xor(grid, x2, y1, y2)?;
             }
 
             ConstraintKind::ExactlyOne(constraints) => {
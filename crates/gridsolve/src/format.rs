@@ -0,0 +1,284 @@
+use crate::clue_kind::{clue_kind, Arg, ParamKind};
+use crate::constraint::{Constraint, ConstraintKind};
+use crate::puzzle::*;
+use crate::verify::Answer;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// Formats in which a puzzle file can be written.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PuzzleFormat {
+    /// The line-oriented `[Categories]`/`[Clues]` format.
+    Text,
+    Json,
+    Toml,
+
+    /// A link to the puzzle in the web app, see `SharedPuzzle`.
+    /// The comments and solution of a puzzle are lost when writing it.
+    Url,
+}
+
+impl PuzzleFormat {
+    /// Return the format corresponding to a file extension, if any.
+    pub fn from_extension(ext: &str) -> Option<PuzzleFormat> {
+        match ext.to_ascii_lowercase().as_str() {
+            "txt" => Some(PuzzleFormat::Text),
+            "json" => Some(PuzzleFormat::Json),
+            "toml" => Some(PuzzleFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guess the format of the file at `path` from its extension.
+    /// Falls back to the text format for unknown extensions.
+    #[cfg(feature = "std")]
+    pub fn from_path(path: &Path) -> PuzzleFormat {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(PuzzleFormat::from_extension)
+            .unwrap_or(PuzzleFormat::Text)
+    }
+}
+
+impl FromStr for PuzzleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(PuzzleFormat::Text),
+            "json" => Ok(PuzzleFormat::Json),
+            "toml" => Ok(PuzzleFormat::Toml),
+            "url" => Ok(PuzzleFormat::Url),
+            _ => Err(format!("Unknown puzzle format: {}", s)),
+        }
+    }
+}
+
+/// Structured representation of a puzzle, in which every label and category
+/// is referred to by name.
+#[derive(Debug, Serialize, Deserialize)]
+struct PuzzleSpec {
+    categories: Vec<CategorySpec>,
+    #[serde(default)]
+    clues: Vec<ClueSpec>,
+    /// The labels of each entity of the expected answer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    solution: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CategorySpec {
+    name: String,
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClueSpec {
+    /// Number of the original clue from which the constraint is derived.
+    name: String,
+    /// Comments written before the clue in the text format.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    comments: Vec<String>,
+    /// Keyword of the kind of clue, as in `CLUE_KINDS`.
+    kind: String,
+    /// The arguments of a clue, as in the text format but grouped by
+    /// repetition of the params for variadic kinds.
+    args: Vec<ArgSpec>,
+}
+
+/// An argument of a clue, using names in place of labels and categories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ArgSpec {
+    Name(String),
+    Number(usize),
+    /// One repetition of the params of a variadic kind of clue.
+    Group(Vec<ArgSpec>),
+}
+
+impl ClueSpec {
+    fn from_constraint(puzzle: &Puzzle, constraint: &Constraint, comments: Vec<String>) -> Self {
+        let args = constraint
+            .kind
+            .args()
+            .into_iter()
+            .map(|arg| match arg {
+                Arg::Label(l) => ArgSpec::Name(puzzle.label_reference(l)),
+                Arg::Category(c) => ArgSpec::Name(puzzle.lookup_category(c).to_string()),
+                Arg::Number(n) => ArgSpec::Number(n),
+            })
+            .collect::<Vec<_>>();
+        let kind = clue_kind(constraint.kind.keyword()).expect("every kind is registered");
+        let args = if kind.variadic {
+            args.chunks(kind.params.len())
+                .map(|group| ArgSpec::Group(group.to_vec()))
+                .collect()
+        } else {
+            args
+        };
+        ClueSpec {
+            name: constraint.name.clone(),
+            comments,
+            kind: kind.keyword.to_string(),
+            args,
+        }
+    }
+
+    /// Build the constraint from the kind of clue named by `kind`, checking
+    /// its args against the params of the kind.
+    fn to_kind(&self, puzzle: &Puzzle) -> Result<ConstraintKind, PuzzleError> {
+        let kind = clue_kind(&self.kind).ok_or_else(|| PuzzleError::InvalidClueName {
+            name: self.kind.clone(),
+        })?;
+        // Every arg is checked against its param, with its index in `args`.
+        let mut args = vec![];
+        if kind.variadic {
+            if self.args.len() < 2 {
+                return Err(PuzzleError::InvalidClueCount {
+                    expected: 2,
+                    found: self.args.len(),
+                });
+            }
+            for (i, group) in self.args.iter().enumerate() {
+                match group {
+                    ArgSpec::Group(group) if group.len() == kind.params.len() => {
+                        args.extend(group.iter().zip(kind.params).map(|arg| (i, arg)))
+                    }
+                    ArgSpec::Group(group) => {
+                        return Err(PuzzleError::InvalidClueCount {
+                            expected: kind.params.len(),
+                            found: group.len(),
+                        })
+                    }
+                    _ => {
+                        return Err(PuzzleError::InvalidArgument {
+                            index: i + 1,
+                            expected: "list of arguments",
+                        })
+                    }
+                }
+            }
+        } else {
+            if self.args.len() != kind.params.len() {
+                return Err(PuzzleError::InvalidClueCount {
+                    expected: kind.params.len(),
+                    found: self.args.len(),
+                });
+            }
+            args.extend(self.args.iter().zip(kind.params).enumerate());
+        }
+
+        let resolved = args
+            .into_iter()
+            .map(|(i, (arg, param))| match (param.kind, arg) {
+                (ParamKind::Label, ArgSpec::Name(name)) => puzzle.label(name).map(Arg::Label),
+                (ParamKind::Category, ArgSpec::Name(name)) => {
+                    puzzle.category(name).map(Arg::Category)
+                }
+                (ParamKind::Number, &ArgSpec::Number(n)) => param
+                    .check_number(n, puzzle.labels_per_category())
+                    .map(Arg::Number),
+                (kind, _) => Err(PuzzleError::InvalidArgument {
+                    index: i + 1,
+                    expected: match kind {
+                        ParamKind::Label => "label",
+                        ParamKind::Category => "category",
+                        ParamKind::Number => "number",
+                    },
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((kind.build)(&resolved))
+    }
+}
+
+impl PuzzleSpec {
+    fn from_puzzle(puzzle: &Puzzle) -> PuzzleSpec {
+        let categories = puzzle
+            .categories()
+            .map(|category| CategorySpec {
+                name: puzzle.lookup_category(category).to_string(),
+                labels: (0..puzzle.labels_per_category())
+                    .map(|l| puzzle.lookup_label(Label::new(category, l)).to_string())
+                    .collect(),
+            })
+            .collect();
+        let clues = puzzle
+            .constraints()
+            .iter()
+            .enumerate()
+            .map(|(i, constraint)| {
+                let comments = puzzle
+                    .comments(CommentAnchor::Clue(i))
+                    .map(String::from)
+                    .collect();
+                ClueSpec::from_constraint(puzzle, constraint, comments)
+            })
+            .collect();
+        let solution = match puzzle.solution() {
+            Some(answer) => (0..answer.num_entities())
+                .map(|entity| {
+                    puzzle
+                        .categories()
+                        .map(|c| puzzle.label_reference(answer.label(entity, c)))
+                        .collect()
+                })
+                .collect(),
+            None => vec![],
+        };
+        PuzzleSpec {
+            categories,
+            clues,
+            solution,
+        }
+    }
+
+    fn into_puzzle(self) -> Result<Puzzle, PuzzleError> {
+        let mut puzzle = Puzzle::default();
+        if self.categories.is_empty() {
+            return Err(PuzzleError::MissingCategories);
+        }
+        for category in &self.categories {
+            puzzle.add_category(&category.name, &category.labels)?;
+        }
+        for (i, clue) in self.clues.into_iter().enumerate() {
+            let kind = clue.to_kind(&puzzle)?;
+            for comment in clue.comments {
+                puzzle.add_comment(CommentAnchor::Clue(i), comment);
+            }
+            puzzle.add_constraint(Constraint {
+                kind,
+                name: clue.name,
+            });
+        }
+        if !self.solution.is_empty() {
+            let entities = self
+                .solution
+                .iter()
+                .map(|row| row.iter().map(|name| puzzle.label(name)).collect())
+                .collect::<Result<Vec<_>, _>>()?;
+            puzzle.set_solution(Some(Answer::from_labels(&puzzle, &entities)?));
+        }
+        Ok(puzzle)
+    }
+}
+
+impl Serialize for Puzzle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PuzzleSpec::from_puzzle(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Puzzle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PuzzleSpec::deserialize(deserializer)?
+            .into_puzzle()
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,143 @@
+//! Snapshots of the grid taken while replaying a solution, so that the grid
+//! after any step can be rebuilt without replaying every step before it.
+
+use crate::puzzle::*;
+use crate::solver::{Cell, Grid, Solution};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Serialize;
+
+/// Every cell of a grid, in the order of `Grid::cells`, as one character each:
+/// `y` for Yes, `n` for No and `.` for Empty.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Snapshot {
+    /// Number of steps made when the snapshot was taken.
+    pub step: usize,
+
+    pub cells: String,
+}
+
+/// The change made to the grid by a step.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub struct Delta {
+    /// Index of the cell in the order of `Grid::cells`.
+    pub cell: usize,
+
+    pub yes: bool,
+}
+
+/// The grid after every step of a solution, as a snapshot every `interval`
+/// steps and the delta made by each step.
+/// Rebuilding the grid after any step takes at most `interval - 1` deltas.
+#[derive(Debug, Clone, Serialize)]
+pub struct History {
+    pub interval: usize,
+
+    /// The labels of each cell, in the order of `Grid::cells`, written as in
+    /// a clue.
+    pub layout: Vec<(String, String)>,
+
+    /// A snapshot after every multiple of `interval` steps, starting with the
+    /// empty grid.
+    pub snapshots: Vec<Snapshot>,
+
+    /// The delta made by each step, in order.
+    pub deltas: Vec<Delta>,
+}
+
+fn cell_char(cell: Cell) -> char {
+    match cell {
+        Cell::Yes => 'y',
+        Cell::No => 'n',
+        Cell::Empty => '.',
+    }
+}
+
+impl Snapshot {
+    /// Take a snapshot of `grid`.
+    pub fn new(grid: &Grid) -> Snapshot {
+        Snapshot {
+            step: grid.steps.len(),
+            cells: grid
+                .cells()
+                .map(|(x, y)| cell_char(*grid.at(x, y)))
+                .collect(),
+        }
+    }
+
+    /// Return the cells of the snapshot, in the order of `Grid::cells`.
+    pub fn to_cells(&self) -> Vec<Cell> {
+        self.cells
+            .chars()
+            .map(|c| match c {
+                'y' => Cell::Yes,
+                'n' => Cell::No,
+                _ => Cell::Empty,
+            })
+            .collect()
+    }
+}
+
+impl History {
+    /// Replay `solution`, taking a snapshot every `interval` steps.
+    pub fn new(solution: &Solution, interval: usize) -> History {
+        let interval = interval.max(1);
+        let puzzle = solution.puzzle;
+        let mut grid = Grid::new(puzzle);
+        let index = grid
+            .cells()
+            .enumerate()
+            .map(|(i, cell)| (cell, i))
+            .collect::<BTreeMap<_, _>>();
+        let layout = grid
+            .cells()
+            .map(|(x, y)| (puzzle.label_reference(x), puzzle.label_reference(y)))
+            .collect();
+        let mut snapshots = vec![Snapshot::new(&grid)];
+        let mut deltas = vec![];
+        for (i, step) in solution.steps.iter().enumerate() {
+            let (x, y) = step.cell;
+            grid.put(x, y, if step.yes { Cell::Yes } else { Cell::No });
+            deltas.push(Delta {
+                cell: index[&step.cell],
+                yes: step.yes,
+            });
+            if (i + 1) % interval == 0 {
+                let mut snapshot = Snapshot::new(&grid);
+                snapshot.step = i + 1;
+                snapshots.push(snapshot);
+            }
+        }
+        History {
+            interval,
+            layout,
+            snapshots,
+            deltas,
+        }
+    }
+
+    /// Return the cells after the first `n` steps, in the order of
+    /// `Grid::cells`.
+    pub fn cells_at(&self, n: usize) -> Vec<Cell> {
+        let n = n.min(self.deltas.len());
+        let snapshot = &self.snapshots[n / self.interval];
+        let mut cells = snapshot.to_cells();
+        for delta in &self.deltas[snapshot.step..n] {
+            cells[delta.cell] = if delta.yes { Cell::Yes } else { Cell::No };
+        }
+        cells
+    }
+
+    /// Return the grid of `puzzle` after the first `n` steps, without the
+    /// steps themselves.
+    pub fn grid_at<'p>(&self, puzzle: &'p Puzzle, n: usize) -> Grid<'p> {
+        let mut grid = Grid::new(puzzle);
+        let cells = grid.cells().collect::<Vec<_>>();
+        for ((x, y), cell) in cells.into_iter().zip(self.cells_at(n)) {
+            grid.put(x, y, cell);
+        }
+        grid
+    }
+}
@@ -1,7 +1,49 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod budget;
+mod clue_kind;
+#[cfg(feature = "std")]
+mod conformance;
 mod constraint;
+mod format;
+mod history;
+mod lint;
+mod locale;
+mod message;
+mod owned;
+mod parse;
 mod puzzle;
+mod render;
+mod report;
 mod rule;
+mod share;
 mod solver;
+mod verify;
 
-pub use puzzle::{Puzzle, PuzzleError};
-pub use solver::{solve, Solution};
+pub use budget::{Budget, Status};
+pub use clue_kind::{clue_kind, ClueKind, Param, ParamKind, CLUE_KINDS};
+#[cfg(feature = "std")]
+pub use conformance::{
+    check_solution, puzzle_files, test_dir, test_file, Difference, Outcome, TestResult,
+};
+pub use constraint::{Constraint, ConstraintKind};
+pub use format::PuzzleFormat;
+pub use history::{Delta, History, Snapshot};
+pub use lint::{lint, Lint, Warning};
+pub use locale::{Locale, LocalizedSolution, LocalizedStep};
+pub use message::{Message, MessageArg, MessageId};
+pub use owned::{OwnedSolution, OwnedStep};
+pub use parse::{comment, quote, tokenize_line, Diagnostic, Span, Suggestion, Token};
+pub use puzzle::{Category, CommentAnchor, Label, Puzzle, PuzzleError};
+pub use render::{render_grid, staircase, Charset, ColumnLabels, GridStyle};
+#[cfg(feature = "schema")]
+pub use report::solution_schema;
+pub use report::{CategoryReport, EntityReport, SolutionReport, StepReport, SCHEMA_VERSION};
+pub use share::{SharedClue, SharedPuzzle, SHARE_URL};
+pub use solver::{
+    count_solutions, count_solutions_within, solve, try_solve, try_solve_all, try_solve_within,
+    Cell, Contradiction, Grid, Solution, Step,
+};
+pub use verify::{verify, Answer, Violation};
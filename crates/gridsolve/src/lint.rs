@@ -0,0 +1,103 @@
+use crate::constraint::ConstraintKind;
+use crate::puzzle::*;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A likely mistake in a clue, which doesn't prevent the puzzle from being
+/// solved but probably doesn't mean what its author intended.
+#[derive(Debug, thiserror::Error)]
+pub enum Lint {
+    #[error("{} and {} are in the same category", first, second)]
+    SameCategory { first: String, second: String },
+    #[error("{} is used more than once", label)]
+    RepeatedLabel { label: String },
+    #[error("same constraint as clue {}", name)]
+    Duplicate { name: String },
+}
+
+/// A `Lint` found in the clue with index `clue` in `Puzzle::constraints`.
+#[derive(Debug)]
+pub struct Warning {
+    pub clue: usize,
+
+    /// Name of the clue.
+    pub name: String,
+
+    pub lint: Lint,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "clue {}: {}", self.name, self.lint)
+    }
+}
+
+/// Return the pairs of labels which a constraint relates to each other.
+fn related_pairs(kind: &ConstraintKind) -> Vec<(Label, Label)> {
+    match kind {
+        &ConstraintKind::Yes(x, y) | &ConstraintKind::No(x, y) => vec![(x, y)],
+        &ConstraintKind::Or(x, y, z) | &ConstraintKind::Xor(x, y, z) => vec![(x, y), (x, z)],
+        &ConstraintKind::After(x, _, y)
+        | &ConstraintKind::AfterAtLeast(x, _, y, _)
+        | &ConstraintKind::AfterExactly(x, _, y, _)
+        | &ConstraintKind::Distance(x, _, y, _) => vec![(x, y)],
+        &ConstraintKind::TwoByTwo(x1, x2, y1, y2) => vec![(x1, y1), (x1, y2), (x2, y1), (x2, y2)],
+        ConstraintKind::ExactlyOne(pairs) => pairs.clone(),
+    }
+}
+
+/// Return every label mentioned by a constraint, in order.
+fn labels(kind: &ConstraintKind) -> Vec<Label> {
+    match kind {
+        &ConstraintKind::Yes(x, y) | &ConstraintKind::No(x, y) => vec![x, y],
+        &ConstraintKind::Or(x, y, z) | &ConstraintKind::Xor(x, y, z) => vec![x, y, z],
+        &ConstraintKind::After(x, _, y)
+        | &ConstraintKind::AfterAtLeast(x, _, y, _)
+        | &ConstraintKind::AfterExactly(x, _, y, _)
+        | &ConstraintKind::Distance(x, _, y, _) => vec![x, y],
+        &ConstraintKind::TwoByTwo(x1, x2, y1, y2) => vec![x1, x2, y1, y2],
+        ConstraintKind::ExactlyOne(_) => vec![],
+    }
+}
+
+/// Find likely mistakes in the clues of `puzzle`.
+pub fn lint(puzzle: &Puzzle) -> Vec<Warning> {
+    let mut warnings = vec![];
+    let constraints = puzzle.constraints();
+    for (i, constraint) in constraints.iter().enumerate() {
+        let mut warn = |lint| {
+            warnings.push(Warning {
+                clue: i,
+                name: constraint.name.clone(),
+                lint,
+            })
+        };
+
+        let mentioned = labels(&constraint.kind);
+        for (j, &label) in mentioned.iter().enumerate() {
+            if mentioned[..j].contains(&label) {
+                warn(Lint::RepeatedLabel {
+                    label: puzzle.label_reference(label),
+                });
+            }
+        }
+
+        for (x, y) in related_pairs(&constraint.kind) {
+            if x != y && x.category == y.category {
+                warn(Lint::SameCategory {
+                    first: puzzle.label_reference(x),
+                    second: puzzle.label_reference(y),
+                });
+            }
+        }
+
+        if let Some(original) = constraints[..i].iter().find(|c| c.kind == constraint.kind) {
+            warn(Lint::Duplicate {
+                name: original.name.clone(),
+            });
+        }
+    }
+    warnings
+}
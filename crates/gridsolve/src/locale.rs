@@ -0,0 +1,230 @@
+use crate::budget::Status;
+use crate::message::{Message, MessageArg, MessageId};
+use crate::puzzle::*;
+use crate::solver::{Solution, Step};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+use serde::Serialize;
+
+/// Languages in which the explanation of a step can be rendered.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    French,
+}
+
+impl Locale {
+    /// Every supported locale.
+    pub const ALL: &'static [Locale] = &[Locale::English, Locale::French];
+
+    /// The language code of the locale.
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::French => "fr",
+        }
+    }
+
+    /// Find the locale for a language tag such as `fr` or `en-US`.
+    /// Only the primary language subtag is considered.
+    pub fn from_language_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?;
+        Locale::ALL
+            .iter()
+            .copied()
+            .find(|locale| locale.code().eq_ignore_ascii_case(language))
+    }
+
+    /// The catalog entry for `id`.
+    /// Parameters are written as `{name}` and are replaced by the argument of
+    /// the message with that name.
+    pub(crate) fn template(self, id: MessageId) -> &'static str {
+        use MessageId::*;
+        match self {
+            Locale::English => match id {
+                DirectYes => "Constraint {clue} => Direct confirmation on {x} and {y}",
+                DirectNo => "Constraint {clue} => Direct elimination on {x} and {y}",
+                AfterDistinct => "Constraint {clue} => {x} must appear after {y}",
+                RoomBefore => "Constraint {clue} => There must be {n} in ({category}) before {x}",
+                RoomAfter => "Constraint {clue} => There must be {n} in ({category}) after {x}",
+                DistanceConflict => "Constraint {clue} => {x} conflicts with {y} due to distance",
+                XorDistinct => "Constraint {clue} => {x} requires {y} xor {z}, can't be the same",
+                XorNotWith => "Constraint {clue} => {x} not with {y}, so must be with {z}",
+                XorWith => "Constraint {clue} => {x} with {y}, so can't be with {z}",
+                EitherNeither => {
+                    "Constraint {clue} => {x} must be either {y} or {z}, \
+                     conflicts with {w} which is with neither"
+                }
+                EitherBoth => {
+                    "Constraint {clue} => {x} must be one of {y} or {z}, \
+                     conflicts with {w} which is with both"
+                }
+                ExactlyAfterDistinct => "Constraint {clue} => {x} after {y}, so must be different",
+                ExactlyRoomBefore => {
+                    "Constraint {clue} => {x} must have {n} before in ({category})"
+                }
+                ExactlyRoomAfter => {
+                    "Constraint {clue} => {x} must be exactly {n} after {y} in ({category})"
+                }
+                AlreadySet => "{x} is already set to {y}, eliminating {z}",
+                OnlyPossibility => "{x} is the only possibility for {y}",
+                Share => "{x} and {y} share {z}",
+                Irreconcilable => "{x} is irreconcilable with {y}: cannot share ({category})",
+                ClueYes => "{x} is {y}.",
+                ClueNo => "{x} is not {y}.",
+                ClueOr => "{x} is {y} or {z}.",
+                ClueXor => "{x} is {y} or {z}, but not both.",
+                ClueAfter => "In {category}, {x} appears somewhere after {y}.",
+                ClueAfterAtLeast => "In {category}, {x} appears at least {n} after {y}.",
+                ClueAfterExactly => "In {category}, {x} appears exactly {n} after {y}.",
+                ClueDistance => "In {category}, {x} appears exactly {n} before or after {y}.",
+                ClueTwoByTwo => {
+                    "Either {x1} is {y1} and {x2} is {y2}, or {x1} is {y2} and {x2} is {y1}."
+                }
+                ClueExactlyOne => "Exactly one of these is true:",
+            },
+            Locale::French => match id {
+                DirectYes => "Contrainte {clue} => Confirmation directe de {x} et {y}",
+                DirectNo => "Contrainte {clue} => Élimination directe de {x} et {y}",
+                AfterDistinct => "Contrainte {clue} => {x} doit apparaître après {y}",
+                RoomBefore => {
+                    "Contrainte {clue} => Il doit y avoir {n} dans ({category}) avant {x}"
+                }
+                RoomAfter => "Contrainte {clue} => Il doit y avoir {n} dans ({category}) après {x}",
+                DistanceConflict => {
+                    "Contrainte {clue} => {x} est incompatible avec {y} à cause de la distance"
+                }
+                XorDistinct => {
+                    "Contrainte {clue} => {x} exige soit {y} soit {z}, \
+                     qui ne peuvent pas être identiques"
+                }
+                XorNotWith => {
+                    "Contrainte {clue} => {x} n'est pas avec {y}, donc doit être avec {z}"
+                }
+                XorWith => "Contrainte {clue} => {x} est avec {y}, donc ne peut pas être avec {z}",
+                EitherNeither => {
+                    "Contrainte {clue} => {x} doit être soit {y} soit {z}, \
+                     incompatible avec {w} qui n'est avec aucun des deux"
+                }
+                EitherBoth => {
+                    "Contrainte {clue} => {x} doit être un seul de {y} ou {z}, \
+                     incompatible avec {w} qui est avec les deux"
+                }
+                ExactlyAfterDistinct => {
+                    "Contrainte {clue} => {x} est après {y}, donc ils doivent être différents"
+                }
+                ExactlyRoomBefore => {
+                    "Contrainte {clue} => {x} doit avoir {n} avant lui dans ({category})"
+                }
+                ExactlyRoomAfter => {
+                    "Contrainte {clue} => {x} doit être exactement {n} après {y} dans ({category})"
+                }
+                AlreadySet => "{x} est déjà associé à {y}, ce qui élimine {z}",
+                OnlyPossibility => "{x} est la seule possibilité pour {y}",
+                Share => "{x} et {y} partagent {z}",
+                Irreconcilable => {
+                    "{x} est inconciliable avec {y} : rien en commun dans ({category})"
+                }
+                ClueYes => "{x} est {y}.",
+                ClueNo => "{x} n'est pas {y}.",
+                ClueOr => "{x} est {y} ou {z}.",
+                ClueXor => "{x} est {y} ou {z}, mais pas les deux.",
+                ClueAfter => "Dans {category}, {x} apparaît quelque part après {y}.",
+                ClueAfterAtLeast => "Dans {category}, {x} apparaît au moins {n} après {y}.",
+                ClueAfterExactly => "Dans {category}, {x} apparaît exactement {n} après {y}.",
+                ClueDistance => "Dans {category}, {x} apparaît exactement {n} avant ou après {y}.",
+                ClueTwoByTwo => {
+                    "Soit {x1} est {y1} et {x2} est {y2}, soit {x1} est {y2} et {x2} est {y1}."
+                }
+                ClueExactlyOne => "Exactement une de ces affirmations est vraie :",
+            },
+        }
+    }
+
+    fn render_arg(self, arg: &MessageArg, puzzle: &Puzzle) -> String {
+        match arg {
+            MessageArg::Label(label) => format!(
+                "{} ({})",
+                puzzle.lookup_label(*label),
+                puzzle.lookup_category(label.category)
+            ),
+            MessageArg::Category(category) => puzzle.lookup_category(*category).to_string(),
+            MessageArg::Clue(name) => name.clone(),
+            MessageArg::Number(n) => n.to_string(),
+        }
+    }
+
+    /// Render `message` as text, naming labels and categories from `puzzle`.
+    pub fn render(self, message: &Message, puzzle: &Puzzle) -> String {
+        let mut result = String::new();
+        let mut rest = self.template(message.id);
+        while let Some(open) = rest.find('{') {
+            result.push_str(&rest[..open]);
+            let close = open + rest[open..].find('}').expect("unterminated parameter");
+            let name = &rest[open + 1..close];
+            match message.args.get(name) {
+                Some(arg) => result.push_str(&self.render_arg(arg, puzzle)),
+                None => {
+                    debug_assert!(false, "missing parameter {} for {:?}", name, message.id);
+                    result.push_str(&rest[open..=close]);
+                }
+            }
+            rest = &rest[close + 1..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Render the explanation of `step`, or an empty string if it has none.
+    pub fn describe(self, step: &Step, puzzle: &Puzzle) -> String {
+        step.message
+            .as_ref()
+            .map(|message| self.render(message, puzzle))
+            .unwrap_or_default()
+    }
+
+    /// Attach a description rendered in this locale to every step of
+    /// `solution`, for serialization.
+    pub fn localize<'s, 'p>(self, solution: &'s Solution<'p>) -> LocalizedSolution<'s, 'p> {
+        LocalizedSolution {
+            labels: &solution.labels,
+            status: solution.status,
+            steps: solution
+                .steps
+                .iter()
+                .map(|step| LocalizedStep {
+                    step,
+                    description: self.describe(step, solution.puzzle),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Locale::from_language_tag(s).ok_or_else(|| format!("Unknown locale: {}", s))
+    }
+}
+
+/// A `Solution` in which each step is described in some locale.
+#[derive(Debug, Serialize)]
+pub struct LocalizedSolution<'s, 'p> {
+    #[serde(rename = "solution")]
+    pub labels: &'s [BTreeMap<&'p str, Option<&'p str>>],
+    pub steps: Vec<LocalizedStep<'s, 'p>>,
+    pub status: Status,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalizedStep<'s, 'p> {
+    #[serde(flatten)]
+    pub step: &'s Step<'p>,
+    pub description: String,
+}
@@ -0,0 +1,159 @@
+use crate::puzzle::*;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use serde::{Deserialize, Serialize};
+
+/// Identifies the reasoning behind a step of the solution.
+/// The text for each message is looked up in a `Locale`'s catalog.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageId {
+    /// Clue `clue` says `x` is `y`.
+    DirectYes,
+    /// Clue `clue` says `x` is not `y`.
+    DirectNo,
+    /// Clue `clue` says `x` is after `y`, so they can't be the same.
+    AfterDistinct,
+    /// Clue `clue` needs `n` labels of `category` before `x`.
+    RoomBefore,
+    /// Clue `clue` needs `n` labels of `category` after `x`.
+    RoomAfter,
+    /// Clue `clue` puts `x` too far from, or too close to, `y`.
+    DistanceConflict,
+    /// Clue `clue` says `x` is exactly one of `y` and `z`, so they differ.
+    XorDistinct,
+    /// Clue `clue` says `x` is one of `y` and `z`, but it isn't `y`.
+    XorNotWith,
+    /// Clue `clue` says `x` is only one of `y` and `z`, and it is `y`.
+    XorWith,
+    /// Clue `clue` says `x` is `y` or `z`, but `w` is neither.
+    EitherNeither,
+    /// Clue `clue` says `x` is only one of `y` and `z`, but `w` is both.
+    EitherBoth,
+    /// Clue `clue` says `x` is exactly some distance after `y`.
+    ExactlyAfterDistinct,
+    /// Clue `clue` needs exactly `n` labels of `category` before `x`.
+    ExactlyRoomBefore,
+    /// Clue `clue` needs exactly `n` labels of `category` after `x`.
+    ExactlyRoomAfter,
+    /// `x` is `y`, so it can't be `z`.
+    AlreadySet,
+    /// `x` is the only label left for `y`.
+    OnlyPossibility,
+    /// `x` and `y` are both `z`.
+    Share,
+    /// `x` and `y` have nothing in common in `category`.
+    Irreconcilable,
+    /// Describes a `yes` clue: `x` is `y`.
+    ClueYes,
+    /// Describes a `no` clue: `x` is not `y`.
+    ClueNo,
+    /// Describes an `or` clue: `x` is `y` or `z`.
+    ClueOr,
+    /// Describes an `xor` clue: `x` is `y` or `z`, but not both.
+    ClueXor,
+    /// Describes an `after` clue: `x` is after `y` in `category`.
+    ClueAfter,
+    /// Describes an `afteratleast` clue: `x` is at least `n` after `y` in
+    /// `category`.
+    ClueAfterAtLeast,
+    /// Describes an `afterexactly` clue: `x` is exactly `n` after `y` in
+    /// `category`.
+    ClueAfterExactly,
+    /// Describes a `distance` clue: `x` is exactly `n` from `y` in
+    /// `category`.
+    ClueDistance,
+    /// Describes a `twobytwo` clue: `x1` and `x2` are `y1` and `y2` in some
+    /// order.
+    ClueTwoByTwo,
+    /// Introduces the pairs of an `exactlyone` clue, each described by
+    /// `ClueYes`.
+    ClueExactlyOne,
+}
+
+impl MessageId {
+    /// Every message, in declaration order.
+    pub const ALL: &'static [MessageId] = &[
+        MessageId::DirectYes,
+        MessageId::DirectNo,
+        MessageId::AfterDistinct,
+        MessageId::RoomBefore,
+        MessageId::RoomAfter,
+        MessageId::DistanceConflict,
+        MessageId::XorDistinct,
+        MessageId::XorNotWith,
+        MessageId::XorWith,
+        MessageId::EitherNeither,
+        MessageId::EitherBoth,
+        MessageId::ExactlyAfterDistinct,
+        MessageId::ExactlyRoomBefore,
+        MessageId::ExactlyRoomAfter,
+        MessageId::AlreadySet,
+        MessageId::OnlyPossibility,
+        MessageId::Share,
+        MessageId::Irreconcilable,
+        MessageId::ClueYes,
+        MessageId::ClueNo,
+        MessageId::ClueOr,
+        MessageId::ClueXor,
+        MessageId::ClueAfter,
+        MessageId::ClueAfterAtLeast,
+        MessageId::ClueAfterExactly,
+        MessageId::ClueDistance,
+        MessageId::ClueTwoByTwo,
+        MessageId::ClueExactlyOne,
+    ];
+}
+
+/// The value of a named parameter of a message.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum MessageArg {
+    Label(Label),
+    Category(Category),
+    /// The name of a clue.
+    Clue(String),
+    Number(usize),
+}
+
+/// Explanation of a step of the solution, without any presentation.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Message {
+    pub id: MessageId,
+    pub args: BTreeMap<Cow<'static, str>, MessageArg>,
+}
+
+impl Message {
+    pub fn new(id: MessageId) -> Message {
+        Message {
+            id,
+            args: BTreeMap::new(),
+        }
+    }
+
+    pub fn label(mut self, name: &'static str, label: Label) -> Message {
+        self.args.insert(name.into(), MessageArg::Label(label));
+        self
+    }
+
+    pub fn category(mut self, name: &'static str, category: Category) -> Message {
+        self.args
+            .insert(name.into(), MessageArg::Category(category));
+        self
+    }
+
+    pub fn clue(mut self, name: &str) -> Message {
+        self.args
+            .insert("clue".into(), MessageArg::Clue(name.to_string()));
+        self
+    }
+
+    pub fn number(mut self, name: &'static str, n: usize) -> Message {
+        self.args.insert(name.into(), MessageArg::Number(n));
+        self
+    }
+}
@@ -0,0 +1,173 @@
+//! Solutions which own their data, so that they can outlive their puzzle and
+//! be stored and read back.
+
+use crate::budget::Status;
+use crate::clue_kind::CLUE_KINDS;
+use crate::locale::Locale;
+use crate::message::{Message, MessageArg};
+use crate::puzzle::*;
+use crate::rule::rules;
+use crate::solver::{Grid, Solution, Step};
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// A `Solution` which doesn't borrow its puzzle.
+/// Labels are referred to by name, as in a clue, so that it can be attached
+/// to a puzzle again with `attach`, which checks that it fits.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct OwnedSolution {
+    /// Whether every label was matched, or why the solver stopped.
+    pub status: Status,
+
+    pub steps: Vec<OwnedStep>,
+}
+
+/// A `Step` which doesn't borrow its puzzle.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct OwnedStep {
+    /// The labels of the cell which was set, written as in a clue.
+    pub label1: String,
+    pub label2: String,
+
+    pub yes: bool,
+
+    /// Name of the rule, or kind of the clue, which made the step.
+    pub rule: String,
+
+    /// Why the cell was set, if known.
+    pub message: Option<Message>,
+}
+
+impl From<&Solution<'_>> for OwnedSolution {
+    fn from(solution: &Solution) -> OwnedSolution {
+        let puzzle = solution.puzzle;
+        OwnedSolution {
+            status: solution.status,
+            steps: solution
+                .steps
+                .iter()
+                .map(|step| OwnedStep {
+                    label1: puzzle.label_reference(step.cell.0),
+                    label2: puzzle.label_reference(step.cell.1),
+                    yes: step.yes,
+                    rule: step.rule.to_string(),
+                    message: step.message.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl OwnedSolution {
+    /// Make the steps again in the grid of `puzzle`, and return the resulting
+    /// solution.
+    /// Fails if a step refers to labels, clues or rules which `puzzle` doesn't
+    /// have, or sets a cell which was already set.
+    /// This doesn't check the steps against the clues: use
+    /// `Solution::verify` for that.
+    pub fn attach<'p>(&self, puzzle: &'p Puzzle) -> Result<Solution<'p>, PuzzleError> {
+        let mut steps = vec![];
+        let mut cells = BTreeSet::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let step = step
+                .attach(puzzle)
+                .and_then(|step| {
+                    if cells.insert(step.cell) {
+                        Ok(step)
+                    } else {
+                        Err(PuzzleError::RepeatedCell {
+                            label1: puzzle.label_reference(step.cell.0),
+                            label2: puzzle.label_reference(step.cell.1),
+                        })
+                    }
+                })
+                .map_err(|error| PuzzleError::InvalidStep {
+                    index: i + 1,
+                    source: Box::new(error),
+                })?;
+            steps.push(step);
+        }
+        let mut solution = Solution::from_grid(Grid::from_steps(puzzle, &steps));
+        if self.status.is_out_of_budget() {
+            solution.status = self.status;
+        }
+        Ok(solution)
+    }
+}
+
+impl OwnedStep {
+    fn attach<'p>(&self, puzzle: &'p Puzzle) -> Result<Step<'p>, PuzzleError> {
+        let (x, y) = (puzzle.label(&self.label1)?, puzzle.label(&self.label2)?);
+        if x.category == y.category {
+            return Err(PuzzleError::SameCategory {
+                label1: self.label1.clone(),
+                label2: self.label2.clone(),
+            });
+        }
+        let cell = if x.category.0 < y.category.0 {
+            (x, y)
+        } else {
+            (y, x)
+        };
+        let rule = static_rule(&self.rule).ok_or_else(|| PuzzleError::UnknownRule {
+            name: self.rule.clone(),
+        })?;
+        if let Some(message) = &self.message {
+            check_message(message, puzzle)?;
+        }
+        Ok(Step::new(
+            puzzle,
+            cell,
+            self.yes,
+            rule,
+            self.message.clone(),
+        ))
+    }
+}
+
+/// Return the name of the rule or kind of clue called `name`, as it is
+/// recorded in steps.
+fn static_rule(name: &str) -> Option<&'static str> {
+    let clues = CLUE_KINDS.iter().map(|kind| kind.keyword);
+    let rules = rules().iter().map(|rule| rule.name()).collect::<Vec<_>>();
+    clues
+        .chain(rules)
+        .chain(core::iter::once("guess"))
+        .find(|&rule| rule == name)
+}
+
+/// Check that `message` has every argument it needs, and that they all exist
+/// in `puzzle`.
+fn check_message(message: &Message, puzzle: &Puzzle) -> Result<(), PuzzleError> {
+    let invalid = |name: &str| PuzzleError::InvalidMessageArg {
+        id: format!("{:?}", message.id),
+        name: name.to_string(),
+    };
+    let n = puzzle.labels_per_category();
+    for (name, arg) in &message.args {
+        let valid = match arg {
+            MessageArg::Label(label) => {
+                label.category.0 < puzzle.num_categories() && label.label < n
+            }
+            MessageArg::Category(category) => category.0 < puzzle.num_categories(),
+            MessageArg::Clue(clue) => puzzle.constraints().iter().any(|c| &c.name == clue),
+            MessageArg::Number(_) => true,
+        };
+        if !valid {
+            return Err(invalid(name));
+        }
+    }
+    let template = Locale::default().template(message.id);
+    for param in template.split('{').skip(1) {
+        let name = param.split('}').next().unwrap_or_default();
+        if !message.args.contains_key(name) {
+            return Err(invalid(name));
+        }
+    }
+    Ok(())
+}
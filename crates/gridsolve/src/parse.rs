@@ -0,0 +1,824 @@
+//! Parser for the line-oriented `[Categories]`/`[Clues]` puzzle format.
+//!
+//! An optional `[Solution]` section after the clues lists the labels of each
+//! entity on a line of its own, in the same way as an answer table.
+//!
+//! Rather than stopping at the first problem, the parser records a
+//! `Diagnostic` for every error it finds, each pointing at the exact location
+//! in the source which caused it.
+//!
+//! Lines whose first non-blank character is `#` are comments, and are kept in
+//! the puzzle so that they can be written back out.
+//! Names may be wrapped in double quotes, inside which `\"` and `\\` are
+//! escapes for `"` and `\`, so that they can contain commas or start with `#`.
+
+use crate::clue_kind::{clue_kind, Arg, Param, ParamKind, CLUE_KINDS};
+use crate::constraint::Constraint;
+use crate::puzzle::*;
+use crate::verify::Answer;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A location in the source text of a puzzle file.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Span {
+    /// Line of the start of the span, starting from 1.
+    pub line: usize,
+
+    /// Column of the start of the span in characters, starting from 1.
+    pub column: usize,
+
+    /// Byte offset of the start of the span in the source.
+    pub start: usize,
+
+    /// Byte offset one past the end of the span in the source.
+    pub end: usize,
+}
+
+/// A possible fix for a diagnostic.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Suggestion {
+    /// What kind of name is being suggested, e.g. "label".
+    pub kind: &'static str,
+
+    /// The text which should replace the span of the diagnostic.
+    pub replacement: String,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "did you mean {} `{}`?", self.kind, self.replacement)
+    }
+}
+
+/// An error found while parsing a puzzle, along with its location.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub error: PuzzleError,
+    pub span: Span,
+    pub suggestion: Option<Suggestion>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.column, self.error)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single line of the source, without its line terminator.
+struct Line<'a> {
+    text: &'a str,
+
+    /// Byte offset of the line in the source.
+    start: usize,
+
+    /// Line number, starting from 1.
+    number: usize,
+}
+
+/// A single name in a line, with quotes and escapes removed.
+#[derive(Debug, Clone)]
+struct Field<'a> {
+    text: Cow<'a, str>,
+
+    /// Location of the name in the source, including any quotes.
+    span: Span,
+}
+
+/// An error which prevents a line from being split into fields.
+type LineError = (Span, PuzzleError);
+
+impl<'a> Line<'a> {
+    /// Return the span between byte offsets `start` and `end` of the line.
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            line: self.number,
+            column: self.text[..start].chars().count() + 1,
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// Return the span of `text`, which must be a subslice of this line.
+    fn span_of(&self, text: &str) -> Span {
+        let offset = text.as_ptr() as usize - self.text.as_ptr() as usize;
+        debug_assert!(offset + text.len() <= self.text.len());
+        self.span(offset, offset + text.len())
+    }
+
+    /// The whole line, without surrounding whitespace.
+    fn trimmed(&self) -> &'a str {
+        self.text.trim()
+    }
+
+    /// The text of the line if it is a comment, without the leading `#`.
+    fn comment(&self) -> Option<&'a str> {
+        comment(self.text)
+    }
+
+    /// Parse the whole line as a single name.
+    fn name(&self) -> Result<Field<'a>, LineError> {
+        self.field_at(0, false).map(|(field, _)| field)
+    }
+
+    /// Split the line into comma-separated fields.
+    fn fields(&self) -> Result<Vec<Field<'a>>, LineError> {
+        let mut fields = vec![];
+        let mut pos = 0;
+        loop {
+            let (field, end) = self.field_at(pos, true)?;
+            fields.push(field);
+            if end == self.text.len() {
+                return Ok(fields);
+            }
+            // Skip the comma.
+            pos = end + 1;
+        }
+    }
+
+    /// Parse a field starting at byte offset `pos`, returning it along with
+    /// the offset at which it ends.
+    /// If `commas` is set, the field ends at the next comma outside quotes,
+    /// otherwise it ends at the end of the line.
+    fn field_at(&self, pos: usize, commas: bool) -> Result<(Field<'a>, usize), LineError> {
+        match self.scan_field(pos, commas) {
+            (field, end, None) => Ok((field, end)),
+            (_, _, Some(error)) => Err(error),
+        }
+    }
+
+    /// Like `field_at`, but keep going past any error, returning the first
+    /// one along with the field as best it could be read.
+    fn scan_field(&self, pos: usize, commas: bool) -> (Field<'a>, usize, Option<LineError>) {
+        let end_from = |from: usize| match self.text[from..].find(',') {
+            Some(i) if commas => from + i,
+            _ => self.text.len(),
+        };
+
+        let start = pos + (self.text[pos..].len() - self.text[pos..].trim_start().len());
+        if !self.text[start..].starts_with('"') {
+            let end = end_from(pos);
+            let text = self.text[pos..end].trim();
+            let field = Field {
+                text: Cow::Borrowed(text),
+                span: self.span_of(text),
+            };
+            return (field, end, None);
+        }
+
+        let unterminated = (
+            self.span(start, self.text.len()),
+            PuzzleError::UnterminatedQuote,
+        );
+        let mut error = None;
+        let mut text = String::new();
+        let mut chars = self.text[start + 1..].char_indices();
+        let close = loop {
+            match chars.next() {
+                None => {
+                    error.get_or_insert(unterminated);
+                    break self.text.len();
+                }
+                Some((i, '"')) => break start + 1 + i + 1,
+                Some((i, '\\')) => match chars.next() {
+                    None => {
+                        error.get_or_insert(unterminated);
+                        break self.text.len();
+                    }
+                    Some((j, c)) => {
+                        if c != '"' && c != '\\' {
+                            let span = self.span(start + 1 + i, start + 1 + j + c.len_utf8());
+                            error.get_or_insert((span, PuzzleError::InvalidEscape { escape: c }));
+                        }
+                        text.push(c);
+                    }
+                },
+                Some((_, c)) => text.push(c),
+            }
+        };
+
+        let end = end_from(close);
+        let trailing = self.text[close..end].trim();
+        if !trailing.is_empty() {
+            error.get_or_insert((
+                self.span_of(trailing),
+                PuzzleError::UnexpectedText {
+                    text: trailing.to_string(),
+                },
+            ));
+        }
+        let field = Field {
+            text: Cow::Owned(text),
+            span: self.span(start, close),
+        };
+        (field, end, error)
+    }
+}
+
+/// The text of `line` of a puzzle file if it is a comment, without the
+/// leading `#`.
+pub fn comment(line: &str) -> Option<&str> {
+    line.trim().strip_prefix('#').map(str::trim)
+}
+
+/// A name written in a line of a puzzle file, with quotes and escapes
+/// removed, as split by `tokenize_line`.
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub text: String,
+
+    /// Byte offset in the source just after the comma before the name, or of
+    /// the start of the line for the first name.
+    pub from: usize,
+
+    /// Location of the name in the source, including any quotes.
+    pub span: Span,
+}
+
+/// Split `text`, line `number` of a puzzle file starting at byte offset
+/// `start`, into names the same way as the parser does, but without
+/// stopping at errors, which are reported by its diagnostics instead.
+/// If `commas` is set, names are separated by commas outside quotes,
+/// otherwise the whole line is one name.
+pub fn tokenize_line(text: &str, start: usize, number: usize, commas: bool) -> Vec<Token> {
+    let line = Line {
+        text: text.strip_suffix('\r').unwrap_or(text),
+        start,
+        number,
+    };
+    let mut tokens = vec![];
+    let mut pos = 0;
+    loop {
+        let (field, end, _) = line.scan_field(pos, commas);
+        tokens.push(Token {
+            text: field.text.into_owned(),
+            from: start + pos,
+            span: field.span,
+        });
+        if end == line.text.len() {
+            return tokens;
+        }
+        pos = end + 1;
+    }
+}
+
+/// Quote `name` if it can't be written as-is in a puzzle file.
+/// If `in_list` is set, the name is a field in a comma-separated clue,
+/// otherwise it takes up a whole line.
+pub fn quote(name: &str, in_list: bool) -> Cow<'_, str> {
+    let needs_quotes = name.is_empty()
+        || name.trim() != name
+        || name.starts_with('"')
+        || name.starts_with('#')
+        || (in_list && name.contains(','))
+        || (!in_list && (name == "[Categories]" || name == "[Clues]"));
+    if needs_quotes {
+        let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+        Cow::Owned(format!("\"{}\"", escaped))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// A category as written in the source, before it is added to the puzzle.
+struct CategoryDef<'a> {
+    name: Field<'a>,
+
+    /// Comments before the name of the category.
+    comments: Vec<String>,
+
+    /// Each label along with the comments before it.
+    labels: Vec<(Field<'a>, Vec<String>)>,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    puzzle: Puzzle,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&mut self, span: Span, error: PuzzleError) {
+        self.diagnostics.push(Diagnostic {
+            error,
+            span,
+            suggestion: None,
+        });
+    }
+
+    fn error_with_suggestion(
+        &mut self,
+        span: Span,
+        error: PuzzleError,
+        suggestion: Option<Suggestion>,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            error,
+            span,
+            suggestion,
+        });
+    }
+
+    /// Span pointing at the very end of the source.
+    fn end_span(&self) -> Span {
+        end_span(self.source)
+    }
+
+    fn parse(&mut self) {
+        let mut lines = lines(self.source);
+
+        // Anything before the [Categories] marker other than comments is ignored.
+        let mut comments = vec![];
+        loop {
+            match lines.next() {
+                Some(line) if line.trimmed() == "[Categories]" => break,
+                Some(line) => comments.extend(line.comment().map(String::from)),
+                None => {
+                    let span = Span {
+                        line: 1,
+                        column: 1,
+                        start: 0,
+                        end: 0,
+                    };
+                    self.error(span, PuzzleError::MissingCategories);
+                    return;
+                }
+            }
+        }
+        self.add_comments(CommentAnchor::Categories, &mut comments);
+
+        let mut categories: Vec<CategoryDef> = vec![];
+        let mut clues_marker = None;
+        let mut current: Option<CategoryDef> = None;
+        for line in &mut lines {
+            let text = line.trimmed();
+            if text == "[Clues]" {
+                clues_marker = Some(line.span_of(text));
+                break;
+            }
+            if let Some(comment) = line.comment() {
+                comments.push(comment.to_string());
+                continue;
+            }
+            // Categories are separated by any number of blank lines.
+            if text.is_empty() {
+                categories.extend(current.take());
+                continue;
+            }
+            let field = match line.name() {
+                Ok(field) => field,
+                Err((span, error)) => {
+                    self.error(span, error);
+                    continue;
+                }
+            };
+            let comments = core::mem::take(&mut comments);
+            if let Some(category) = &mut current {
+                category.labels.push((field, comments));
+            } else {
+                current = Some(CategoryDef {
+                    name: field,
+                    comments,
+                    labels: vec![],
+                });
+            }
+        }
+        categories.extend(current.take());
+
+        for category in &categories {
+            self.add_category(category);
+        }
+
+        let clues_marker = match clues_marker {
+            Some(span) => span,
+            None => {
+                let span = self.end_span();
+                self.error(span, PuzzleError::MissingClues);
+                return;
+            }
+        };
+        if self.puzzle.num_categories() == 0 {
+            if categories.is_empty() {
+                self.error(clues_marker, PuzzleError::MissingCategories);
+            }
+            return;
+        }
+        self.add_comments(CommentAnchor::Clues, &mut comments);
+
+        let mut solution = false;
+        for line in &mut lines {
+            if line.trimmed() == "[Solution]" {
+                solution = true;
+                break;
+            }
+            if let Some(comment) = line.comment() {
+                comments.push(comment.to_string());
+            } else if !line.trimmed().is_empty() {
+                if self.parse_clue(&line) {
+                    let clue = self.puzzle.constraints().len() - 1;
+                    self.add_comments(CommentAnchor::Clue(clue), &mut comments);
+                } else {
+                    comments.clear();
+                }
+            }
+        }
+        self.add_comments(CommentAnchor::End, &mut comments);
+
+        if solution {
+            let end = self.end_span();
+            let mut comments = vec![];
+            let answer = parse_answer(
+                &self.puzzle,
+                lines,
+                end,
+                &mut self.diagnostics,
+                &mut comments,
+            );
+            self.puzzle.set_solution(answer);
+            for (anchor, comment) in comments {
+                self.puzzle.add_comment(anchor, comment);
+            }
+        }
+    }
+
+    /// Add all of `comments` to the puzzle at `anchor`, leaving it empty.
+    fn add_comments(&mut self, anchor: CommentAnchor, comments: &mut Vec<String>) {
+        for comment in comments.drain(..) {
+            self.puzzle.add_comment(anchor, comment);
+        }
+    }
+
+    fn add_category(&mut self, category: &CategoryDef) {
+        let name = &*category.name.text;
+        let labels = category
+            .labels
+            .iter()
+            .map(|(l, _)| &*l.text)
+            .collect::<Vec<_>>();
+        let added = match self.puzzle.add_category(name, &labels) {
+            Ok(added) => added,
+            // Point at every repeated label rather than just the first.
+            Err(PuzzleError::InvalidLabelName { .. }) => {
+                for (i, (label, _)) in category.labels.iter().enumerate() {
+                    if labels[..i].contains(&&*label.text) {
+                        self.error(
+                            label.span,
+                            PuzzleError::InvalidLabelName {
+                                name: label.text.to_string(),
+                            },
+                        );
+                    }
+                }
+                return;
+            }
+            Err(error) => {
+                self.error(category.name.span, error);
+                return;
+            }
+        };
+        for comment in &category.comments {
+            self.puzzle
+                .add_comment(CommentAnchor::Category(added), comment.clone());
+        }
+        for (l, (_, comments)) in category.labels.iter().enumerate() {
+            for comment in comments {
+                self.puzzle
+                    .add_comment(CommentAnchor::Label(Label::new(added, l)), comment.clone());
+            }
+        }
+    }
+
+    /// Parse a line of the `[Clues]` section and add its constraint to the
+    /// puzzle. Returns whether the clue was valid.
+    fn parse_clue(&mut self, line: &Line) -> bool {
+        let fields = match line.fields() {
+            Ok(fields) => fields,
+            Err((span, error)) => {
+                self.error(span, error);
+                return false;
+            }
+        };
+        if fields.len() < 2 {
+            self.error(
+                line.span_of(line.trimmed()),
+                PuzzleError::InvalidClueCount {
+                    expected: 2,
+                    found: fields.len() - 1,
+                },
+            );
+            return false;
+        }
+        let (name, kind, args) = (&fields[0], &fields[1], &fields[2..]);
+
+        let clue_kind = match clue_kind(&kind.text) {
+            Some(clue_kind) => clue_kind,
+            None => {
+                let keywords = CLUE_KINDS.iter().map(|k| k.keyword);
+                let suggestion = closest(&kind.text, keywords).map(|s| Suggestion {
+                    kind: "clue kind",
+                    replacement: s.to_string(),
+                });
+                self.error_with_suggestion(
+                    kind.span,
+                    PuzzleError::InvalidClueName {
+                        name: kind.text.to_string(),
+                    },
+                    suggestion,
+                );
+                return false;
+            }
+        };
+        let params = clue_kind.params_for(args.len());
+
+        if args.len() != params.len() {
+            let span = match args.get(params.len()) {
+                Some(extra) => extra.span,
+                None => line.span_of(line.trimmed()),
+            };
+            self.error(
+                span,
+                PuzzleError::InvalidClueCount {
+                    expected: params.len(),
+                    found: args.len(),
+                },
+            );
+            return false;
+        }
+
+        let mut resolved = vec![];
+        for (param, arg) in params.iter().zip(args) {
+            if let Some(arg) = self.resolve(param, arg) {
+                resolved.push(arg);
+            }
+        }
+        if resolved.len() != args.len() {
+            return false;
+        }
+
+        let kind = (clue_kind.build)(&resolved);
+        self.puzzle.add_constraint(Constraint {
+            kind,
+            name: name.text.to_string(),
+        });
+        true
+    }
+
+    /// Resolve a single argument of a clue, reporting an error and returning
+    /// `None` if it isn't valid.
+    fn resolve(&mut self, param: &Param, arg: &Field) -> Option<Arg> {
+        let puzzle = &self.puzzle;
+        let result = match param.kind {
+            ParamKind::Label => find_label(puzzle, &arg.text)
+                .map(Arg::Label)
+                .map_err(|(e, suggestion)| (e, suggestion.map(|s| ("label", s)))),
+            ParamKind::Category => puzzle.category(&arg.text).map(Arg::Category).map_err(|e| {
+                let categories = puzzle.categories().map(|c| puzzle.lookup_category(c));
+                (
+                    e,
+                    closest(&arg.text, categories).map(|s| ("category", s.to_string())),
+                )
+            }),
+            ParamKind::Number => match arg.text.parse::<usize>() {
+                Ok(n) => param
+                    .check_number(n, puzzle.labels_per_category())
+                    .map(Arg::Number)
+                    .map_err(|e| (e, None)),
+                Err(_) => Err((
+                    PuzzleError::InvalidInteger {
+                        value: arg.text.to_string(),
+                    },
+                    None,
+                )),
+            },
+        };
+        match result {
+            Ok(arg) => Some(arg),
+            Err((error, suggestion)) => {
+                let suggestion =
+                    suggestion.map(|(kind, replacement)| Suggestion { kind, replacement });
+                self.error_with_suggestion(arg.span, error, suggestion);
+                None
+            }
+        }
+    }
+}
+
+/// Span pointing at the very end of `source`.
+fn end_span(source: &str) -> Span {
+    let last = source.rsplit('\n').next().unwrap_or("");
+    Span {
+        line: source.matches('\n').count() + 1,
+        column: last.chars().count() + 1,
+        start: source.len(),
+        end: source.len(),
+    }
+}
+
+/// Split `source` into lines.
+fn lines(source: &str) -> impl Iterator<Item = Line<'_>> {
+    source
+        .split('\n')
+        .scan(0, |start, text| {
+            let line_start = *start;
+            *start += text.len() + 1;
+            Some((line_start, text))
+        })
+        .enumerate()
+        .map(|(i, (start, text))| Line {
+            text: text.strip_suffix('\r').unwrap_or(text),
+            start,
+            number: i + 1,
+        })
+}
+
+/// Find the label of `puzzle` referred to by `name`, or return the error
+/// along with the closest label reference, if any.
+fn find_label(puzzle: &Puzzle, name: &str) -> Result<Label, (PuzzleError, Option<String>)> {
+    puzzle.label(name).map_err(|e| {
+        let references = puzzle
+            .labels()
+            .flat_map(|l| vec![puzzle.label_reference(l), puzzle.qualified_label(l)])
+            .collect::<Vec<_>>();
+        let suggestion = match e {
+            PuzzleError::AmbiguousLabel { .. } => None,
+            _ => closest(name, references.iter().map(String::as_str)),
+        };
+        (e, suggestion.map(String::from))
+    })
+}
+
+/// Parse the rows of an answer table from `lines`, each of which lists the
+/// labels of one entity separated by commas, in any order.
+/// Blank lines are skipped, and comments are added to `comments`, anchored
+/// to the entity of the next row. Errors are added to `diagnostics`,
+/// pointing at `end` if there are too few rows.
+fn parse_answer<'a>(
+    puzzle: &Puzzle,
+    lines: impl Iterator<Item = Line<'a>>,
+    end: Span,
+    diagnostics: &mut Vec<Diagnostic>,
+    comments: &mut Vec<(CommentAnchor, String)>,
+) -> Option<Answer> {
+    let mut error = |span, error, suggestion| {
+        diagnostics.push(Diagnostic {
+            error,
+            span,
+            suggestion,
+        })
+    };
+    let mut valid = true;
+    let mut rows = vec![];
+    let mut seen = BTreeSet::new();
+    let mut last = end;
+    let mut pending = vec![];
+    for line in lines {
+        if let Some(comment) = line.comment() {
+            pending.push(comment.to_string());
+            continue;
+        }
+        if line.trimmed().is_empty() {
+            continue;
+        }
+        last = line.span_of(line.trimmed());
+        let fields = match line.fields() {
+            Ok(fields) => fields,
+            Err((span, e)) => {
+                error(span, e, None);
+                valid = false;
+                continue;
+            }
+        };
+        let mut row = vec![None; puzzle.num_categories()];
+        let mut complete = true;
+        for field in &fields {
+            let label = match find_label(puzzle, &field.text) {
+                Ok(label) => label,
+                Err((e, suggestion)) => {
+                    let suggestion = suggestion.map(|replacement| Suggestion {
+                        kind: "label",
+                        replacement,
+                    });
+                    error(field.span, e, suggestion);
+                    complete = false;
+                    continue;
+                }
+            };
+            let category = puzzle.lookup_category(label.category).to_string();
+            if row[label.category.0].is_some() {
+                error(field.span, PuzzleError::RepeatedCategory { category }, None);
+            } else if !seen.insert(label) {
+                let label = puzzle.label_reference(label);
+                error(field.span, PuzzleError::ReusedLabel { label }, None);
+            } else {
+                row[label.category.0] = Some(label.label);
+                continue;
+            }
+            complete = false;
+        }
+        // Only report missing labels when the rest of the row made sense.
+        if complete {
+            for category in puzzle.categories().filter(|c| row[c.0].is_none()) {
+                let category = puzzle.lookup_category(category).to_string();
+                error(last, PuzzleError::MissingLabel { category }, None);
+                complete = false;
+            }
+        }
+        valid &= complete;
+        // The rows are written in the order of the labels of the first
+        // category, which is also the order of the entities.
+        match row[0] {
+            Some(entity) => comments.extend(
+                pending
+                    .drain(..)
+                    .map(|comment| (CommentAnchor::Entity(entity), comment)),
+            ),
+            None => pending.clear(),
+        }
+        rows.push(row);
+    }
+    comments.extend(
+        pending
+            .into_iter()
+            .map(|comment| (CommentAnchor::SolutionEnd, comment)),
+    );
+    if rows.len() != puzzle.labels_per_category() {
+        let e = PuzzleError::InvalidEntityCount {
+            expected: puzzle.labels_per_category(),
+            found: rows.len(),
+        };
+        error(last, e, None);
+        valid = false;
+    }
+    if !valid {
+        return None;
+    }
+    let rows = rows
+        .into_iter()
+        .map(|row| row.into_iter().map(Option::unwrap).collect())
+        .collect();
+    Some(Answer::from_rows(rows))
+}
+
+/// Parse an answer table for `puzzle`, returning the answer if it was valid
+/// along with every diagnostic that was found.
+pub fn parse_answer_table(puzzle: &Puzzle, source: &str) -> (Option<Answer>, Vec<Diagnostic>) {
+    let mut diagnostics = vec![];
+    let answer = parse_answer(
+        puzzle,
+        lines(source),
+        end_span(source),
+        &mut diagnostics,
+        &mut vec![],
+    );
+    (answer, diagnostics)
+}
+
+/// Edit distance between `a` and `b`, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect::<Vec<_>>();
+    let b = b.to_lowercase().chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// Find the candidate closest to `name`, if any is close enough to be a
+/// plausible typo.
+fn closest<'c>(name: &str, candidates: impl Iterator<Item = &'c str>) -> Option<&'c str> {
+    let threshold = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Parse a puzzle in the text format, returning the puzzle and every
+/// diagnostic that was found.
+/// The puzzle contains everything that could be parsed successfully,
+/// so it is only complete if there were no diagnostics.
+pub fn parse_text(source: &str) -> (Puzzle, Vec<Diagnostic>) {
+    let mut parser = Parser {
+        source,
+        puzzle: Puzzle::default(),
+        diagnostics: vec![],
+    };
+    parser.parse();
+    (parser.puzzle, parser.diagnostics)
+}
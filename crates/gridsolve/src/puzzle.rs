@@ -1,14 +1,26 @@
 use crate::constraint::Constraint;
-use anyhow::{Context, Result};
-use bimap::BiMap;
+use crate::format::PuzzleFormat;
+use crate::parse::{parse_text, quote, Diagnostic};
+use crate::share::{SharedPuzzle, SHARE_URL};
+use crate::verify::Answer;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bimap::BiBTreeMap;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
 use std::path::Path;
 
 /// A category index in the puzzle.
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Category(pub usize);
 
 /// A label in the puzzle in a specific category.
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Label {
     /// Category to which this label belongs.
     pub category: Category,
@@ -23,18 +35,67 @@ impl Label {
     }
 }
 
+/// Where a comment appears in the text format of a puzzle.
+/// Each comment is written on its own line, directly before the item it is
+/// anchored to.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum CommentAnchor {
+    /// Before the `[Categories]` marker.
+    Categories,
+
+    /// Before the name of a category.
+    Category(Category),
+
+    /// Before a label in the list of labels of its category.
+    Label(Label),
+
+    /// Before the `[Clues]` marker.
+    Clues,
+
+    /// Before the clue with the given index in `Puzzle::constraints`.
+    Clue(usize),
+
+    /// After the last clue.
+    End,
+
+    /// Before the row of the entity with the given index in the solution.
+    Entity(usize),
+
+    /// After the last row of the solution.
+    SolutionEnd,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PuzzleError {
     #[error("Invalid label name: {}", name)]
     InvalidLabelName { name: String },
+    #[error("Invalid category name: {}", name)]
+    InvalidCategoryName { name: String },
+    #[error("Category \"{}\" has no labels", category)]
+    EmptyCategory { category: String },
     #[error("Missing [Categories] marker")]
     MissingCategories,
     #[error("Missing [Clues] marker")]
     MissingClues,
     #[error("Invalid Clue kind: {0}", name)]
     InvalidClueName { name: String },
-    #[error("Invalid Clue: expected {} but found {}", expected, found)]
+    #[error("Invalid Clue: expected {} arguments but found {}", expected, found)]
     InvalidClueCount { expected: usize, found: usize },
+    #[error("Invalid argument {} of clue: expected a {}", index, expected)]
+    InvalidArgument {
+        index: usize,
+        expected: &'static str,
+    },
+    #[error("Invalid distance: {}", distance)]
+    InvalidDistance { distance: usize },
+    #[error("Invalid integer: {}", value)]
+    InvalidInteger { value: String },
+    #[error("Missing closing quote")]
+    UnterminatedQuote,
+    #[error("Invalid escape sequence: \\{}", escape)]
+    InvalidEscape { escape: char },
+    #[error("Unexpected text after closing quote: {}", text)]
+    UnexpectedText { text: String },
     #[error(
         "Invalid number of labels in category \"{}\", expected {} but found {}",
         category,
@@ -50,94 +111,158 @@ pub enum PuzzleError {
     CategoryNotFound { name: String },
     #[error("Label not found: {}", name)]
     LabelNotFound { name: String },
+    #[error(
+        "Ambiguous label \"{}\", qualify it with its category: {}",
+        name,
+        references.join(", ")
+    )]
+    AmbiguousLabel {
+        name: String,
+        references: Vec<String>,
+    },
+    #[error("Entity has more than one label in category \"{}\"", category)]
+    RepeatedCategory { category: String },
+    #[error("Entity has no label in category \"{}\"", category)]
+    MissingLabel { category: String },
+    #[error("Label {} belongs to more than one entity", label)]
+    ReusedLabel { label: String },
+    #[error(
+        "Invalid number of entities, expected {} but found {}",
+        expected,
+        found
+    )]
+    InvalidEntityCount { expected: usize, found: usize },
+    #[error("Unknown rule: {}", name)]
+    UnknownRule { name: String },
+    #[error("Labels {} and {} are in the same category", label1, label2)]
+    SameCategory { label1: String, label2: String },
+    #[error("Cell of {} and {} is set more than once", label1, label2)]
+    RepeatedCell { label1: String, label2: String },
+    #[error("Invalid argument \"{}\" of message {}", name, id)]
+    InvalidMessageArg { id: String, name: String },
+    #[error("Invalid step {}: {}", index, source)]
+    InvalidStep {
+        index: usize,
+        source: Box<PuzzleError>,
+    },
+    #[error("Invalid {} index: {}", kind, index)]
+    InvalidIndex { kind: &'static str, index: usize },
+    #[error("Not a link to a puzzle in the web app: {}", url)]
+    InvalidShareUrl { url: String },
+    #[error("The {:?} format needs the `std` feature", format)]
+    UnsupportedFormat { format: PuzzleFormat },
+    #[cfg(feature = "std")]
     #[error(transparent)]
     Io {
         #[from]
         source: std::io::Error,
     },
     #[error(transparent)]
-    InvalidInteger {
+    Json {
+        #[from]
+        source: serde_json::Error,
+    },
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Toml {
+        #[from]
+        source: toml::de::Error,
+    },
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    TomlWrite {
         #[from]
-        source: anyhow::Error,
+        source: toml::ser::Error,
     },
+    #[error("{}", join(.diagnostics, "\n"))]
+    Parse { diagnostics: Vec<Diagnostic> },
 }
 
 /// The actual puzzle to be solved.
 #[derive(Debug, Default)]
 pub struct Puzzle {
     /// Maps from the category index to the name of the category.
-    category_map: BiMap<Category, String>,
+    category_map: BiBTreeMap<Category, String>,
 
-    /// Maps from the label to the name of the label.
-    label_map: BiMap<Label, String>,
+    /// Maps from the label to its category and the name of the label.
+    /// Label names are only unique within their category.
+    label_map: BiBTreeMap<Label, (Category, String)>,
 
     /// All the constraints which arise from the clues in the puzzle.
     constraints: Vec<Constraint>,
+
+    /// Comments from the puzzle file, in the order they appeared.
+    comments: Vec<(CommentAnchor, String)>,
+
+    /// The expected answer, from the `[Solution]` section of the file.
+    solution: Option<Answer>,
 }
 
 impl Puzzle {
     /// Parse a puzzle file from `path` and return the resultant Puzzle
     /// if the file was a valid puzzle file.
+    /// The format of the file is determined by its extension.
+    #[cfg(feature = "std")]
     pub fn from_file(path: &Path) -> Result<Self, PuzzleError> {
+        Puzzle::from_file_as(path, PuzzleFormat::from_path(path))
+    }
+
+    /// Parse a puzzle file from `path` in the given `format`.
+    #[cfg(feature = "std")]
+    pub fn from_file_as(path: &Path, format: PuzzleFormat) -> Result<Self, PuzzleError> {
         let file = std::fs::read_to_string(path)?;
-        Puzzle::parse(&file)
+        Puzzle::parse_as(&file, format)
     }
 
-    /// Parse a puzzle file from `path` and return the resultant Puzzle
-    /// if the file was a valid puzzle file.
-    pub fn parse(string: &str) -> Result<Self, PuzzleError> {
-        let mut lines = string.trim().lines();
-        let mut puzzle = Self::default();
-        loop {
-            match lines.next() {
-                Some(line) => {
-                    if line.trim() == "[Categories]" {
-                        break;
-                    }
-                }
-                _ => {
-                    return Err(PuzzleError::MissingCategories);
-                }
-            };
+    /// Parse a puzzle from `string` in the given `format`.
+    pub fn parse_as(string: &str, format: PuzzleFormat) -> Result<Self, PuzzleError> {
+        match format {
+            PuzzleFormat::Text => Puzzle::parse(string),
+            PuzzleFormat::Json => Ok(serde_json::from_str(string)?),
+            #[cfg(feature = "std")]
+            PuzzleFormat::Toml => Ok(toml::from_str(string)?),
+            #[cfg(not(feature = "std"))]
+            PuzzleFormat::Toml => Err(PuzzleError::UnsupportedFormat { format }),
+            PuzzleFormat::Url => SharedPuzzle::from_url(string.trim())?.to_puzzle(),
         }
+    }
 
-        loop {
-            let category_name = match lines.next() {
-                Some(line) => {
-                    if line.trim() == "[Clues]" {
-                        break;
-                    }
-                    line
-                }
-                _ => {
-                    return Err(PuzzleError::MissingClues);
-                }
-            };
-            let mut labels = vec![];
-            loop {
-                match lines.next() {
-                    Some(line) => {
-                        if line.trim().is_empty() {
-                            break;
-                        }
-                        labels.push(line);
-                    }
-                    _ => {
-                        return Err(PuzzleError::MissingClues);
-                    }
-                };
-            }
-            puzzle.add_category(category_name, &labels)?;
+    /// Parse a puzzle in the text format from `string` and return the
+    /// resultant Puzzle if it was a valid puzzle file.
+    /// Otherwise, returns `PuzzleError::Parse` with every error that was found.
+    pub fn parse(string: &str) -> Result<Self, PuzzleError> {
+        let (puzzle, diagnostics) = parse_text(string);
+        if diagnostics.is_empty() {
+            Ok(puzzle)
+        } else {
+            Err(PuzzleError::Parse { diagnostics })
         }
+    }
 
-        for (line_number, line) in lines.enumerate() {
-            puzzle.add_constraint(
-                Constraint::from_str(&puzzle, line)
-                    .with_context(|| format!("in line {}", line_number))?,
-            );
-        }
+    /// Parse as much as possible of a puzzle in the text format from
+    /// `string`, returning the partial puzzle along with every diagnostic,
+    /// for tools such as editors which work on puzzles as they are written.
+    /// Unless no category could be parsed, in which case there are no
+    /// constraints, each line of the `[Clues]` section with no diagnostics
+    /// on it gives a constraint, in the same order.
+    pub fn parse_partial(string: &str) -> (Self, Vec<Diagnostic>) {
+        parse_text(string)
+    }
 
-        Ok(puzzle)
+    /// Write the puzzle in the given `format`.
+    /// The text format is written canonically, see the `Display` impl.
+    pub fn to_string_as(&self, format: PuzzleFormat) -> Result<String, PuzzleError> {
+        match format {
+            PuzzleFormat::Text => Ok(self.to_string()),
+            PuzzleFormat::Json => Ok(serde_json::to_string_pretty(self)? + "\n"),
+            // Going through a `toml::Value` writes the solution, which is a
+            // plain array, before the arrays of tables that TOML needs last.
+            #[cfg(feature = "std")]
+            PuzzleFormat::Toml => Ok(toml::to_string(&toml::Value::try_from(self)?)?),
+            #[cfg(not(feature = "std"))]
+            PuzzleFormat::Toml => Err(PuzzleError::UnsupportedFormat { format }),
+            PuzzleFormat::Url => Ok(SharedPuzzle::from(self).to_url(SHARE_URL) + "\n"),
+        }
     }
 
     pub fn add_category<S: AsRef<str>>(
@@ -145,6 +270,16 @@ impl Puzzle {
         cat_name: &str,
         label_names: &[S],
     ) -> Result<Category, PuzzleError> {
+        if label_names.is_empty() {
+            return Err(PuzzleError::EmptyCategory {
+                category: cat_name.to_string(),
+            });
+        }
+        if self.category(cat_name).is_ok() {
+            return Err(PuzzleError::InvalidCategoryName {
+                name: cat_name.to_string(),
+            });
+        }
         if !self.category_map.is_empty() && label_names.len() != self.labels_per_category() {
             return Err(PuzzleError::InvalidLabelCount {
                 category: cat_name.to_string(),
@@ -152,15 +287,19 @@ impl Puzzle {
                 found: label_names.len(),
             });
         }
-        let category = Category(self.category_map.len());
         for (i, name) in label_names.iter().enumerate() {
-            if self.label(name.as_ref()).is_ok() {
+            let name = name.as_ref();
+            if label_names[..i].iter().any(|other| other.as_ref() == name) {
                 return Err(PuzzleError::InvalidLabelName {
-                    name: name.as_ref().to_string(),
+                    name: name.to_string(),
                 });
             }
+        }
+        let category = Category(self.category_map.len());
+        for (i, name) in label_names.iter().enumerate() {
             let label = Label { category, label: i };
-            self.label_map.insert(label, name.as_ref().to_string());
+            self.label_map
+                .insert(label, (category, name.as_ref().to_string()));
         }
         self.category_map.insert(category, cat_name.to_string());
         Ok(category)
@@ -181,16 +320,66 @@ impl Puzzle {
     }
 
     pub fn lookup_label(&self, label: Label) -> &str {
-        self.label_map.get_by_left(&label).unwrap()
+        &self.label_map.get_by_left(&label).unwrap().1
     }
 
+    /// Find the label referred to by `name`, which is either the name of a
+    /// label or a `Category:Label` reference to a label in a specific category.
+    /// A bare name which is used in several categories is ambiguous.
     pub fn label(&self, name: &str) -> Result<Label, PuzzleError> {
-        let result = self.label_map.get_by_right(&name.to_string()).copied();
-        match &result {
-            None => Err(PuzzleError::LabelNotFound {
-                name: name.to_string(),
-            }),
-            &Some(label) => Ok(label),
+        let matches = self.labels_named(name).collect::<Vec<_>>();
+        match matches.as_slice() {
+            &[label] => return Ok(label),
+            [] => {}
+            _ => {
+                return Err(PuzzleError::AmbiguousLabel {
+                    name: name.to_string(),
+                    references: matches
+                        .iter()
+                        .map(|&label| self.qualified_label(label))
+                        .collect(),
+                })
+            }
+        }
+        for (i, _) in name.match_indices(':') {
+            let (category, label) = (name[..i].trim(), name[i + 1..].trim());
+            if let Ok(category) = self.category(category) {
+                let key = (category, label.to_string());
+                if let Some(&label) = self.label_map.get_by_right(&key) {
+                    return Ok(label);
+                }
+            }
+        }
+        Err(PuzzleError::LabelNotFound {
+            name: name.to_string(),
+        })
+    }
+
+    /// Iterate through every label whose name is exactly `name`.
+    fn labels_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Label> + 'a {
+        self.categories().filter_map(move |category| {
+            let key = (category, name.to_string());
+            self.label_map.get_by_right(&key).copied()
+        })
+    }
+
+    /// Return the `Category:Label` reference to `label`.
+    pub fn qualified_label(&self, label: Label) -> String {
+        format!(
+            "{}:{}",
+            self.lookup_category(label.category),
+            self.lookup_label(label)
+        )
+    }
+
+    /// Return the shortest unambiguous reference to `label`: its name, unless
+    /// that is shared with another category, in which case it is qualified.
+    pub fn label_reference(&self, label: Label) -> String {
+        let name = self.lookup_label(label);
+        if self.labels_named(name).count() == 1 {
+            name.to_string()
+        } else {
+            self.qualified_label(label)
         }
     }
 
@@ -198,6 +387,19 @@ impl Puzzle {
         self.constraints.push(constraint);
     }
 
+    /// Add a comment to be written before `anchor` in the text format.
+    pub fn add_comment<S: Into<String>>(&mut self, anchor: CommentAnchor, text: S) {
+        self.comments.push((anchor, text.into()));
+    }
+
+    /// Iterate through the comments anchored at `anchor`.
+    pub fn comments(&self, anchor: CommentAnchor) -> impl Iterator<Item = &str> {
+        self.comments
+            .iter()
+            .filter(move |(a, _)| *a == anchor)
+            .map(|(_, text)| text.as_str())
+    }
+
     pub fn num_categories(&self) -> usize {
         self.category_map.len()
     }
@@ -206,6 +408,14 @@ impl Puzzle {
         (0..self.num_categories()).map(Category)
     }
 
+    /// Iterate through every label in the puzzle.
+    pub fn labels(&self) -> impl Iterator<Item = Label> {
+        let labels_per_category = self.label_map.len() / self.num_categories().max(1);
+        self.categories().flat_map(move |category| {
+            (0..labels_per_category).map(move |l| Label::new(category, l))
+        })
+    }
+
     pub fn labels_per_category(&self) -> usize {
         debug_assert!(!self.category_map.is_empty());
         self.label_map.len() / self.num_categories()
@@ -214,4 +424,74 @@ impl Puzzle {
     pub fn constraints(&self) -> &[Constraint] {
         &self.constraints
     }
+
+    /// The answer which the puzzle declares to be its solution, if any.
+    pub fn solution(&self) -> Option<&Answer> {
+        self.solution.as_ref()
+    }
+
+    pub fn set_solution(&mut self, solution: Option<Answer>) {
+        self.solution = solution;
+    }
+}
+
+/// Writes the puzzle in the canonical text format:
+/// categories and labels in the order they were added, one blank line after
+/// each category, and one clue per line with no spaces around the commas.
+/// The solution, if any, follows the clues after a blank line, with one
+/// entity per line and its labels in the order of their categories.
+/// Names are only quoted where necessary, and comments are kept.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let comments = |f: &mut fmt::Formatter, anchor| -> fmt::Result {
+            for comment in self.comments(anchor) {
+                if comment.is_empty() {
+                    writeln!(f, "#")?;
+                } else {
+                    writeln!(f, "# {}", comment)?;
+                }
+            }
+            Ok(())
+        };
+
+        comments(f, CommentAnchor::Categories)?;
+        writeln!(f, "[Categories]")?;
+        for category in self.categories() {
+            comments(f, CommentAnchor::Category(category))?;
+            writeln!(f, "{}", quote(self.lookup_category(category), false))?;
+            for l in 0..self.labels_per_category() {
+                let label = Label::new(category, l);
+                comments(f, CommentAnchor::Label(label))?;
+                writeln!(f, "{}", quote(self.lookup_label(label), false))?;
+            }
+            writeln!(f)?;
+        }
+        comments(f, CommentAnchor::Clues)?;
+        writeln!(f, "[Clues]")?;
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            comments(f, CommentAnchor::Clue(i))?;
+            constraint.write_line(self, f)?;
+            writeln!(f)?;
+        }
+        comments(f, CommentAnchor::End)?;
+        if let Some(answer) = &self.solution {
+            writeln!(f, "\n[Solution]")?;
+            for (entity, row) in answer.table(self).lines().enumerate() {
+                comments(f, CommentAnchor::Entity(entity))?;
+                writeln!(f, "{}", row)?;
+            }
+            comments(f, CommentAnchor::SolutionEnd)?;
+        }
+        Ok(())
+    }
+}
+
+/// Join `items` as displayed with `sep` in between, like `itertools::join`
+/// which needs `std`.
+pub(crate) fn join<T: fmt::Display>(items: &[T], sep: &str) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(sep)
 }
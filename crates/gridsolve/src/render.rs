@@ -0,0 +1,256 @@
+use crate::puzzle::*;
+use crate::solver::{Cell, Grid};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::iter;
+
+/// Characters used to draw a grid.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Charset {
+    /// Box-drawing characters, with ✓ and ✗ marks.
+    #[default]
+    Unicode,
+    /// Plain ASCII, with `O` and `X` marks.
+    Ascii,
+}
+
+/// How the labels above the columns of a grid are written.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ColumnLabels {
+    /// Written vertically, one character per line.
+    #[default]
+    Rotated,
+    /// Written horizontally, cut down to the given number of characters.
+    Abbreviated(usize),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct GridStyle {
+    pub charset: Charset,
+    pub column_labels: ColumnLabels,
+
+    /// Cell to highlight by drawing brackets around it.
+    pub cursor: Option<(Label, Label)>,
+}
+
+struct Chars {
+    vertical: char,
+    horizontal: char,
+    cross: char,
+    yes: char,
+    no: char,
+}
+
+impl Charset {
+    fn chars(self) -> Chars {
+        match self {
+            Charset::Unicode => Chars {
+                vertical: '│',
+                horizontal: '─',
+                cross: '┼',
+                yes: '✓',
+                no: '✗',
+            },
+            Charset::Ascii => Chars {
+                vertical: '|',
+                horizontal: '-',
+                cross: '+',
+                yes: 'O',
+                no: 'X',
+            },
+        }
+    }
+}
+
+/// Return `text` cut down or padded with spaces to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    text.chars()
+        .take(width)
+        .chain(iter::repeat_n(' ', width.saturating_sub(len)))
+        .collect()
+}
+
+/// Return the categories of the rows and of the columns of the staircase
+/// layout used to solve puzzles on paper.
+/// The columns hold every category but the first. The rows hold the first
+/// category followed by the others in reverse order, and the `i`th row
+/// category only meets the first `columns.len() - i` column categories,
+/// which it hasn't met yet.
+pub fn staircase(puzzle: &Puzzle) -> (Vec<Category>, Vec<Category>) {
+    let num_categories = puzzle.num_categories();
+    if num_categories < 2 {
+        return (vec![], vec![]);
+    }
+    let rows = iter::once(0)
+        .chain((2..num_categories).rev())
+        .map(Category)
+        .collect();
+    let columns = (1..num_categories).map(Category).collect();
+    (rows, columns)
+}
+
+/// Render the grid in the staircase layout, see `staircase`.
+pub fn render_grid(grid: &Grid, style: GridStyle) -> String {
+    let puzzle = grid.puzzle;
+    let n = grid.labels_per_category;
+    let (rows, columns) = staircase(puzzle);
+    if columns.is_empty() {
+        return String::new();
+    }
+    let chars = style.charset.chars();
+
+    let cell_width = match style.column_labels {
+        ColumnLabels::Rotated => 3,
+        ColumnLabels::Abbreviated(chars) => chars.max(1) + 2,
+    };
+    let group_width = n * cell_width;
+    let labels = |c: Category| (0..n).map(move |l| Label::new(c, l));
+    let width = |text: &str| text.chars().count();
+    let category_width = rows
+        .iter()
+        .map(|&c| width(puzzle.lookup_category(c)))
+        .max()
+        .unwrap_or(0);
+    let label_width = rows
+        .iter()
+        .flat_map(|&c| labels(c))
+        .map(|l| width(puzzle.lookup_label(l)))
+        .max()
+        .unwrap_or(0);
+    let margin = " ".repeat(category_width + label_width + 4);
+
+    let mut out = String::new();
+
+    // Column headers.
+    out.push_str(&margin);
+    for &c in &columns {
+        write!(
+            out,
+            "{}{}",
+            chars.vertical,
+            fit(puzzle.lookup_category(c), group_width)
+        )
+        .unwrap();
+    }
+    out.push('\n');
+    let header_lines: Vec<Vec<String>> = match style.column_labels {
+        ColumnLabels::Rotated => {
+            let height = columns
+                .iter()
+                .flat_map(|&c| labels(c))
+                .map(|l| width(puzzle.lookup_label(l)))
+                .max()
+                .unwrap_or(0);
+            (0..height)
+                .map(|i| {
+                    columns
+                        .iter()
+                        .map(|&c| {
+                            labels(c)
+                                .map(|l| {
+                                    let ch = puzzle.lookup_label(l).chars().nth(i).unwrap_or(' ');
+                                    format!(" {} ", ch)
+                                })
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+        ColumnLabels::Abbreviated(len) => vec![columns
+            .iter()
+            .map(|&c| {
+                labels(c)
+                    .map(|l| format!(" {} ", fit(puzzle.lookup_label(l), len.max(1))))
+                    .collect()
+            })
+            .collect()],
+    };
+    for line in header_lines {
+        out.push_str(&margin);
+        for group in line {
+            write!(out, "{}{}", chars.vertical, group).unwrap();
+        }
+        out.push('\n');
+    }
+
+    let separator = |out: &mut String, groups: usize| {
+        let rule = |len| iter::repeat_n(chars.horizontal, len).collect::<String>();
+        write!(
+            out,
+            "{}{}{}",
+            rule(category_width + 1),
+            chars.cross,
+            rule(label_width + 2)
+        )
+        .unwrap();
+        for _ in 0..groups {
+            write!(out, "{}{}", chars.cross, rule(group_width)).unwrap();
+        }
+        out.push('\n');
+    };
+    separator(&mut out, columns.len());
+
+    // Each row category meets one fewer column category than the last.
+    for (i, &row) in rows.iter().enumerate() {
+        let groups = &columns[..columns.len() - i];
+        for x in labels(row) {
+            let name = if x.label == 0 {
+                puzzle.lookup_category(row)
+            } else {
+                ""
+            };
+            write!(
+                out,
+                "{} {} {} ",
+                fit(name, category_width),
+                chars.vertical,
+                fit(puzzle.lookup_label(x), label_width)
+            )
+            .unwrap();
+            for &c in groups {
+                out.push(chars.vertical);
+                for y in labels(c) {
+                    let mark = match grid.at(x, y) {
+                        Cell::Yes => chars.yes,
+                        Cell::No => chars.no,
+                        Cell::Empty => ' ',
+                    };
+                    let left = (cell_width - 1) / 2;
+                    let right = cell_width - 1 - left;
+                    let (open, close) = if style.cursor == Some((x, y)) {
+                        ('[', ']')
+                    } else {
+                        (' ', ' ')
+                    };
+                    write!(
+                        out,
+                        "{}{:l$}{}{:r$}{}",
+                        open,
+                        "",
+                        mark,
+                        "",
+                        close,
+                        l = left - 1,
+                        r = right - 1
+                    )
+                    .unwrap();
+                }
+            }
+            out.push('\n');
+        }
+        separator(&mut out, groups.len());
+    }
+
+    let mut result: String = out
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    result.push('\n');
+    result
+}
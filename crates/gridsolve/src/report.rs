@@ -0,0 +1,157 @@
+//! A stable JSON representation of a solution, for other programs to read.
+//!
+//! Everything is listed in the order of the puzzle, so the same solution is
+//! always written the same way.
+//! `SCHEMA_VERSION` is incremented whenever the representation changes in a
+//! way which could break readers.
+
+use crate::budget::Status;
+use crate::locale::Locale;
+use crate::message::Message;
+use crate::puzzle::*;
+use crate::solver::{Cell, Solution};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::Serialize;
+
+/// Version of the schema of `SolutionReport`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A solution with everything needed to display it, without the puzzle.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SolutionReport {
+    /// Version of the schema, currently 1.
+    pub version: u32,
+
+    /// Whether every label was matched, or why the solver stopped.
+    pub status: Status,
+
+    /// Every category of the puzzle, in order.
+    pub categories: Vec<CategoryReport>,
+
+    /// An entity for each label of the first category, in order.
+    pub entities: Vec<EntityReport>,
+
+    /// Every step made by the solver, in order.
+    pub steps: Vec<StepReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CategoryReport {
+    pub name: String,
+
+    /// Every label of the category, in order.
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EntityReport {
+    /// For each category, in order, the label of the entity, or `null` if it
+    /// wasn't found.
+    pub labels: Vec<Option<String>>,
+
+    /// For each category, in order, the labels which the entity may still
+    /// have.
+    /// This is only the label of the entity if it was found.
+    pub candidates: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StepReport {
+    /// Position of the step in the solution, starting from 1.
+    pub index: usize,
+
+    /// The labels of the cell which was set, the one of the lower category
+    /// first, written as in a clue.
+    pub label1: String,
+    pub label2: String,
+
+    /// Whether the labels were found to belong together.
+    pub yes: bool,
+
+    /// Name of the rule, or kind of the clue, which made the step.
+    pub rule: String,
+
+    /// Why the cell was set, if known.
+    pub message: Option<Message>,
+
+    /// The message rendered in the requested locale, or "" if there is none.
+    pub description: String,
+}
+
+impl SolutionReport {
+    /// Describe `solution`, rendering the messages of its steps in `locale`.
+    pub fn new(solution: &Solution, locale: Locale) -> SolutionReport {
+        let puzzle = solution.puzzle;
+        let grid = solution.grid(solution.steps.len());
+        let labels = |c: Category| (0..puzzle.labels_per_category()).map(move |l| Label::new(c, l));
+        let categories = puzzle
+            .categories()
+            .map(|c| CategoryReport {
+                name: puzzle.lookup_category(c).to_string(),
+                labels: labels(c)
+                    .map(|l| puzzle.lookup_label(l).to_string())
+                    .collect(),
+            })
+            .collect();
+        let entities = labels(Category(0))
+            .map(|primary| {
+                let candidates: Vec<Vec<String>> = puzzle
+                    .categories()
+                    .map(|c| {
+                        labels(c)
+                            .filter(|&l| {
+                                if c == primary.category {
+                                    l == primary
+                                } else {
+                                    *grid.at(primary, l) != Cell::No
+                                }
+                            })
+                            .map(|l| puzzle.lookup_label(l).to_string())
+                            .collect()
+                    })
+                    .collect();
+                let labels = puzzle
+                    .categories()
+                    .map(|c| {
+                        labels(c)
+                            .find(|&l| l == primary || *grid.at(primary, l) == Cell::Yes)
+                            .map(|l| puzzle.lookup_label(l).to_string())
+                    })
+                    .collect();
+                EntityReport { labels, candidates }
+            })
+            .collect();
+        let steps = solution
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| StepReport {
+                index: i + 1,
+                label1: puzzle.label_reference(step.cell.0),
+                label2: puzzle.label_reference(step.cell.1),
+                yes: step.yes,
+                rule: step.rule.to_string(),
+                message: step.message.clone(),
+                description: locale.describe(step, puzzle),
+            })
+            .collect();
+        SolutionReport {
+            version: SCHEMA_VERSION,
+            status: solution.status,
+            categories,
+            entities,
+            steps,
+        }
+    }
+}
+
+/// Return the JSON Schema of `SolutionReport`.
+#[cfg(feature = "schema")]
+pub fn solution_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(SolutionReport)
+}
@@ -1,18 +1,39 @@
+use crate::message::{Message, MessageId};
 use crate::puzzle::*;
 use crate::solver::*;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
 
 pub trait Rule {
+    /// Name identifying the rule in the steps it makes.
+    fn name(&self) -> &'static str;
+
     /// Return whether the application altered the grid at all.
     fn apply<'p>(&self, grid: &mut Grid<'p>, puzzle: &'p Puzzle) -> Option<bool>;
 }
 
+/// Every deduction rule, in the order in which they are applied.
+pub(crate) fn rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(ElimOthers {}),
+        Box::new(OnlyEmpty {}),
+        Box::new(Transitivity {}),
+        Box::new(NoByProxy {}),
+    ]
+}
+
 /// Eliminate the other cells in the row/column of a Yes cell.
 /// If we have labels (x1,x2,x3) and (y1,y2,y3),
 /// then if (x1,y1) == Yes, then all other x,y pairings must be No.
 pub struct ElimOthers {}
 
 impl Rule for ElimOthers {
-    fn apply<'p>(&self, grid: &mut Grid<'p>, puzzle: &'p Puzzle) -> Option<bool> {
+    fn name(&self) -> &'static str {
+        "elim-others"
+    }
+
+    fn apply<'p>(&self, grid: &mut Grid<'p>, _puzzle: &'p Puzzle) -> Option<bool> {
         let mut changed = false;
         for (l1, l2) in grid.cells() {
             if *grid.at(l1, l2) == Cell::Yes {
@@ -25,15 +46,10 @@ impl Rule for ElimOthers {
                         label: l,
                     };
                     changed |= grid.set_with_callback(l1, l3, Cell::No, || {
-                        format!(
-                            "    {} ({}) is already set to {} ({}), eliminating {} ({})\n",
-                            puzzle.lookup_label(l1),
-                            puzzle.lookup_category(l1.category),
-                            puzzle.lookup_label(l2),
-                            puzzle.lookup_category(l2.category),
-                            puzzle.lookup_label(l3),
-                            puzzle.lookup_category(l3.category),
-                        )
+                        Message::new(MessageId::AlreadySet)
+                            .label("x", l1)
+                            .label("y", l2)
+                            .label("z", l3)
                     })?;
                 }
                 for l in 0..grid.labels_per_category {
@@ -45,15 +61,10 @@ impl Rule for ElimOthers {
                         label: l,
                     };
                     changed |= grid.set_with_callback(l3, l2, Cell::No, || {
-                        format!(
-                            "    {} ({}) is already set to {} ({}), eliminating {} ({})\n",
-                            puzzle.lookup_label(l2),
-                            puzzle.lookup_category(l2.category),
-                            puzzle.lookup_label(l1),
-                            puzzle.lookup_category(l1.category),
-                            puzzle.lookup_label(l3),
-                            puzzle.lookup_category(l3.category),
-                        )
+                        Message::new(MessageId::AlreadySet)
+                            .label("x", l2)
+                            .label("y", l1)
+                            .label("z", l3)
                     })?;
                 }
             }
@@ -67,7 +78,11 @@ impl Rule for ElimOthers {
 pub struct OnlyEmpty {}
 
 impl Rule for OnlyEmpty {
-    fn apply<'p>(&self, grid: &mut Grid<'p>, puzzle: &'p Puzzle) -> Option<bool> {
+    fn name(&self) -> &'static str {
+        "only-empty"
+    }
+
+    fn apply<'p>(&self, grid: &mut Grid<'p>, _puzzle: &'p Puzzle) -> Option<bool> {
         let mut changed = false;
         for (l1, l2) in grid.cells() {
             if *grid.at(l1, l2) == Cell::Empty {
@@ -88,13 +103,9 @@ impl Rule for OnlyEmpty {
 
                 if only {
                     changed |= grid.set_with_callback(l1, l2, Cell::Yes, || {
-                        format!(
-                            "    {} ({}) is the only possibility for {} ({})\n",
-                            puzzle.lookup_label(l2),
-                            puzzle.lookup_category(l2.category),
-                            puzzle.lookup_label(l1),
-                            puzzle.lookup_category(l1.category),
-                        )
+                        Message::new(MessageId::OnlyPossibility)
+                            .label("x", l2)
+                            .label("y", l1)
                     })?;
                     continue;
                 }
@@ -115,15 +126,10 @@ impl Rule for OnlyEmpty {
                 }
 
                 if only {
-                    changed |= grid.set(l1, l2, Cell::Yes)?;
-                    grid.set_with_callback(l1, l2, Cell::Yes, || {
-                        format!(
-                            "    {} ({}) is the only possibility for {} ({})\n",
-                            puzzle.lookup_label(l1),
-                            puzzle.lookup_category(l1.category),
-                            puzzle.lookup_label(l2),
-                            puzzle.lookup_category(l2.category),
-                        )
+                    changed |= grid.set_with_callback(l1, l2, Cell::Yes, || {
+                        Message::new(MessageId::OnlyPossibility)
+                            .label("x", l1)
+                            .label("y", l2)
                     })?;
                     continue;
                 }
@@ -133,47 +139,182 @@ impl Rule for OnlyEmpty {
     }
 }
 
+/// Where a `Scan` reads the grid from and sends what it deduces to.
+trait Deduce<'p> {
+    fn grid(&self) -> &Grid<'p>;
+
+    /// Deduce that `(x, y)` is `val`.
+    /// Returns `None` if a contradiction was found.
+    fn deduce<CB: FnOnce() -> Message>(
+        &mut self,
+        x: Label,
+        y: Label,
+        val: Cell,
+        message: CB,
+    ) -> Option<()>;
+}
+
+/// Makes each deduction in the grid as soon as it is found.
+struct Apply<'a, 'p> {
+    grid: &'a mut Grid<'p>,
+    changed: bool,
+}
+
+impl<'a, 'p> Deduce<'p> for Apply<'a, 'p> {
+    fn grid(&self) -> &Grid<'p> {
+        self.grid
+    }
+
+    fn deduce<CB: FnOnce() -> Message>(
+        &mut self,
+        x: Label,
+        y: Label,
+        val: Cell,
+        message: CB,
+    ) -> Option<()> {
+        self.changed |= self.grid.set_with_callback(x, y, val, message)?;
+        Some(())
+    }
+}
+
+/// A cell which a rule found it can set, and why.
+#[cfg(feature = "parallel")]
+struct Deduction {
+    x: Label,
+    y: Label,
+    val: Cell,
+    message: Message,
+}
+
+/// Collects the deductions made from a grid which can't be changed yet.
+#[cfg(feature = "parallel")]
+struct Collect<'a, 'p> {
+    grid: &'a Grid<'p>,
+    found: Vec<Deduction>,
+}
+
+#[cfg(feature = "parallel")]
+impl<'a, 'p> Deduce<'p> for Collect<'a, 'p> {
+    fn grid(&self) -> &Grid<'p> {
+        self.grid
+    }
+
+    /// Keep the deduction unless the cell already is `val`.
+    fn deduce<CB: FnOnce() -> Message>(
+        &mut self,
+        x: Label,
+        y: Label,
+        val: Cell,
+        message: CB,
+    ) -> Option<()> {
+        if *self.grid.at(x, y) != val {
+            self.found.push(Deduction {
+                x,
+                y,
+                val,
+                message: message(),
+            });
+        }
+        Some(())
+    }
+}
+
+/// A rule which works through the grid one cell at a time.
+trait Scan {
+    /// Make what can be deduced from the cell `(x, y)`.
+    fn scan<'p, D: Deduce<'p>>(&self, deduce: &mut D, x: Label, y: Label) -> Option<()>;
+}
+
+/// Scan every cell in turn, making each deduction as soon as it is found.
+#[cfg(not(feature = "parallel"))]
+fn apply_scan<S: Scan + Sync>(rule: &S, grid: &mut Grid) -> Option<bool> {
+    let mut apply = Apply {
+        grid,
+        changed: false,
+    };
+    for (x, y) in apply.grid.cells() {
+        rule.scan(&mut apply, x, y)?;
+    }
+    Some(apply.changed)
+}
+
+/// Scan partitions of the cells on several threads, all reading the grid as
+/// it was before the scan, then make the deductions in the order of the
+/// cells.
+/// The steps are the same on every run, whatever the number of threads, but
+/// not always the same as those of the sequential scan: a deduction which
+/// that makes from a cell set earlier in the same scan is left to a later
+/// pass here, by which time another rule or clue may have made it instead,
+/// with its own explanation.
+#[cfg(feature = "parallel")]
+fn apply_scan<S: Scan + Sync>(rule: &S, grid: &mut Grid) -> Option<bool> {
+    use rayon::prelude::*;
+
+    let cells = grid.cells().collect::<Vec<_>>();
+    let partition = cells.len().div_ceil(rayon::current_num_threads()).max(1);
+    let snapshot = &*grid;
+    let found = cells
+        .par_chunks(partition)
+        .map(|cells| {
+            let mut collect = Collect {
+                grid: snapshot,
+                found: vec![],
+            };
+            for &(x, y) in cells {
+                // Collecting never fails: contradictions are found when the
+                // deductions are made below.
+                let _ = rule.scan(&mut collect, x, y);
+            }
+            collect.found
+        })
+        .collect::<Vec<_>>();
+    let mut apply = Apply {
+        grid,
+        changed: false,
+    };
+    for d in found.into_iter().flatten() {
+        apply.deduce(d.x, d.y, d.val, || d.message)?;
+    }
+    Some(apply.changed)
+}
+
 /// If (x,y) == Yes and (y,z) == Yes, then (x,z) == Yes.
 /// If (x,y) == Yes and (x,z) == Yes, then (y,z) == Yes.
 pub struct Transitivity {}
 
 impl Rule for Transitivity {
-    fn apply<'p>(&self, grid: &mut Grid<'p>, puzzle: &'p Puzzle) -> Option<bool> {
-        let mut changed = false;
-        for (x, y) in grid.cells() {
-            let (cx, cy) = (x.category, y.category);
-            if *grid.at(x, y) == Cell::Yes {
-                for z in grid.labels() {
-                    if *grid.at(x, z) == Cell::Yes {
-                        changed |= grid.set_with_callback(y, z, Cell::Yes, || {
-                            format!(
-                                "    {} ({}) and {} ({}) share {} ({})\n",
-                                puzzle.lookup_label(y),
-                                puzzle.lookup_category(cy),
-                                puzzle.lookup_label(z),
-                                puzzle.lookup_category(z.category),
-                                puzzle.lookup_label(x),
-                                puzzle.lookup_category(cx),
-                            )
-                        })?;
-                    }
-                    if *grid.at(y, z) == Cell::Yes {
-                        changed |= grid.set_with_callback(x, z, Cell::Yes, || {
-                            format!(
-                                "    {} ({}) and {} ({}) share {} ({})\n",
-                                puzzle.lookup_label(x),
-                                puzzle.lookup_category(cx),
-                                puzzle.lookup_label(z),
-                                puzzle.lookup_category(z.category),
-                                puzzle.lookup_label(y),
-                                puzzle.lookup_category(cy),
-                            )
-                        })?;
-                    }
+    fn name(&self) -> &'static str {
+        "transitivity"
+    }
+
+    fn apply<'p>(&self, grid: &mut Grid<'p>, _puzzle: &'p Puzzle) -> Option<bool> {
+        apply_scan(self, grid)
+    }
+}
+
+impl Scan for Transitivity {
+    fn scan<'p, D: Deduce<'p>>(&self, deduce: &mut D, x: Label, y: Label) -> Option<()> {
+        if *deduce.grid().at(x, y) == Cell::Yes {
+            for z in deduce.grid().labels() {
+                if *deduce.grid().at(x, z) == Cell::Yes {
+                    deduce.deduce(y, z, Cell::Yes, || {
+                        Message::new(MessageId::Share)
+                            .label("x", y)
+                            .label("y", z)
+                            .label("z", x)
+                    })?;
+                }
+                if *deduce.grid().at(y, z) == Cell::Yes {
+                    deduce.deduce(x, z, Cell::Yes, || {
+                        Message::new(MessageId::Share)
+                            .label("x", x)
+                            .label("y", z)
+                            .label("z", y)
+                    })?;
                 }
             }
         }
-        Some(changed)
+        Some(())
     }
 }
 
@@ -186,46 +327,50 @@ impl Rule for Transitivity {
 pub struct NoByProxy {}
 
 impl Rule for NoByProxy {
-    fn apply<'p>(&self, grid: &mut Grid<'p>, puzzle: &'p Puzzle) -> Option<bool> {
-        let mut changed = false;
-        for (x, y) in grid.cells() {
-            let (cx, cy) = (x.category, y.category);
-            if cx == cy {
+    fn name(&self) -> &'static str {
+        "no-by-proxy"
+    }
+
+    fn apply<'p>(&self, grid: &mut Grid<'p>, _puzzle: &'p Puzzle) -> Option<bool> {
+        apply_scan(self, grid)
+    }
+}
+
+impl Scan for NoByProxy {
+    fn scan<'p, D: Deduce<'p>>(&self, deduce: &mut D, x: Label, y: Label) -> Option<()> {
+        let (cx, cy) = (x.category, y.category);
+        if cx == cy {
+            return Some(());
+        }
+        for cz in deduce.grid().categories() {
+            if cz == cx || cz == cy {
                 continue;
             }
-            for cz in grid.categories() {
-                if cz == cx || cz == cy {
-                    continue;
-                }
-                // If for every z in cz, either (x,z) == No or (y,z) == No,
-                // then there is no path to (x,y) == Yes, because they cannot
-                // be reconciled.
-                let mut has_path = false;
-                for i in 0..grid.labels_per_category {
-                    let z = Label::new(cz, i);
-                    if *grid.at(x, z) != Cell::No && *grid.at(y, z) != Cell::No {
-                        // (x,y) == Yes is reconcilable in category cz.
-                        has_path = true;
-                        // Stop iteration because we can never break the path now.
-                        break;
-                    }
-                }
-                if !has_path {
-                    // No path in one category, no point trying the rest.
-                    changed |= grid.set_with_callback(x, y, Cell::No, || {
-                        format!(
-                            "    {} ({}) is irreconcilable with {} ({}): cannot share ({})\n",
-                            puzzle.lookup_label(x),
-                            puzzle.lookup_category(cx),
-                            puzzle.lookup_label(y),
-                            puzzle.lookup_category(cy),
-                            puzzle.lookup_category(cz),
-                        )
-                    })?;
+            // If for every z in cz, either (x,z) == No or (y,z) == No,
+            // then there is no path to (x,y) == Yes, because they cannot
+            // be reconciled.
+            let grid = deduce.grid();
+            let mut has_path = false;
+            for i in 0..grid.labels_per_category {
+                let z = Label::new(cz, i);
+                if *grid.at(x, z) != Cell::No && *grid.at(y, z) != Cell::No {
+                    // (x,y) == Yes is reconcilable in category cz.
+                    has_path = true;
+                    // Stop iteration because we can never break the path now.
                     break;
                 }
             }
+            if !has_path {
+                // No path in one category, no point trying the rest.
+                deduce.deduce(x, y, Cell::No, || {
+                    Message::new(MessageId::Irreconcilable)
+                        .label("x", x)
+                        .label("y", y)
+                        .category("category", cz)
+                })?;
+                break;
+            }
         }
-        Some(changed)
+        Some(())
     }
 }
@@ -0,0 +1,240 @@
+//! The puzzle model of the web app, which keeps the puzzle being edited as
+//! JSON in the hash of its URL, so that links to puzzles can be shared.
+//!
+//! Labels are listed for every category in turn and referred to by their
+//! index in that list, and categories by their index, rather than by name.
+
+use crate::clue_kind::{clue_kind, Arg, ParamKind};
+use crate::constraint::Constraint;
+use crate::puzzle::*;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+/// Address of the web app, to which share links point by default.
+pub const SHARE_URL: &str = "https://avp.github.io/gridsolve/";
+
+/// A puzzle as stored by the web app.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedPuzzle {
+    pub categories: Vec<String>,
+
+    /// The labels of each category in turn, `num_labels` of them each.
+    pub labels: Vec<String>,
+
+    #[serde(deserialize_with = "index")]
+    pub num_labels: usize,
+
+    #[serde(default)]
+    pub clues: Vec<SharedClue>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SharedClue {
+    pub name: String,
+
+    /// Keyword of the kind of clue.
+    pub kind: String,
+
+    /// The arguments of the clue: the index in `SharedPuzzle::labels` of a
+    /// label, the index of a category, or a number.
+    #[serde(deserialize_with = "indices")]
+    pub params: Vec<usize>,
+}
+
+/// A number in the model. Older links have numbers written as strings, as
+/// they were read from the form.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Index {
+    Number(usize),
+    Text(String),
+}
+
+impl Index {
+    fn value<E: de::Error>(self) -> Result<usize, E> {
+        match self {
+            Index::Number(n) => Ok(n),
+            Index::Text(text) => text
+                .parse()
+                .map_err(|_| E::custom(format!("invalid number: {}", text))),
+        }
+    }
+}
+
+fn index<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    Index::deserialize(deserializer)?.value()
+}
+
+fn indices<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<usize>, D::Error> {
+    Vec::<Index>::deserialize(deserializer)?
+        .into_iter()
+        .map(Index::value)
+        .collect()
+}
+
+impl From<&Puzzle> for SharedPuzzle {
+    /// Comments and the solution of the puzzle are left out, as the web app
+    /// has no place for them.
+    fn from(puzzle: &Puzzle) -> SharedPuzzle {
+        let n = puzzle.labels_per_category();
+        let label = |l: Label| l.category.0 * n + l.label;
+        let clues = puzzle
+            .constraints()
+            .iter()
+            .map(|constraint| SharedClue {
+                name: constraint.name.clone(),
+                kind: constraint.kind.keyword().to_string(),
+                params: constraint
+                    .kind
+                    .args()
+                    .into_iter()
+                    .map(|arg| match arg {
+                        Arg::Label(l) => label(l),
+                        Arg::Category(c) => c.0,
+                        Arg::Number(n) => n,
+                    })
+                    .collect(),
+            })
+            .collect();
+        SharedPuzzle {
+            categories: puzzle
+                .categories()
+                .map(|c| puzzle.lookup_category(c).to_string())
+                .collect(),
+            labels: puzzle
+                .categories()
+                .flat_map(|c| (0..n).map(move |l| Label::new(c, l)))
+                .map(|l| puzzle.lookup_label(l).to_string())
+                .collect(),
+            num_labels: n,
+            clues,
+        }
+    }
+}
+
+impl SharedPuzzle {
+    /// Build the puzzle, checking that every index refers to something.
+    pub fn to_puzzle(&self) -> Result<Puzzle, PuzzleError> {
+        let n = self.num_labels;
+        let mut puzzle = Puzzle::default();
+        if self.categories.is_empty() {
+            return Err(PuzzleError::MissingCategories);
+        }
+        if n == 0 {
+            return Err(PuzzleError::EmptyCategory {
+                category: self.categories[0].clone(),
+            });
+        }
+        if n.checked_mul(self.categories.len()) != Some(self.labels.len()) {
+            // Report the first category without `n` labels, counting any
+            // extra labels against the last category.
+            let c = (self.labels.len() / n).min(self.categories.len() - 1);
+            return Err(PuzzleError::InvalidLabelCount {
+                category: self.categories[c].clone(),
+                expected: n,
+                found: self.labels.len() - c * n,
+            });
+        }
+        for (name, labels) in self.categories.iter().zip(self.labels.chunks(n)) {
+            puzzle.add_category(name, labels)?;
+        }
+
+        for clue in &self.clues {
+            // Older links write afterexactly as after with a number of places.
+            let keyword = match clue.kind.as_str() {
+                "after" if clue.params.len() == 4 => "afterexactly",
+                keyword => keyword,
+            };
+            let kind = clue_kind(keyword).ok_or_else(|| PuzzleError::InvalidClueName {
+                name: clue.kind.clone(),
+            })?;
+            let params = kind.params_for(clue.params.len());
+            if params.len() != clue.params.len() {
+                return Err(PuzzleError::InvalidClueCount {
+                    expected: params.len(),
+                    found: clue.params.len(),
+                });
+            }
+            let args = params
+                .iter()
+                .zip(&clue.params)
+                .map(|(param, &i)| match param.kind {
+                    ParamKind::Label if i < self.labels.len() => {
+                        Ok(Arg::Label(Label::new(Category(i / n), i % n)))
+                    }
+                    ParamKind::Label => Err(PuzzleError::InvalidIndex {
+                        kind: "label",
+                        index: i,
+                    }),
+                    ParamKind::Category if i < self.categories.len() => {
+                        Ok(Arg::Category(Category(i)))
+                    }
+                    ParamKind::Category => Err(PuzzleError::InvalidIndex {
+                        kind: "category",
+                        index: i,
+                    }),
+                    ParamKind::Number => param.check_number(i, n).map(Arg::Number),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            puzzle.add_constraint(Constraint {
+                kind: (kind.build)(&args),
+                name: clue.name.clone(),
+            });
+        }
+        Ok(puzzle)
+    }
+
+    /// Return a link to the puzzle in the web app at `base`, e.g. `SHARE_URL`.
+    pub fn to_url(&self, base: &str) -> String {
+        let json = serde_json::to_string(self).expect("puzzle can be written as JSON");
+        format!("{}#{}", base, encode_uri_component(&json))
+    }
+
+    /// Read the puzzle from a link to the web app.
+    pub fn from_url(url: &str) -> Result<SharedPuzzle, PuzzleError> {
+        let invalid = || PuzzleError::InvalidShareUrl {
+            url: url.to_string(),
+        };
+        let (_, hash) = url.split_once('#').ok_or_else(invalid)?;
+        let json = decode_uri_component(hash).ok_or_else(invalid)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Escape `text` like `encodeURIComponent` in JavaScript.
+fn encode_uri_component(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Undo `encode_uri_component`, returning `None` if an escape is invalid or
+/// the result isn't UTF-8.
+fn decode_uri_component(text: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            let hex = core::str::from_utf8(hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -1,26 +1,49 @@
+use crate::budget::{Budget, Status, Timer};
+use crate::history::History;
+use crate::message::Message;
 use crate::puzzle::*;
 use crate::rule::*;
+use crate::verify::{verify, Answer, Violation};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
 use itertools::iproduct;
 use serde::Serialize;
-use std::collections::HashMap;
 
-#[derive(Debug)]
-struct SolutionRow(Vec<Option<Label>>);
-
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Step<'p> {
     pub label1: &'p str,
     pub label2: &'p str,
+
+    /// The cell which was set, with the label of the lower category first.
+    #[serde(skip)]
+    pub cell: (Label, Label),
+
     pub yes: bool,
-    pub description: String,
+
+    /// Name of the rule, or kind of the clue, which made the step.
+    pub rule: &'static str,
+
+    /// Why the cell was set, if known.
+    /// Render it with `Locale::render`.
+    pub message: Option<Message>,
 }
 
 impl<'p> Step<'p> {
-    pub fn new(label1: &'p str, label2: &'p str, yes: bool, description: String) -> Self {
+    pub fn new(
+        puzzle: &'p Puzzle,
+        cell: (Label, Label),
+        yes: bool,
+        rule: &'static str,
+        message: Option<Message>,
+    ) -> Self {
         Step {
-            description,
-            label1,
-            label2,
+            rule,
+            message,
+            label1: puzzle.lookup_label(cell.0),
+            label2: puzzle.lookup_label(cell.1),
+            cell,
             yes,
         }
     }
@@ -35,15 +58,89 @@ pub struct Solution<'p> {
     /// label for that entity, if it was solved. If that category for the entity
     /// was not solved, the value is None.
     #[serde(rename = "solution")]
-    pub labels: Vec<HashMap<&'p str, Option<&'p str>>>,
+    pub labels: Vec<BTreeMap<&'p str, Option<&'p str>>>,
 
     pub steps: Vec<Step<'p>>,
 
+    /// Whether every label was matched, or why the solver stopped.
+    pub status: Status,
+
+    /// How long solving took, where there is a clock.
+    #[serde(skip)]
+    pub time: Option<Duration>,
+
     /// The puzzle that this is the solution for.
     #[serde(skip)]
     pub puzzle: &'p Puzzle,
 }
 
+impl<'p> Solution<'p> {
+    /// Return the grid as it was after the first `steps` steps.
+    pub fn grid(&self, steps: usize) -> Grid<'p> {
+        Grid::from_steps(self.puzzle, &self.steps[..steps.min(self.steps.len())])
+    }
+
+    /// Create a `Solution` from `grid`, with the steps which were made in it.
+    /// Labels which aren't matched yet are `None`.
+    pub fn from_grid(grid: Grid<'p>) -> Solution<'p> {
+        let puzzle = grid.puzzle;
+        let status = if grid.is_solved() {
+            Status::Solved
+        } else {
+            Status::Partial
+        };
+        let mut map = vec![];
+        for l in 0..puzzle.labels_per_category() {
+            let primary = Label::new(Category(0), l);
+            let mut knowns = BTreeMap::new();
+            knowns.insert(
+                puzzle.lookup_category(Category(0)),
+                Some(puzzle.lookup_label(primary)),
+            );
+            for cat in 1..puzzle.num_categories() {
+                let cat = Category(cat);
+                let mut found = false;
+                for l in 0..puzzle.labels_per_category() {
+                    let secondary = Label::new(cat, l);
+                    if *grid.at(primary, secondary) == Cell::Yes {
+                        knowns.insert(
+                            puzzle.lookup_category(cat),
+                            Some(puzzle.lookup_label(secondary)),
+                        );
+                        found = true;
+                        break;
+                    }
+                }
+                if !found {
+                    knowns.insert(puzzle.lookup_category(cat), None);
+                }
+            }
+            map.push(knowns);
+        }
+        Solution {
+            labels: map,
+            puzzle,
+            steps: grid.steps,
+            status,
+            time: None,
+        }
+    }
+
+    /// Replay the steps, taking a snapshot of the grid every `interval` steps,
+    /// so that the grid after any step can be rebuilt quickly.
+    pub fn history(&self, interval: usize) -> History {
+        History::new(self, interval)
+    }
+
+    /// Check the solution against every constraint of the puzzle, returning
+    /// those which it doesn't satisfy.
+    /// Returns `None` if the solution isn't complete.
+    pub fn verify(&self) -> Option<Vec<Violation>> {
+        let answer = Answer::from_grid(&self.grid(self.steps.len()))?;
+        Some(verify(self.puzzle, &answer))
+    }
+}
+
 /// Cell in the "grid puzzle" format for the logic puzzle.
 /// Empty by default.
 /// `Yes` and `No` indicate definite confirmations of whether or not the corresponding grid
@@ -56,6 +153,7 @@ pub enum Cell {
 }
 
 /// The actual Grid that houses the label/label cell interactions.
+#[derive(Clone)]
 pub struct Grid<'p> {
     /// A 2-d array with `labels_per_category * num_categories` cells.
     /// The intersection of two labels of the same categories are all
@@ -73,10 +171,34 @@ pub struct Grid<'p> {
 
     /// The number of labels per category in the puzzle.
     pub labels_per_category: usize,
+
+    /// Name of the rule being applied, recorded in the steps it makes.
+    rule: &'static str,
+
+    /// The last cell which a rule tried to set to the opposite of its value.
+    conflict: Option<(Label, Label)>,
+}
+
+/// Why the clues of a puzzle can't all be satisfied.
+#[derive(Debug, Clone)]
+pub struct Contradiction<'p> {
+    /// Index in `Puzzle::constraints` of the clue which was being applied when
+    /// the contradiction was found, or `None` if one of the deduction rules
+    /// found it.
+    pub clue: Option<usize>,
+
+    /// Name of the rule, or kind of the clue, which found the contradiction.
+    pub rule: &'static str,
+
+    /// The cell which would have had to be both Yes and No, if known.
+    pub cell: Option<(Label, Label)>,
+
+    /// The steps which were made before the contradiction was found.
+    pub steps: Vec<Step<'p>>,
 }
 
 impl<'p> Grid<'p> {
-    pub fn new(puzzle: &'p Puzzle) -> Grid {
+    pub fn new(puzzle: &'p Puzzle) -> Grid<'p> {
         let mut cells = vec![];
         let labels_per_category = puzzle.labels_per_category();
         for cat1 in 0..puzzle.num_categories() {
@@ -104,6 +226,8 @@ impl<'p> Grid<'p> {
             puzzle,
             steps: Default::default(),
             labels_per_category,
+            rule: "",
+            conflict: None,
         }
     }
 
@@ -114,7 +238,7 @@ impl<'p> Grid<'p> {
         debug_assert!(label2.category.0 < self.puzzle.num_categories());
 
         if label1.category.0 > label2.category.0 {
-            std::mem::swap(&mut label1, &mut label2);
+            core::mem::swap(&mut label1, &mut label2);
         }
 
         (
@@ -158,6 +282,7 @@ impl<'p> Grid<'p> {
                     //     self.puzzle.lookup_label(label2),
                     //     val
                     // );
+                    self.conflict = Some((label1, label2));
                     None
                 }
             }
@@ -178,10 +303,11 @@ impl<'p> Grid<'p> {
         match self.set_impl(label1, label2, val) {
             Some(true) => {
                 self.steps.push(Step::new(
-                    self.puzzle.lookup_label(label1),
-                    self.puzzle.lookup_label(label2),
+                    self.puzzle,
+                    (label1, label2),
                     val == Cell::Yes,
-                    String::new(),
+                    self.rule,
+                    None,
                 ));
                 Some(true)
             }
@@ -190,9 +316,9 @@ impl<'p> Grid<'p> {
     }
 
     /// Call `set` but if it succeeds also call the `callback`.
-    /// This can be used for logging information if the set goes through.
+    /// The returned `Message` explains the step if the set goes through.
     #[must_use]
-    pub fn set_with_callback<CB: FnOnce() -> String>(
+    pub fn set_with_callback<CB: FnOnce() -> Message>(
         &mut self,
         label1: Label,
         label2: Label,
@@ -207,10 +333,11 @@ impl<'p> Grid<'p> {
         match self.set_impl(label1, label2, val) {
             Some(true) => {
                 self.steps.push(Step::new(
-                    self.puzzle.lookup_label(label1),
-                    self.puzzle.lookup_label(label2),
+                    self.puzzle,
+                    (label1, label2),
                     val == Cell::Yes,
-                    callback(),
+                    self.rule,
+                    Some(callback()),
                 ));
                 Some(true)
             }
@@ -218,6 +345,140 @@ impl<'p> Grid<'p> {
         }
     }
 
+    /// Overwrite the cell `(label1, label2)` with `val` whatever its current
+    /// value, without recording a step.
+    /// This is meant for editing a grid by hand.
+    pub fn put(&mut self, label1: Label, label2: Label, val: Cell) {
+        debug_assert_ne!(label1.category, label2.category);
+        *self.at_mut(label1, label2) = val;
+    }
+
+    /// Build the grid of `puzzle` resulting from making `steps` in order.
+    pub fn from_steps(puzzle: &'p Puzzle, steps: &[Step<'p>]) -> Grid<'p> {
+        let mut grid = Grid::new(puzzle);
+        for step in steps {
+            let (label1, label2) = step.cell;
+            *grid.at_mut(label1, label2) = if step.yes { Cell::Yes } else { Cell::No };
+            grid.steps.push(step.clone());
+        }
+        grid
+    }
+
+    /// Return the grid as it was after its first `n` steps, rebuilt from the
+    /// nearest snapshot in `history`, which must have been taken of the steps
+    /// of this grid.
+    pub fn at_step(&self, history: &History, n: usize) -> Grid<'p> {
+        let n = n.min(self.steps.len());
+        let mut grid = history.grid_at(self.puzzle, n);
+        grid.steps = self.steps[..n].to_vec();
+        grid
+    }
+
+    /// Return whether every label has been matched with exactly one label of
+    /// each other category, leaving no cell empty.
+    pub fn is_solved(&self) -> bool {
+        self.labels().all(|x| {
+            self.categories().filter(|&c| c != x.category).all(|c| {
+                let row = (0..self.labels_per_category).map(|l| *self.at(x, Label::new(c, l)));
+                let mut yes = 0;
+                for cell in row {
+                    match cell {
+                        Cell::Yes => yes += 1,
+                        Cell::No => {}
+                        Cell::Empty => return false,
+                    }
+                }
+                yes == 1
+            })
+        })
+    }
+
+    /// Apply the puzzle's constraints and the deduction rules until the grid
+    /// stops changing.
+    /// Returns `None` if a contradiction was found.
+    #[must_use]
+    pub fn deduce(&mut self) -> Option<()> {
+        self.try_deduce().ok()
+    }
+
+    /// Like `deduce`, but explains the contradiction if one was found.
+    pub fn try_deduce(&mut self) -> Result<(), Contradiction<'p>> {
+        self.try_deduce_within(&Budget::default()).map(|_| ())
+    }
+
+    /// Like `try_deduce`, but stops early once `budget` is spent.
+    /// Steps are counted from those already in the grid.
+    /// Returns whether the grid is solved, or why deduction stopped.
+    pub fn try_deduce_within(&mut self, budget: &Budget) -> Result<Status, Contradiction<'p>> {
+        let rules = rules();
+
+        // Run the rules in a loop until we hit a fixed point or a contradictory condition.
+        // Hopefully that's a solution.
+        let puzzle = self.puzzle;
+        let timer = Timer::start();
+        let known = self.steps.len();
+        let mut passes = 0;
+        let mut changed = true;
+        while changed {
+            if budget.max_passes.is_some_and(|max| passes >= max) {
+                return Ok(self.stopped(Status::PassLimit));
+            }
+            passes += 1;
+            changed = false;
+            for (i, constraint) in puzzle.constraints().iter().enumerate() {
+                self.rule = constraint.name();
+                changed |= constraint
+                    .apply(self, puzzle)
+                    .ok_or_else(|| self.contradiction(Some(i)))?;
+                if let Some(status) = budget.spent(self.steps.len() - known, &timer) {
+                    return Ok(self.stop(status, budget, known));
+                }
+            }
+
+            for rule in &rules {
+                self.rule = rule.name();
+                changed |= rule
+                    .apply(self, puzzle)
+                    .ok_or_else(|| self.contradiction(None))?;
+                if let Some(status) = budget.spent(self.steps.len() - known, &timer) {
+                    return Ok(self.stop(status, budget, known));
+                }
+            }
+        }
+        Ok(self.stopped(Status::Partial))
+    }
+
+    /// Undo any steps made beyond the budget's step limit, counting from the
+    /// `known` steps the grid started with.
+    fn stop(&mut self, status: Status, budget: &Budget, known: usize) -> Status {
+        if let Some(max) = budget.max_steps {
+            let undone = self.steps.split_off((known + max).min(self.steps.len()));
+            for step in undone {
+                *self.at_mut(step.cell.0, step.cell.1) = Cell::Empty;
+            }
+        }
+        self.stopped(status)
+    }
+
+    /// Return `Solved` if the grid is solved, whatever stopped the deduction,
+    /// or `status` otherwise.
+    fn stopped(&self, status: Status) -> Status {
+        if self.is_solved() {
+            Status::Solved
+        } else {
+            status
+        }
+    }
+
+    fn contradiction(&self, clue: Option<usize>) -> Contradiction<'p> {
+        Contradiction {
+            clue,
+            rule: self.rule,
+            cell: self.conflict,
+            steps: self.steps.clone(),
+        }
+    }
+
     /// Iterate over every category in the grid.
     pub fn categories(&self) -> impl Iterator<Item = Category> {
         let num_categories = self.puzzle.num_categories();
@@ -254,83 +515,148 @@ impl<'p> Grid<'p> {
     }
 }
 
-struct Solver<'p> {
-    puzzle: &'p Puzzle,
+struct Solver<'p, 'b> {
     grid: Grid<'p>,
+    budget: &'b Budget,
 }
 
-impl<'p> Solver<'p> {
-    pub fn new(puzzle: &Puzzle) -> Solver {
-        let grid = Grid::new(puzzle);
-        Solver { puzzle, grid }
+impl<'p, 'b> Solver<'p, 'b> {
+    pub fn new(puzzle: &'p Puzzle, budget: &'b Budget) -> Solver<'p, 'b> {
+        Solver {
+            grid: Grid::new(puzzle),
+            budget,
+        }
     }
 
     /// Attempt to solve the given puzzle and return the `Solution` for it.
-    pub fn solve(mut self) -> Option<Solution<'p>> {
-        let mut changed = true;
-
-        // List of rules to attempt to execute.
-        let rules: &[Box<dyn Rule>] = &[
-            Box::new(ElimOthers {}),
-            Box::new(OnlyEmpty {}),
-            Box::new(Transitivity {}),
-            Box::new(NoByProxy {}),
-        ];
-
-        // Run the rules in a loop until we hit a fixed point or a contradictory condition.
-        // Hopefully that's a solution.
-        while changed {
-            changed = false;
-            for constraint in self.puzzle.constraints() {
-                changed |= constraint.apply(&mut self.grid, self.puzzle)?;
-            }
-
-            for rule in rules {
-                changed |= rule.apply(&mut self.grid, self.puzzle)?;
+    pub fn solve(mut self) -> Result<Solution<'p>, Contradiction<'p>> {
+        let timer = Timer::start();
+        let budget = self.budget;
+        let status = self.grid.try_deduce_within(budget)?;
+        let mut solution = self.solution();
+        solution.status = status;
+        solution.time = timer.elapsed();
+        if budget.self_check {
+            if let Some(violations) = solution.verify() {
+                assert!(
+                    violations.is_empty(),
+                    "solution violates clues: {}",
+                    crate::puzzle::join(&violations, ", ")
+                );
             }
         }
-        Some(self.solution())
+        Ok(solution)
     }
 
     /// Create a `Solution` from the current puzzle grid.
     fn solution(self) -> Solution<'p> {
-        let mut map = vec![];
-        for l in 0..self.puzzle.labels_per_category() {
-            let primary = Label::new(Category(0), l);
-            let mut knowns = HashMap::new();
-            knowns.insert(
-                self.puzzle.lookup_category(Category(0)),
-                Some(self.puzzle.lookup_label(primary)),
-            );
-            for cat in 1..self.puzzle.num_categories() {
-                let cat = Category(cat);
-                let mut found = false;
-                for l in 0..self.puzzle.labels_per_category() {
-                    let secondary = Label::new(cat, l);
-                    if *self.grid.at(primary, secondary) == Cell::Yes {
-                        knowns.insert(
-                            self.puzzle.lookup_category(cat),
-                            Some(self.puzzle.lookup_label(secondary)),
-                        );
-                        found = true;
-                        break;
-                    }
+        Solution::from_grid(self.grid)
+    }
+}
+
+pub fn solve(puzzle: &Puzzle) -> Option<Solution<'_>> {
+    try_solve(puzzle).ok()
+}
+
+/// Solve `puzzle`, or explain why its clues are contradictory.
+/// The solution may be partial if the solver got stuck.
+pub fn try_solve(puzzle: &Puzzle) -> Result<Solution<'_>, Contradiction<'_>> {
+    try_solve_within(puzzle, &Budget::default())
+}
+
+/// Like `try_solve`, but stops early once `budget` is spent, returning the
+/// partial solution found so far with the status saying why.
+pub fn try_solve_within<'p>(
+    puzzle: &'p Puzzle,
+    budget: &Budget,
+) -> Result<Solution<'p>, Contradiction<'p>> {
+    Solver::new(puzzle, budget).solve()
+}
+
+/// Solve each of `puzzles` independently within `budget`, returning the
+/// results in the same order.
+/// With the `parallel` feature, the puzzles are solved on several threads.
+pub fn try_solve_all<'p>(
+    puzzles: &'p [Puzzle],
+    budget: &Budget,
+) -> Vec<Result<Solution<'p>, Contradiction<'p>>> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        puzzles
+            .par_iter()
+            .map(|puzzle| try_solve_within(puzzle, budget))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        puzzles
+            .iter()
+            .map(|puzzle| try_solve_within(puzzle, budget))
+            .collect()
+    }
+}
+
+/// Count the solutions of `puzzle` by guessing the value of an empty cell
+/// whenever deduction gets stuck, stopping once `limit` have been found.
+pub fn count_solutions(puzzle: &Puzzle, limit: usize) -> usize {
+    count_solutions_within(puzzle, limit, &Budget::default()).0
+}
+
+/// Like `count_solutions`, but stops early once `budget` is spent, counting
+/// each guess as a step.
+/// Returns the number of solutions found, along with the status saying why
+/// the search stopped early if it did.
+pub fn count_solutions_within(
+    puzzle: &Puzzle,
+    limit: usize,
+    budget: &Budget,
+) -> (usize, Option<Status>) {
+    struct Search<'b> {
+        limit: usize,
+        budget: &'b Budget,
+        timer: Timer,
+        count: usize,
+        guesses: usize,
+        stopped: Option<Status>,
+    }
+
+    fn search(mut grid: Grid, s: &mut Search) {
+        if s.stopped.is_some() || s.count >= s.limit || grid.deduce().is_none() {
+            return;
+        }
+        match grid.cells().find(|&(x, y)| *grid.at(x, y) == Cell::Empty) {
+            None => {
+                let answer = Answer::from_grid(&grid);
+                if answer.is_some_and(|answer| verify(grid.puzzle, &answer).is_empty()) {
+                    s.count += 1;
                 }
-                if !found {
-                    knowns.insert(self.puzzle.lookup_category(cat), None);
+            }
+            Some((x, y)) => {
+                for &val in &[Cell::Yes, Cell::No] {
+                    s.stopped = s.budget.spent(s.guesses, &s.timer);
+                    if s.stopped.is_some() {
+                        return;
+                    }
+                    s.guesses += 1;
+                    let mut guess = grid.clone();
+                    guess.rule = "guess";
+                    if guess.set(x, y, val).is_some() {
+                        search(guess, s);
+                    }
                 }
             }
-            map.push(knowns);
-        }
-        Solution {
-            labels: map,
-            puzzle: self.puzzle,
-            steps: self.grid.steps,
         }
     }
-}
 
-pub fn solve(puzzle: &Puzzle) -> Option<Solution> {
-    let solver = Solver::new(puzzle);
-    solver.solve()
+    let mut s = Search {
+        limit,
+        budget,
+        timer: Timer::start(),
+        count: 0,
+        guesses: 0,
+        stopped: None,
+    };
+    search(Grid::new(puzzle), &mut s);
+    (s.count, s.stopped)
 }
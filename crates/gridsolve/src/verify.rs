@@ -0,0 +1,216 @@
+use crate::constraint::ConstraintKind;
+use crate::parse::{parse_answer_table, quote};
+use crate::puzzle::*;
+use crate::solver::{Cell, Grid};
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::Serialize;
+
+/// A complete answer to a puzzle, matching every label with exactly one label
+/// of each other category. Each such group of labels is an entity, numbered
+/// by the label of the first category which belongs to it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Answer {
+    /// `rows[e][c]` is the index of the label of category `c` which belongs
+    /// to entity `e`.
+    rows: Vec<Vec<usize>>,
+
+    /// `entities[c][l]` is the entity to which label `l` of category `c`
+    /// belongs.
+    entities: Vec<Vec<usize>>,
+}
+
+impl Answer {
+    /// Build an answer from the label of each category of each entity.
+    /// Every label must appear exactly once.
+    pub(crate) fn from_rows(mut rows: Vec<Vec<usize>>) -> Answer {
+        rows.sort_by_key(|row| row[0]);
+        let num_categories = rows.first().map_or(0, Vec::len);
+        let mut entities = vec![vec![0; rows.len()]; num_categories];
+        for (e, row) in rows.iter().enumerate() {
+            for (c, &l) in row.iter().enumerate() {
+                entities[c][l] = e;
+            }
+        }
+        Answer { rows, entities }
+    }
+
+    /// Build an answer from the labels of each entity, in any order.
+    /// Every label must belong to exactly one entity.
+    pub fn from_labels(puzzle: &Puzzle, entities: &[Vec<Label>]) -> Result<Answer, PuzzleError> {
+        let mut seen = BTreeSet::new();
+        let mut rows = vec![];
+        for entity in entities {
+            let mut row = vec![None; puzzle.num_categories()];
+            for &label in entity {
+                let category = puzzle.lookup_category(label.category).to_string();
+                if row[label.category.0].is_some() {
+                    return Err(PuzzleError::RepeatedCategory { category });
+                }
+                if !seen.insert(label) {
+                    let label = puzzle.label_reference(label);
+                    return Err(PuzzleError::ReusedLabel { label });
+                }
+                row[label.category.0] = Some(label.label);
+            }
+            if let Some(c) = row.iter().position(Option::is_none) {
+                let category = puzzle.lookup_category(Category(c)).to_string();
+                return Err(PuzzleError::MissingLabel { category });
+            }
+            rows.push(row.into_iter().map(Option::unwrap).collect());
+        }
+        if rows.len() != puzzle.labels_per_category() {
+            return Err(PuzzleError::InvalidEntityCount {
+                expected: puzzle.labels_per_category(),
+                found: rows.len(),
+            });
+        }
+        Ok(Answer::from_rows(rows))
+    }
+
+    /// Read the answer from a solved grid.
+    /// Returns `None` if the grid isn't solved, or if its cells don't agree
+    /// with each other.
+    pub fn from_grid(grid: &Grid) -> Option<Answer> {
+        if !grid.is_solved() {
+            return None;
+        }
+        let n = grid.labels_per_category;
+        let mut rows = vec![];
+        for e in 0..n {
+            let primary = Label::new(Category(0), e);
+            let mut row = vec![e];
+            for c in grid.categories().skip(1) {
+                row.push((0..n).find(|&l| *grid.at(primary, Label::new(c, l)) == Cell::Yes)?);
+            }
+            rows.push(row);
+        }
+        let answer = Answer::from_rows(rows);
+        let consistent = grid.cells().all(|(x, y)| {
+            let matched = answer.entity(x) == answer.entity(y);
+            *grid.at(x, y) == if matched { Cell::Yes } else { Cell::No }
+        });
+        if consistent {
+            Some(answer)
+        } else {
+            None
+        }
+    }
+
+    /// Parse an answer table for `puzzle`, in which each line lists the
+    /// labels of one entity separated by commas.
+    /// Returns `PuzzleError::Parse` with every error that was found.
+    pub fn parse(puzzle: &Puzzle, source: &str) -> Result<Answer, PuzzleError> {
+        match parse_answer_table(puzzle, source) {
+            (Some(answer), diagnostics) if diagnostics.is_empty() => Ok(answer),
+            (_, diagnostics) => Err(PuzzleError::Parse { diagnostics }),
+        }
+    }
+
+    pub fn num_entities(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Return the entity to which `label` belongs.
+    pub fn entity(&self, label: Label) -> usize {
+        self.entities[label.category.0][label.label]
+    }
+
+    /// Return the label of `category` which belongs to `entity`.
+    pub fn label(&self, entity: usize, category: Category) -> Label {
+        Label::new(category, self.rows[entity][category.0])
+    }
+
+    /// Write the answer as the rows of an answer table, as in the
+    /// `[Solution]` section of a puzzle file: one entity per line, with its
+    /// labels in the order of their categories.
+    pub fn table(&self, puzzle: &Puzzle) -> String {
+        let mut table = String::new();
+        for entity in 0..self.num_entities() {
+            let labels = puzzle
+                .categories()
+                .map(|c| quote(&puzzle.label_reference(self.label(entity, c)), true).into_owned())
+                .collect::<Vec<_>>();
+            table.push_str(&labels.join(","));
+            table.push('\n');
+        }
+        table
+    }
+
+    /// Return whether `x` and `y` belong to the same entity.
+    fn matched(&self, x: Label, y: Label) -> bool {
+        self.entity(x) == self.entity(y)
+    }
+
+    /// Return the index of the label of `category` which belongs to the same
+    /// entity as `label`.
+    fn position(&self, label: Label, category: Category) -> usize {
+        self.label(self.entity(label), category).label
+    }
+
+    /// Return whether the answer satisfies a constraint.
+    fn satisfies(&self, kind: &ConstraintKind) -> bool {
+        match kind {
+            &ConstraintKind::Yes(x, y) => self.matched(x, y),
+            &ConstraintKind::No(x, y) => !self.matched(x, y),
+            &ConstraintKind::Or(x, y, z) => self.matched(x, y) || self.matched(x, z),
+            &ConstraintKind::Xor(x, y, z) => self.matched(x, y) ^ self.matched(x, z),
+            &ConstraintKind::After(x, c, y) => self.position(x, c) > self.position(y, c),
+            &ConstraintKind::AfterAtLeast(x, c, y, n) => {
+                self.position(x, c) >= self.position(y, c) + n
+            }
+            &ConstraintKind::AfterExactly(x, c, y, n) => {
+                self.position(x, c) == self.position(y, c) + n
+            }
+            &ConstraintKind::Distance(x, c, y, n) => {
+                let (px, py) = (self.position(x, c), self.position(y, c));
+                px.max(py) - px.min(py) == n
+            }
+            &ConstraintKind::TwoByTwo(x1, x2, y1, y2) => {
+                (self.matched(x1, y1) && self.matched(x2, y2))
+                    ^ (self.matched(x1, y2) && self.matched(x2, y1))
+            }
+            ConstraintKind::ExactlyOne(pairs) => {
+                pairs.iter().filter(|&&(x, y)| self.matched(x, y)).count() == 1
+            }
+        }
+    }
+}
+
+/// A constraint which isn't satisfied by an answer.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Violation {
+    /// Index of the constraint in `Puzzle::constraints`.
+    pub clue: usize,
+
+    /// Name of the clue.
+    pub name: String,
+
+    /// Keyword of the kind of constraint.
+    pub kind: &'static str,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "clue {}: {} is not satisfied", self.name, self.kind)
+    }
+}
+
+/// Check `answer` against every constraint of `puzzle`, returning those
+/// which it doesn't satisfy.
+pub fn verify(puzzle: &Puzzle, answer: &Answer) -> Vec<Violation> {
+    puzzle
+        .constraints()
+        .iter()
+        .enumerate()
+        .filter(|(_, constraint)| !answer.satisfies(&constraint.kind))
+        .map(|(clue, constraint)| Violation {
+            clue,
+            name: constraint.name.clone(),
+            kind: constraint.kind.keyword(),
+        })
+        .collect()
+}
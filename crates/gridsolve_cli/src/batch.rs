@@ -0,0 +1,210 @@
+use crate::error::Error;
+use gridsolve::{
+    puzzle_files, try_solve_all, Budget, Cell, Contradiction, Puzzle, PuzzleError, PuzzleFormat,
+    Solution,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Outcome of solving one puzzle.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Every label was matched.
+    Solved,
+    /// The solver got stuck before matching every label.
+    Partial,
+    /// The solver ran out of its budget before matching every label.
+    StepLimit,
+    PassLimit,
+    TimeLimit,
+    Cancelled,
+    Contradiction,
+    ParseError,
+    /// The file couldn't be read.
+    IoError,
+}
+
+/// One line of the JSON-lines report.
+#[derive(Debug, Serialize)]
+pub struct Record {
+    pub path: PathBuf,
+    pub status: Status,
+    pub steps: usize,
+    pub unsolved_cells: usize,
+    pub time_ms: f64,
+
+    /// Number of steps made by each rule or kind of clue.
+    pub rules: BTreeMap<&'static str, usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Record {
+    fn failed(path: &Path, status: Status, error: String, start: Instant) -> Record {
+        Record {
+            path: path.to_path_buf(),
+            status,
+            steps: 0,
+            unsolved_cells: 0,
+            time_ms: duration_ms(start.elapsed()),
+            rules: BTreeMap::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Milliseconds in `duration`, rounded to the microsecond.
+fn duration_ms(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1e6).round() / 1000.0
+}
+
+/// Read and parse the puzzle at `path`, or return the record of the failure.
+fn load_file(path: &Path) -> Result<Puzzle, Record> {
+    let start = Instant::now();
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| Record::failed(path, Status::IoError, err.to_string(), start))?;
+    Puzzle::parse_as(&source, PuzzleFormat::from_path(path)).map_err(|err| {
+        let status = match err {
+            PuzzleError::Io { .. } => Status::IoError,
+            _ => Status::ParseError,
+        };
+        Record::failed(path, status, err.to_string(), start)
+    })
+}
+
+/// Build the record of solving the puzzle at `path`.
+fn record(path: &Path, result: Result<Solution, Contradiction>) -> Record {
+    let solution = match result {
+        Ok(solution) => solution,
+        Err(_) => {
+            return Record {
+                path: path.to_path_buf(),
+                status: Status::Contradiction,
+                steps: 0,
+                unsolved_cells: 0,
+                time_ms: 0.0,
+                rules: BTreeMap::new(),
+                error: Some("clues are contradictory".to_string()),
+            }
+        }
+    };
+
+    let grid = solution.grid(solution.steps.len());
+    let unsolved_cells = grid
+        .cells()
+        .filter(|&(x, y)| *grid.at(x, y) == Cell::Empty)
+        .count();
+    let mut rules = BTreeMap::new();
+    for step in &solution.steps {
+        *rules.entry(step.rule).or_insert(0) += 1;
+    }
+    Record {
+        path: path.to_path_buf(),
+        status: match solution.status {
+            gridsolve::Status::Solved => Status::Solved,
+            gridsolve::Status::Partial => Status::Partial,
+            gridsolve::Status::StepLimit => Status::StepLimit,
+            gridsolve::Status::PassLimit => Status::PassLimit,
+            gridsolve::Status::TimeLimit => Status::TimeLimit,
+            gridsolve::Status::Cancelled => Status::Cancelled,
+        },
+        steps: solution.steps.len(),
+        unsolved_cells,
+        time_ms: solution.time.map_or(0.0, duration_ms),
+        rules,
+        error: None,
+    }
+}
+
+/// Expand `paths` into the list of puzzle files to solve.
+/// Directories are searched recursively for files with a puzzle extension.
+pub fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            files.extend(puzzle_files(path).map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// Solve every file in `files`, returning the records in the same order.
+/// With the `parallel` feature, the puzzles are solved on `jobs` threads, or
+/// one per CPU.
+pub fn solve_files(files: &[PathBuf], jobs: Option<usize>, budget: &Budget) -> Vec<Record> {
+    let mut puzzles = vec![];
+    let mut failures = vec![];
+    for path in files {
+        match load_file(path) {
+            Ok(puzzle) => {
+                puzzles.push(puzzle);
+                failures.push(None);
+            }
+            Err(record) => failures.push(Some(record)),
+        }
+    }
+
+    let solve = || try_solve_all(&puzzles, budget);
+    #[cfg(feature = "parallel")]
+    let results = match jobs {
+        Some(jobs) => rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .expect("could not start the solver threads")
+            .install(solve),
+        None => solve(),
+    };
+    #[cfg(not(feature = "parallel"))]
+    let results = {
+        let _ = jobs;
+        solve()
+    };
+
+    let mut results = results.into_iter();
+    files
+        .iter()
+        .zip(failures)
+        .map(|(path, failure)| {
+            failure.unwrap_or_else(|| record(path, results.next().expect("puzzle was solved")))
+        })
+        .collect()
+}
+
+/// Build the table summarizing `records`.
+pub fn summary(records: &[Record]) -> prettytable::Table {
+    use prettytable::*;
+    let mut counts = BTreeMap::new();
+    for record in records {
+        let (count, steps, time) = counts.entry(record.status).or_insert((0, 0, 0.0));
+        *count += 1;
+        *steps += record.steps;
+        *time += record.time_ms;
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["Status", r->"Puzzles", r->"Steps", r->"Time (ms)"]);
+    for (status, (count, steps, time)) in &counts {
+        let status = serde_json::to_value(status).unwrap();
+        table.add_row(row![
+            status.as_str().unwrap(),
+            r->count,
+            r->steps,
+            r->format!("{:.1}", time)
+        ]);
+    }
+    table.add_row(row![
+        "total",
+        r->records.len(),
+        r->records.iter().map(|r| r.steps).sum::<usize>(),
+        r->format!("{:.1}", records.iter().map(|r| r.time_ms).sum::<f64>())
+    ]);
+    table
+}
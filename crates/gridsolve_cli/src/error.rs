@@ -0,0 +1,140 @@
+use crate::plural;
+use gridsolve::{Diagnostic, PuzzleError};
+use serde_json::json;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Reasons for which a command can fail.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{}: {}", path.display(), source)]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: {}", path.display(), error)]
+    Puzzle {
+        path: PathBuf,
+        /// Contents of the file, used to show the location of parse errors.
+        contents: String,
+        error: PuzzleError,
+    },
+    #[error("terminal: {}", source)]
+    Terminal { source: std::io::Error },
+    #[error("{}: clues are contradictory", path.display())]
+    Contradiction { path: PathBuf },
+    #[error("{}: {} {}", path.display(), count, plural(*count, "warning"))]
+    Warnings { path: PathBuf, count: usize },
+    #[error("{}: {} violated {}", path.display(), count, plural(*count, "clue"))]
+    Violations { path: PathBuf, count: usize },
+    #[error("puzzles which could not be solved: {}", failed)]
+    Batch { failed: usize },
+    #[error("puzzles which failed their tests: {}", failed)]
+    Tests { failed: usize },
+    #[error("unformatted files: {}", count)]
+    Unformatted { count: usize },
+    #[error("files which could not be formatted: {}", count)]
+    Failed { count: usize },
+}
+
+impl Error {
+    /// Print the error to stderr, as JSON if `json` is set.
+    pub fn report(&self, json: bool) {
+        if json {
+            eprintln!("{}", self.to_json());
+            return;
+        }
+        match self {
+            Error::Puzzle {
+                path,
+                contents,
+                error: PuzzleError::Parse { diagnostics },
+            } => {
+                for diagnostic in diagnostics {
+                    eprintln!("{}\n", render_diagnostic(path, contents, diagnostic));
+                }
+            }
+            _ => eprintln!("error: {}", self),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Io { .. } | Error::Terminal { .. } => "io",
+            Error::Puzzle {
+                error: PuzzleError::Parse { .. },
+                ..
+            } => "parse",
+            Error::Puzzle { .. } => "puzzle",
+            Error::Contradiction { .. } => "contradiction",
+            Error::Warnings { .. } => "warnings",
+            Error::Violations { .. } => "violations",
+            Error::Batch { .. } => "batch",
+            Error::Tests { .. } => "tests",
+            Error::Unformatted { .. } => "unformatted",
+            Error::Failed { .. } => "failed",
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = json!({
+            "error": self.kind(),
+            "message": self.to_string(),
+        });
+        if let Error::Puzzle {
+            error: PuzzleError::Parse { diagnostics },
+            ..
+        } = self
+        {
+            value["diagnostics"] = diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    json!({
+                        "line": diagnostic.span.line,
+                        "column": diagnostic.span.column,
+                        "message": diagnostic.error.to_string(),
+                        "suggestion": diagnostic.suggestion.as_ref().map(|s| &s.replacement),
+                    })
+                })
+                .collect();
+        }
+        value
+    }
+}
+
+/// Render a parse diagnostic in the style of a compiler error,
+/// showing the offending line of `source` with the span underlined.
+pub fn render_diagnostic(path: &Path, source: &str, diagnostic: &Diagnostic) -> String {
+    let span = diagnostic.span;
+    let line = source.lines().nth(span.line - 1).unwrap_or("");
+    let gutter = span.line.to_string().len();
+    let width = source[span.start..span.end].chars().count().max(1);
+
+    let mut out = String::new();
+    writeln!(out, "error: {}", diagnostic.error).unwrap();
+    writeln!(
+        out,
+        "{:w$}--> {}:{}:{}",
+        "",
+        path.display(),
+        span.line,
+        span.column,
+        w = gutter
+    )
+    .unwrap();
+    writeln!(out, "{:w$} |", "", w = gutter).unwrap();
+    writeln!(out, "{} | {}", span.line, line).unwrap();
+    write!(
+        out,
+        "{:w$} | {}{}",
+        "",
+        " ".repeat(span.column - 1),
+        "^".repeat(width),
+        w = gutter
+    )
+    .unwrap();
+    if let Some(suggestion) = &diagnostic.suggestion {
+        write!(out, " {}", suggestion).unwrap();
+    }
+    out
+}
@@ -1,25 +1,371 @@
 extern crate gridsolve;
 
-use gridsolve::{solve, Puzzle, Solution};
-use std::path::PathBuf;
+mod batch;
+mod error;
+mod play;
+
+use error::Error;
+use gridsolve::{
+    count_solutions_within, lint, render_grid, solution_schema, test_file, try_solve_within,
+    verify, Answer, Budget, Charset, ColumnLabels, GridStyle, Locale, Outcome, Puzzle,
+    PuzzleFormat, SharedPuzzle, Solution, SolutionReport, TestResult, SHARE_URL,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "gridsolve", about = "A grid logic puzzle solver")]
+#[structopt(
+    name = "gridsolve",
+    about = "A grid logic puzzle solver",
+    setting = AppSettings::SubcommandRequiredElseHelp
+)]
 struct Opt {
-    /// Show step-by-step solving method
-    #[structopt(short, long)]
-    verbose: bool,
+    #[structopt(subcommand)]
+    cmd: Command,
+
+    #[structopt(flatten)]
+    output: OutputOpt,
+
+    #[structopt(flatten)]
+    budget: BudgetOpt,
+}
 
-    /// Output solution as JSON
+// Options shared by every command which reads a single puzzle.
+#[derive(Debug, StructOpt)]
+struct InputOpt {
+    /// Format of the input file: text, json, toml or url.
+    /// Detected from the file extension by default
     #[structopt(long)]
-    json: bool,
+    format: Option<PuzzleFormat>,
 
-    /// Input file, formatted as a grid puzzle
+    /// Input file, formatted as a grid puzzle, or a link to a puzzle in the
+    /// web app
     #[structopt(parse(from_os_str))]
     input: PathBuf,
 }
 
+// Options controlling how results and errors are printed.
+#[derive(Debug, StructOpt)]
+struct OutputOpt {
+    /// Print results and errors as JSON
+    #[structopt(long, global = true)]
+    json: bool,
+
+    /// Language of the step-by-step explanations: en or fr
+    #[structopt(long, global = true, default_value = "en")]
+    locale: Locale,
+
+    /// Don't print errors, only report them through the exit status
+    #[structopt(short, long, global = true)]
+    quiet: bool,
+}
+
+// Limits on solving, after which the solution found so far is reported.
+#[derive(Debug, StructOpt)]
+struct BudgetOpt {
+    /// Stop solving after making N steps
+    #[structopt(long, global = true, value_name = "N")]
+    max_steps: Option<usize>,
+
+    /// Stop solving after N passes over the clues and deduction rules
+    #[structopt(long, global = true, value_name = "N")]
+    max_passes: Option<usize>,
+
+    /// Stop solving a puzzle after this many seconds
+    #[structopt(long, global = true, value_name = "SECONDS", parse(try_from_str = parse_timeout))]
+    timeout: Option<Duration>,
+}
+
+fn parse_timeout(seconds: &str) -> Result<Duration, String> {
+    let seconds = seconds.parse::<f64>().map_err(|e| e.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| "must be a number of seconds".to_string())
+}
+
+impl BudgetOpt {
+    fn budget(&self) -> Budget {
+        Budget {
+            max_steps: self.max_steps,
+            max_passes: self.max_passes,
+            time_limit: self.timeout,
+            ..Budget::default()
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Solve a puzzle and print the solution
+    Solve {
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+
+    /// Parse a puzzle and check its clues for likely mistakes, without solving it
+    Check {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Fail if any warnings are found
+        #[structopt(short = "D", long)]
+        deny_warnings: bool,
+    },
+
+    /// Solve a puzzle and print the reasoning behind every step
+    Explain {
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+
+    /// Print the next step of the solution
+    Hint {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Number of steps which have already been made
+        #[structopt(long, default_value = "0")]
+        after: usize,
+    },
+
+    /// Count the solutions of a puzzle, to check that it has exactly one
+    Count {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Stop counting after this many solutions
+        #[structopt(long, default_value = "100")]
+        limit: usize,
+    },
+
+    /// Convert a puzzle to another format
+    Convert {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Format to convert to: text, json, toml or url.
+        /// Detected from the extension of the output file by default
+        #[structopt(long)]
+        to: Option<PuzzleFormat>,
+
+        /// Output file, instead of printing the result
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Print a link which opens a puzzle in the web app
+    Share {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Address of the web app
+        #[structopt(long, default_value = SHARE_URL)]
+        base: String,
+    },
+
+    /// Check an answer to a puzzle against its clues
+    Verify {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Answer file, with a line for each entity listing its labels
+        /// separated by commas
+        #[structopt(parse(from_os_str))]
+        answer: PathBuf,
+    },
+
+    /// Print the logic grid of a puzzle, as filled in by the solver
+    Grid {
+        #[structopt(flatten)]
+        input: InputOpt,
+
+        /// Show the grid after only the first N steps of the solution
+        #[structopt(long, value_name = "N")]
+        step: Option<usize>,
+
+        /// Draw the grid with ASCII characters only
+        #[structopt(long)]
+        ascii: bool,
+
+        /// Write the column labels horizontally, cut down to N characters,
+        /// instead of vertically
+        #[structopt(long, value_name = "N")]
+        abbreviate: Option<usize>,
+    },
+
+    /// Solve a puzzle interactively in the terminal
+    Play {
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+
+    /// Print statistics about a puzzle and its solution
+    Stats {
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+
+    /// Solve many puzzles, printing a JSON line for each of them and a summary
+    /// table on stderr
+    Batch {
+        /// Number of threads to solve puzzles on, with the parallel feature.
+        /// Defaults to the number of CPUs
+        #[structopt(short, long)]
+        jobs: Option<usize>,
+
+        /// Don't print the summary table
+        #[structopt(long)]
+        no_summary: bool,
+
+        /// Puzzle files, or directories to search for puzzle files
+        #[structopt(parse(from_os_str), required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Solve puzzle files which declare their solution in a [Solution] section,
+    /// and check that the solver finds it
+    Test {
+        /// Puzzle files, or directories to search for puzzle files
+        #[structopt(parse(from_os_str), required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Print the JSON Schema of the solutions printed by `solve --json`
+    Schema,
+
+    /// Rewrite puzzle files in place in their canonical format
+    Fmt {
+        /// Only report files which are not canonically formatted
+        #[structopt(long)]
+        check: bool,
+
+        /// Puzzle files to format
+        #[structopt(parse(from_os_str), required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+impl InputOpt {
+    /// Read and parse the input puzzle.
+    fn load(&self) -> Result<Puzzle, Error> {
+        // A link pasted from the web app is read directly rather than as a
+        // path.
+        if let Some(url) = self.input.to_str().filter(|input| is_url(input)) {
+            return Puzzle::parse_as(url, PuzzleFormat::Url).map_err(|error| Error::Puzzle {
+                path: self.input.clone(),
+                contents: url.to_string(),
+                error,
+            });
+        }
+        let format = self
+            .format
+            .unwrap_or_else(|| PuzzleFormat::from_path(&self.input));
+        load(&self.input, format)
+    }
+}
+
+/// Return whether `input` is a link rather than a path.
+fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Read and parse the puzzle at `path`.
+fn load(path: &Path, format: PuzzleFormat) -> Result<Puzzle, Error> {
+    let source = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Puzzle::parse_as(&source, format).map_err(|error| Error::Puzzle {
+        path: path.to_path_buf(),
+        contents: source,
+        error,
+    })
+}
+
+/// Solve `puzzle`, read from `path`, within `budget`.
+fn solve_loaded<'p>(
+    path: &Path,
+    puzzle: &'p Puzzle,
+    budget: &Budget,
+) -> Result<Solution<'p>, Error> {
+    try_solve_within(puzzle, budget).map_err(|_| Error::Contradiction {
+        path: path.to_path_buf(),
+    })
+}
+
+/// Return `noun`, made plural unless `count` is 1.
+pub fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        noun.to_string()
+    } else {
+        format!("{}s", noun)
+    }
+}
+
+fn print_json(value: &impl serde::Serialize) {
+    println!("{}", serde_json::to_string(value).unwrap());
+}
+
+/// Format each of `files` in place, or with `check` only report the files
+/// whose contents differ from their canonical form.
+fn format_files(files: &[PathBuf], check: bool, output: &OutputOpt) -> Result<(), Error> {
+    let mut unformatted = 0;
+    let mut failed = 0;
+    for path in files {
+        let format = PuzzleFormat::from_path(path);
+        let result = std::fs::read_to_string(path)
+            .map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })
+            .and_then(|original| {
+                match Puzzle::parse_as(&original, format).and_then(|p| p.to_string_as(format)) {
+                    Ok(formatted) => Ok((original, formatted)),
+                    Err(error) => Err(Error::Puzzle {
+                        path: path.clone(),
+                        contents: original,
+                        error,
+                    }),
+                }
+            });
+        let (original, formatted) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                if !output.quiet {
+                    err.report(output.json);
+                }
+                failed += 1;
+                continue;
+            }
+        };
+        if original == formatted {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            unformatted += 1;
+        } else if let Err(source) = std::fs::write(path, formatted) {
+            let err = Error::Io {
+                path: path.clone(),
+                source,
+            };
+            if !output.quiet {
+                err.report(output.json);
+            }
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        Err(Error::Failed { count: failed })
+    } else if unformatted > 0 {
+        Err(Error::Unformatted { count: unformatted })
+    } else {
+        Ok(())
+    }
+}
+
 fn pretty_solution(solution: &Solution) -> prettytable::Table {
     use prettytable::*;
     let mut table = Table::new();
@@ -40,39 +386,396 @@ fn pretty_solution(solution: &Solution) -> prettytable::Table {
     table
 }
 
-fn main() {
-    let opt = Opt::from_args();
+/// Print the outcome of each test, followed by the details of the failures
+/// and a summary, or with `json` a JSON line for each test.
+fn report_tests(results: &[TestResult], json: bool) {
+    if json {
+        for result in results {
+            let mut value = json!({ "path": result.path });
+            let (outcome, details) = match &result.outcome {
+                Outcome::Passed => ("passed", None),
+                Outcome::Skipped => ("skipped", None),
+                Outcome::Contradiction => ("contradiction", None),
+                Outcome::InvalidSolution(violations) => {
+                    ("invalid_solution", Some(("violations", json!(violations))))
+                }
+                Outcome::Mismatch(differences) => (
+                    "mismatch",
+                    Some((
+                        "differences",
+                        differences
+                            .iter()
+                            .map(|d| {
+                                json!({
+                                    "entity": d.entity,
+                                    "category": d.category,
+                                    "expected": d.expected,
+                                    "found": d.found,
+                                })
+                            })
+                            .collect(),
+                    )),
+                ),
+                Outcome::Error(error) => ("error", Some(("error", json!(error.to_string())))),
+            };
+            value["outcome"] = json!(outcome);
+            if let Some((key, details)) = details {
+                value[key] = details;
+            }
+            print_json(&value);
+        }
+        return;
+    }
+
+    for result in results {
+        let status = match result.outcome {
+            Outcome::Passed => "ok",
+            Outcome::Skipped => "skipped",
+            _ => "FAILED",
+        };
+        println!("test {} ... {}", result.path.display(), status);
+    }
+    let failures = results
+        .iter()
+        .filter(|r| r.outcome.is_failure())
+        .collect::<Vec<_>>();
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for result in &failures {
+            println!("\n---- {} ----", result.path.display());
+            match &result.outcome {
+                Outcome::InvalidSolution(violations) => {
+                    println!("the declared solution breaks some clues:");
+                    for violation in violations {
+                        println!("  {}", violation);
+                    }
+                }
+                Outcome::Contradiction => println!("clues are contradictory"),
+                Outcome::Mismatch(differences) => {
+                    for difference in differences {
+                        println!("  {}", difference);
+                    }
+                }
+                Outcome::Error(error) => println!("{}", error),
+                Outcome::Passed | Outcome::Skipped => {}
+            }
+        }
+    }
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|r| f(&r.outcome)).count();
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} skipped",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        count(|o| matches!(o, Outcome::Passed)),
+        failures.len(),
+        count(|o| matches!(o, Outcome::Skipped)),
+    );
+}
+
+/// Print the `index`th step of `solution`, counting from 1.
+fn print_step(index: usize, solution: &Solution, locale: Locale) {
+    let step = &solution.steps[index - 1];
+    println!(
+        "{:>3}. {} ({}, {}) [{}]",
+        index,
+        if step.yes { "\u{2705}" } else { " \u{274c}" },
+        step.label1,
+        step.label2,
+        locale.describe(step, solution.puzzle)
+    );
+}
 
-    let puzzle = match Puzzle::from_file(&opt.input) {
-        Ok(puzzle) => puzzle,
-        Err(err) => {
-            eprintln!("{}\n", err);
-            return;
+fn run(cmd: &Command, output: &OutputOpt, budget: &Budget) -> Result<(), Error> {
+    let locale = output.locale;
+    match cmd {
+        Command::Solve { input } => {
+            let puzzle = input.load()?;
+            let solution = solve_loaded(&input.input, &puzzle, budget)?;
+            if output.json {
+                print_json(&SolutionReport::new(&solution, locale));
+            } else {
+                println!("{}", pretty_solution(&solution));
+                if solution.status.is_out_of_budget() && !output.quiet {
+                    eprintln!(
+                        "warning: {}: stopped after {} {}: {}",
+                        input.input.display(),
+                        solution.steps.len(),
+                        plural(solution.steps.len(), "step"),
+                        solution.status
+                    );
+                }
+            }
         }
-    };
-    let solution = match solve(&puzzle) {
-        Some(solution) => solution,
-        None => {
-            eprintln!("Clues are contradictory\n");
-            return;
+
+        Command::Check {
+            input,
+            deny_warnings,
+        } => {
+            let puzzle = input.load()?;
+            let warnings = lint(&puzzle);
+            if output.json {
+                print_json(&json!({
+                    "warnings": warnings
+                        .iter()
+                        .map(|w| json!({"clue": w.name, "message": w.lint.to_string()}))
+                        .collect::<Vec<_>>(),
+                }));
+            } else {
+                for warning in &warnings {
+                    println!("warning: {}: {}", input.input.display(), warning);
+                }
+            }
+            if *deny_warnings && !warnings.is_empty() {
+                return Err(Error::Warnings {
+                    path: input.input.clone(),
+                    count: warnings.len(),
+                });
+            }
+        }
+
+        Command::Explain { input } => {
+            let puzzle = input.load()?;
+            let solution = solve_loaded(&input.input, &puzzle, budget)?;
+            if output.json {
+                print_json(&locale.localize(&solution).steps);
+            } else {
+                for i in 1..=solution.steps.len() {
+                    print_step(i, &solution, locale);
+                }
+            }
+        }
+
+        Command::Hint { input, after } => {
+            let puzzle = input.load()?;
+            let solution = solve_loaded(&input.input, &puzzle, budget)?;
+            let localized = locale.localize(&solution);
+            let hint = localized.steps.get(*after);
+            if output.json {
+                print_json(&hint);
+            } else if hint.is_some() {
+                print_step(after + 1, &solution, locale);
+            } else {
+                println!("No more steps can be deduced");
+            }
+        }
+
+        Command::Count { input, limit } => {
+            let puzzle = input.load()?;
+            let (count, stopped) = count_solutions_within(&puzzle, *limit, budget);
+            if output.json {
+                print_json(&json!({ "count": count, "limit": limit, "status": stopped }));
+            } else {
+                if count >= *limit || stopped.is_some() {
+                    println!("at least {} {}", count, plural(count, "solution"));
+                } else {
+                    println!("{} {}", count, plural(count, "solution"));
+                }
+                if let Some(status) = stopped.filter(|_| !output.quiet) {
+                    eprintln!(
+                        "warning: {}: stopped counting: {}",
+                        input.input.display(),
+                        status
+                    );
+                }
+            }
+        }
+
+        Command::Verify { input, answer } => {
+            let puzzle = input.load()?;
+            let source = std::fs::read_to_string(answer).map_err(|source| Error::Io {
+                path: answer.clone(),
+                source,
+            })?;
+            let parsed = Answer::parse(&puzzle, &source).map_err(|error| Error::Puzzle {
+                path: answer.clone(),
+                contents: source,
+                error,
+            })?;
+            let violations = verify(&puzzle, &parsed);
+            if output.json {
+                print_json(&json!({
+                    "violations": violations
+                        .iter()
+                        .map(|v| json!({"clue": v.name, "kind": v.kind}))
+                        .collect::<Vec<_>>(),
+                }));
+            } else if violations.is_empty() {
+                println!("all clues are satisfied");
+            } else {
+                for violation in &violations {
+                    println!("{}: {}", answer.display(), violation);
+                }
+            }
+            if !violations.is_empty() {
+                return Err(Error::Violations {
+                    path: answer.clone(),
+                    count: violations.len(),
+                });
+            }
+        }
+
+        Command::Convert {
+            input,
+            to,
+            output: path,
+        } => {
+            let puzzle = input.load()?;
+            let format = to
+                .or_else(|| path.as_deref().map(PuzzleFormat::from_path))
+                .unwrap_or(PuzzleFormat::Text);
+            let text = puzzle.to_string_as(format).map_err(|error| Error::Puzzle {
+                path: input.input.clone(),
+                contents: String::new(),
+                error,
+            })?;
+            match path {
+                Some(path) => std::fs::write(path, text).map_err(|source| Error::Io {
+                    path: path.clone(),
+                    source,
+                })?,
+                None => print!("{}", text),
+            }
+        }
+
+        Command::Share { input, base } => {
+            let puzzle = input.load()?;
+            let url = SharedPuzzle::from(&puzzle).to_url(base);
+            if output.json {
+                print_json(&json!({ "url": url }));
+            } else {
+                println!("{}", url);
+            }
+        }
+
+        Command::Grid {
+            input,
+            step,
+            ascii,
+            abbreviate,
+        } => {
+            let puzzle = input.load()?;
+            let solution = solve_loaded(&input.input, &puzzle, budget)?;
+            let grid = solution.grid(step.unwrap_or(solution.steps.len()));
+            let style = GridStyle {
+                charset: if *ascii {
+                    Charset::Ascii
+                } else {
+                    Charset::Unicode
+                },
+                column_labels: match abbreviate {
+                    Some(len) => ColumnLabels::Abbreviated(*len),
+                    None => ColumnLabels::Rotated,
+                },
+                cursor: None,
+            };
+            print!("{}", render_grid(&grid, style));
         }
-    };
 
-    if opt.verbose {
-        for step in &solution.steps {
+        Command::Play { input } => {
+            let puzzle = input.load()?;
+            let solution = solve_loaded(&input.input, &puzzle, budget)?;
+            let answer = solution.grid(solution.steps.len());
+            play::play(&puzzle, answer, locale).map_err(|source| Error::Terminal { source })?;
+        }
+
+        Command::Stats { input } => {
+            let puzzle = input.load()?;
+            let solution = try_solve_within(&puzzle, budget).ok();
+            let mut kinds = BTreeMap::new();
+            for constraint in puzzle.constraints() {
+                *kinds.entry(constraint.kind.keyword()).or_insert(0) += 1;
+            }
+            let mut clues: Vec<_> = puzzle.constraints().iter().map(|c| &c.name).collect();
+            clues.dedup();
+            let steps = solution.as_ref().map(|s| s.steps.len());
+            let status = solution.as_ref().map(|s| s.status);
+            let unsolved = solution.as_ref().map(|s| {
+                s.labels
+                    .iter()
+                    .flat_map(|row| row.values())
+                    .filter(|l| l.is_none())
+                    .count()
+            });
+            if output.json {
+                print_json(&json!({
+                    "categories": puzzle.num_categories(),
+                    "labels_per_category": puzzle.labels_per_category(),
+                    "clues": clues.len(),
+                    "constraints": puzzle.constraints().len(),
+                    "kinds": kinds,
+                    "contradictory": solution.is_none(),
+                    "steps": steps,
+                    "unsolved": unsolved,
+                    "status": status,
+                }));
+            } else {
+                println!("categories:          {}", puzzle.num_categories());
+                println!("labels per category: {}", puzzle.labels_per_category());
+                println!("clues:               {}", clues.len());
+                println!("constraints:         {}", puzzle.constraints().len());
+                for (kind, count) in &kinds {
+                    println!("  {:<18} {}", kind, count);
+                }
+                match (steps, unsolved, status) {
+                    (Some(steps), Some(unsolved), Some(status)) => {
+                        println!("steps:               {}", steps);
+                        println!("unsolved labels:     {}", unsolved);
+                        println!("status:              {}", status);
+                    }
+                    _ => println!("clues are contradictory"),
+                }
+            }
+        }
+
+        Command::Batch {
+            jobs,
+            no_summary,
+            paths,
+        } => {
+            let files = batch::collect_files(paths)?;
+            let records = batch::solve_files(&files, *jobs, budget);
+            for record in &records {
+                print_json(record);
+            }
+            if !no_summary {
+                eprint!("{}", batch::summary(&records));
+            }
+            let failed = records
+                .iter()
+                .filter(|r| r.status > batch::Status::Partial)
+                .count();
+            if failed > 0 {
+                return Err(Error::Batch { failed });
+            }
+        }
+
+        Command::Test { paths } => {
+            let files = batch::collect_files(paths)?;
+            let results = files.iter().map(|path| test_file(path)).collect::<Vec<_>>();
+            report_tests(&results, output.json);
+            let failed = results.iter().filter(|r| r.outcome.is_failure()).count();
+            if failed > 0 {
+                return Err(Error::Tests { failed });
+            }
+        }
+
+        Command::Schema => {
             println!(
-                "{} ({}, {}) [{}]",
-                if step.yes { "\u{2705}" } else { " \u{274c}" },
-                step.label1,
-                step.label2,
-                &step.description.trim()
+                "{}",
+                serde_json::to_string_pretty(&solution_schema()).unwrap()
             );
         }
+
+        Command::Fmt { check, files } => format_files(files, *check, output)?,
     }
+    Ok(())
+}
 
-    if opt.json {
-        println!("{}", serde_json::to_string(&solution).unwrap());
-    } else {
-        println!("{}", pretty_solution(&solution));
+fn main() {
+    let opt = Opt::from_args();
+    if let Err(err) = run(&opt.cmd, &opt.output, &opt.budget.budget()) {
+        if !opt.output.quiet {
+            err.report(opt.output.json);
+        }
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,281 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::{cursor, execute, queue, style, terminal};
+use gridsolve::{render_grid, staircase, Cell, Grid, GridStyle, Label, Locale, Puzzle};
+use std::io::{self, Write};
+
+/// Help line shown below the grid.
+const KEYS: &str = "arrows/hjkl: move  space: cycle  y/n: mark  backspace: clear  \
+                    u: undo  ?: hint  c: check  q: quit";
+
+/// State of an interactive solving session.
+struct Game<'p> {
+    puzzle: &'p Puzzle,
+
+    /// The grid as filled in by the solver, to check the player's marks.
+    answer: Grid<'p>,
+
+    /// The grid as marked by the player.
+    marks: Grid<'p>,
+
+    /// Previous value of every cell changed by the player, most recent last.
+    history: Vec<(Label, Label, Cell)>,
+
+    rows: Vec<Label>,
+    columns: Vec<Label>,
+
+    /// Number of column labels which each row meets in the staircase layout.
+    row_widths: Vec<usize>,
+
+    /// Position of the cursor, as indices into `rows` and `columns`.
+    cursor: (usize, usize),
+
+    /// Whether the player's marks contradict the clues.
+    contradiction: bool,
+
+    status: String,
+    locale: Locale,
+}
+
+impl<'p> Game<'p> {
+    fn new(puzzle: &'p Puzzle, answer: Grid<'p>, locale: Locale) -> Game<'p> {
+        let n = puzzle.labels_per_category();
+        let (row_categories, column_categories) = staircase(puzzle);
+        let labels = |categories: &[_]| -> Vec<Label> {
+            categories
+                .iter()
+                .flat_map(|&c| (0..n).map(move |l| Label::new(c, l)))
+                .collect()
+        };
+        let row_widths = (0..row_categories.len())
+            .flat_map(|i| std::iter::repeat_n((column_categories.len() - i) * n, n))
+            .collect();
+        Game {
+            puzzle,
+            answer,
+            marks: Grid::new(puzzle),
+            history: vec![],
+            rows: labels(&row_categories),
+            columns: labels(&column_categories),
+            row_widths,
+            cursor: (0, 0),
+            contradiction: false,
+            status: String::new(),
+            locale,
+        }
+    }
+
+    fn selected(&self) -> (Label, Label) {
+        (self.rows[self.cursor.0], self.columns[self.cursor.1])
+    }
+
+    fn move_cursor(&mut self, rows: isize, columns: isize) {
+        let (row, column) = self.cursor;
+        let row = (row as isize + rows).clamp(0, self.rows.len() as isize - 1) as usize;
+        let width = self.row_widths[row] as isize;
+        let column = (column as isize + columns).clamp(0, width - 1) as usize;
+        self.cursor = (row, column);
+    }
+
+    /// Set the selected cell to `val`, remembering its previous value.
+    fn mark(&mut self, val: Cell) {
+        let (x, y) = self.selected();
+        let previous = *self.marks.at(x, y);
+        if previous == val {
+            return;
+        }
+        self.history.push((x, y, previous));
+        self.marks.put(x, y, val);
+        self.status.clear();
+        self.update();
+    }
+
+    fn cycle(&mut self) {
+        let (x, y) = self.selected();
+        self.mark(match self.marks.at(x, y) {
+            Cell::Empty => Cell::Yes,
+            Cell::Yes => Cell::No,
+            Cell::No => Cell::Empty,
+        });
+    }
+
+    fn undo(&mut self) {
+        match self.history.pop() {
+            Some((x, y, previous)) => {
+                self.marks.put(x, y, previous);
+                self.status = "Undone".to_string();
+                self.update();
+            }
+            None => self.status = "Nothing to undo".to_string(),
+        }
+    }
+
+    /// Check whether the marks can still be completed to a solution, by running
+    /// the solver's rules from them.
+    fn update(&mut self) {
+        self.contradiction = self.marks.clone().deduce().is_none();
+    }
+
+    /// Return the first cell marked differently from the answer.
+    fn first_mistake(&self) -> Option<(Label, Label)> {
+        self.marks.cells().find(|&(x, y)| {
+            let mark = *self.marks.at(x, y);
+            mark != Cell::Empty && mark != *self.answer.at(x, y)
+        })
+    }
+
+    fn describe_cell(&self, (x, y): (Label, Label)) -> String {
+        format!(
+            "{} / {}",
+            self.puzzle.lookup_label(x),
+            self.puzzle.lookup_label(y)
+        )
+    }
+
+    /// Move the cursor to the cell `(x, y)`, in whichever order it appears in
+    /// the staircase.
+    fn select(&mut self, (x, y): (Label, Label)) {
+        for &(x, y) in &[(x, y), (y, x)] {
+            let row = self.rows.iter().position(|&l| l == x);
+            let column = self.columns.iter().position(|&l| l == y);
+            if let (Some(row), Some(column)) = (row, column) {
+                if column < self.row_widths[row] {
+                    self.cursor = (row, column);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Point out a mistake, or the next cell which can be deduced from the
+    /// marks along with the reason for it.
+    fn hint(&mut self) {
+        if let Some(cell) = self.first_mistake() {
+            self.status = format!("{} is marked wrong", self.describe_cell(cell));
+            self.select(cell);
+            return;
+        }
+        let mut grid = self.marks.clone();
+        let known = grid.steps.len();
+        if grid.deduce().is_none() {
+            self.status = "Your marks contradict the clues".to_string();
+            return;
+        }
+        match grid.steps.get(known) {
+            Some(step) => {
+                let description = self.locale.describe(step, self.puzzle);
+                self.status = format!(
+                    "{}: {}",
+                    self.describe_cell(step.cell),
+                    if description.is_empty() {
+                        if step.yes { "yes" } else { "no" }.to_string()
+                    } else {
+                        description
+                    }
+                );
+                self.select(step.cell);
+            }
+            None => self.status = "No further deductions can be made".to_string(),
+        }
+    }
+
+    /// Compare the marks with the answer.
+    fn check(&mut self) {
+        let mut total = 0;
+        let mut filled = 0;
+        let mut wrong = 0;
+        for (x, y) in self.marks.cells() {
+            total += 1;
+            let mark = *self.marks.at(x, y);
+            if mark != Cell::Empty {
+                filled += 1;
+                if mark != *self.answer.at(x, y) {
+                    wrong += 1;
+                }
+            }
+        }
+        self.status = if wrong == 0 && filled == total {
+            "Solved!".to_string()
+        } else {
+            format!("{} of {} cells marked, {} wrong", filled, total, wrong)
+        };
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let style = GridStyle {
+            cursor: Some(self.selected()),
+            ..GridStyle::default()
+        };
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        for line in render_grid(&self.marks, style).lines() {
+            queue!(out, style::Print(line), cursor::MoveToNextLine(1))?;
+        }
+        queue!(
+            out,
+            cursor::MoveToNextLine(1),
+            style::Print(self.describe_cell(self.selected())),
+            cursor::MoveToNextLine(1)
+        )?;
+        if self.contradiction {
+            queue!(
+                out,
+                style::Print("Your marks contradict the clues"),
+                cursor::MoveToNextLine(1)
+            )?;
+        }
+        queue!(
+            out,
+            style::Print(&self.status),
+            cursor::MoveToNextLine(2),
+            style::Print(KEYS)
+        )?;
+        out.flush()
+    }
+
+    /// Apply a key press, and return whether to keep playing.
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1, 0),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1, 0),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(0, -1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(0, 1),
+            KeyCode::Char(' ') | KeyCode::Enter => self.cycle(),
+            KeyCode::Char('y') | KeyCode::Char('o') => self.mark(Cell::Yes),
+            KeyCode::Char('n') | KeyCode::Char('x') => self.mark(Cell::No),
+            KeyCode::Backspace | KeyCode::Delete | KeyCode::Char('.') => self.mark(Cell::Empty),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('?') => self.hint(),
+            KeyCode::Char('c') => self.check(),
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+}
+
+fn run(game: &mut Game, out: &mut impl Write) -> io::Result<()> {
+    loop {
+        game.draw(out)?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release && !game.handle(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Let the player solve `puzzle` in the terminal, checking their marks
+/// against `answer`.
+pub fn play<'p>(puzzle: &'p Puzzle, answer: Grid<'p>, locale: Locale) -> io::Result<()> {
+    let mut game = Game::new(puzzle, answer, locale);
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = run(&mut game, &mut out);
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
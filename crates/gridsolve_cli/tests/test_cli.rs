@@ -0,0 +1,161 @@
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const CATEGORIES: &str = "[Categories]\nA\na1\na2\n\nB\nb1\nb2\n\n[Clues]\n";
+
+fn puzzle_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../puzzles")
+        .join(name)
+}
+
+/// Write `contents` to the file `name` in a directory for the tests, and
+/// return its path.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gridsolve_cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn json_lines(output: &Output) -> Vec<Value> {
+    stdout(output)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_check() {
+    let simple = puzzle_path("simple.txt");
+    let output = run(&["check", "-D", simple.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+
+    let duplicate = temp_file(
+        "duplicate.txt",
+        &format!("{}1,yes,a1,b1\n2,yes,a1,b1\n", CATEGORIES),
+    );
+    let duplicate = duplicate.to_str().unwrap();
+    let output = run(&["check", duplicate]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("same constraint as clue 1"));
+    let output = run(&["check", "--deny-warnings", duplicate]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let invalid = temp_file("invalid.txt", &format!("{}1,yes,a1,b3\n", CATEGORIES));
+    let output = run(&["check", "--json", invalid.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"], "parse");
+    assert_eq!(error["diagnostics"][0]["line"], 11);
+}
+
+#[test]
+fn test_count() {
+    let simple = puzzle_path("simple.txt");
+    let output = run(&["count", simple.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1 solution\n");
+
+    let open = temp_file("open.txt", CATEGORIES);
+    let output = run(&["count", "--json", "--limit", "1", open.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(json_lines(&output)[0]["count"], 1);
+    assert_eq!(json_lines(&output)[0]["status"], Value::Null);
+
+    let output = run(&[
+        "count",
+        "--json",
+        "--max-steps",
+        "1",
+        open.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(json_lines(&output)[0]["status"], "step_limit");
+    let output = run(&["count", "--max-steps", "1", open.to_str().unwrap()]);
+    assert!(stdout(&output).starts_with("at least"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("step limit reached"));
+}
+
+#[test]
+fn test_verify() {
+    let simple = puzzle_path("simple.txt");
+    let simple = simple.to_str().unwrap();
+    let answer = temp_file(
+        "answer.txt",
+        "Angela,Germany,1954\nDonald,United States,1946\nLeo,Ireland,1979\n",
+    );
+    let output = run(&["verify", simple, answer.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "all clues are satisfied\n");
+
+    let wrong = temp_file(
+        "wrong.txt",
+        "Angela,Germany,1946\nDonald,United States,1954\nLeo,Ireland,1979\n",
+    );
+    let output = run(&["verify", "--json", simple, wrong.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let violations = &json_lines(&output)[0]["violations"];
+    assert_eq!(violations[0]["clue"], "1");
+}
+
+#[test]
+fn test_fmt_check() {
+    let original = std::fs::read_to_string(puzzle_path("simple.txt")).unwrap();
+    let canonical = temp_file("canonical.txt", &original);
+    let output = run(&["fmt", "--check", canonical.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let messy = original.replace(",yes,", " , yes , ");
+    let path = temp_file("messy.txt", &messy);
+    let path = path.to_str().unwrap();
+    let output = run(&["fmt", "--check", path]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), messy);
+
+    assert!(run(&["fmt", path]).status.success());
+    assert_eq!(std::fs::read_to_string(path).unwrap(), original);
+    assert!(run(&["fmt", "--check", path]).status.success());
+}
+
+#[test]
+fn test_batch() {
+    let simple = puzzle_path("simple.txt");
+    let simple = simple.to_str().unwrap();
+    let output = run(&["batch", "--no-summary", simple]);
+    assert!(output.status.success());
+    let records = json_lines(&output);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["path"], simple);
+    assert_eq!(records[0]["status"], "solved");
+    assert_eq!(records[0]["unsolved_cells"], 0);
+
+    let contradiction = temp_file(
+        "contradiction.txt",
+        &format!("{}1,yes,a1,b1\n2,yes,a1,b2\n", CATEGORIES),
+    );
+    let output = run(&[
+        "batch",
+        "--no-summary",
+        simple,
+        contradiction.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let statuses = json_lines(&output)
+        .iter()
+        .map(|record| record["status"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(statuses, ["solved", "contradiction"]);
+}
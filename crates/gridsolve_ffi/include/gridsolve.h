@@ -0,0 +1,224 @@
+#ifndef GRIDSOLVE_H
+#define GRIDSOLVE_H
+
+/* Generated by cbindgen from crates/gridsolve_ffi, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of a call to the API.
+ * On failure, `gs_last_error_message` describes what went wrong.
+ */
+typedef enum GsError {
+  GS_ERROR_OK = 0,
+  /**
+   * A required pointer was NULL.
+   */
+  GS_ERROR_NULL_ARGUMENT,
+  /**
+   * A string wasn't valid UTF-8.
+   */
+  GS_ERROR_INVALID_UTF8,
+  /**
+   * The puzzle couldn't be parsed.
+   */
+  GS_ERROR_PARSE,
+  /**
+   * The clues of the puzzle are contradictory.
+   */
+  GS_ERROR_CONTRADICTION,
+  /**
+   * An index was out of range.
+   */
+  GS_ERROR_OUT_OF_RANGE,
+  /**
+   * gridsolve panicked, which is a bug.
+   */
+  GS_ERROR_PANIC,
+  /**
+   * The timeout of a budget wasn't a number of seconds.
+   */
+  GS_ERROR_INVALID_BUDGET,
+  /**
+   * An argument wasn't one of the values it can take.
+   */
+  GS_ERROR_INVALID_ARGUMENT,
+} GsError;
+
+/**
+ * Formats in which a puzzle can be written.
+ * Functions take them as a `uint32_t`, so that any value passed from C is
+ * checked instead of being undefined behaviour.
+ */
+typedef enum GsFormat {
+  /**
+   * The line-oriented `[Categories]`/`[Clues]` format.
+   */
+  GS_FORMAT_TEXT = 0,
+  GS_FORMAT_JSON = 1,
+  GS_FORMAT_TOML = 2,
+} GsFormat;
+
+/**
+ * How far the solver got, see `gridsolve::Status`.
+ */
+typedef enum GsStatus {
+  GS_STATUS_SOLVED,
+  GS_STATUS_PARTIAL,
+  GS_STATUS_STEP_LIMIT,
+  GS_STATUS_PASS_LIMIT,
+  GS_STATUS_TIME_LIMIT,
+  GS_STATUS_CANCELLED,
+} GsStatus;
+
+typedef enum GsCell {
+  GS_CELL_EMPTY,
+  GS_CELL_YES,
+  GS_CELL_NO,
+} GsCell;
+
+/**
+ * A parsed puzzle.
+ */
+typedef struct GsPuzzle GsPuzzle;
+
+/**
+ * The steps made solving a puzzle, and the resulting grid.
+ */
+typedef struct GsSolution GsSolution;
+
+/**
+ * Limits on solving, where 0 means no limit.
+ * `gs_solve` fails with `InvalidBudget` if the timeout is negative, NaN or
+ * too large.
+ */
+typedef struct GsBudget {
+  uintptr_t max_steps;
+  uintptr_t max_passes;
+  double timeout_seconds;
+} GsBudget;
+
+/**
+ * A step of a solution.
+ * The strings belong to the solution.
+ */
+typedef struct GsStep {
+  /**
+   * The labels of the cell which was set, the one of the lower category
+   * first.
+   */
+  const char *label1;
+  const char *label2;
+  /**
+   * Whether the labels were found to belong together.
+   */
+  bool yes;
+  /**
+   * Name of the rule, or kind of the clue, which made the step.
+   */
+  const char *rule;
+  /**
+   * Why the cell was set, in English, or "" if unknown.
+   */
+  const char *description;
+} GsStep;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Return the message of the last error on this thread, or NULL if there was
+ * none.
+ * The message is valid until the next error on this thread.
+ */
+const char *gs_last_error_message(void);
+
+/**
+ * Parse the puzzle in `text`, written in `format`, one of `GsFormat`, into
+ * `*out`.
+ * The puzzle belongs to the caller, who must free it with `gs_puzzle_free`.
+ */
+GsError gs_puzzle_parse(const char *text, uint32_t format, GsPuzzle **out);
+
+/**
+ * Free a puzzle returned by `gs_puzzle_parse`.
+ * Does nothing if `puzzle` is NULL.
+ */
+void gs_puzzle_free(GsPuzzle *puzzle);
+
+/**
+ * Return the number of categories of `puzzle`, or 0 if it is NULL.
+ */
+uintptr_t gs_puzzle_num_categories(const GsPuzzle *puzzle);
+
+/**
+ * Return the number of labels in each category of `puzzle`, or 0 if it is
+ * NULL.
+ */
+uintptr_t gs_puzzle_labels_per_category(const GsPuzzle *puzzle);
+
+/**
+ * Return the name of `category`, or NULL if it is out of range.
+ * The name belongs to the puzzle.
+ */
+const char *gs_puzzle_category_name(const GsPuzzle *puzzle, uintptr_t category);
+
+/**
+ * Return the name of the label at index `label` of `category`, or NULL if
+ * either is out of range.
+ * The name belongs to the puzzle.
+ */
+const char *gs_puzzle_label_name(const GsPuzzle *puzzle, uintptr_t category, uintptr_t label);
+
+/**
+ * Solve `puzzle` into `*out`, stopping early once `budget` is spent.
+ * `budget` may be NULL for no limits.
+ * The solution belongs to the caller, who must free it with
+ * `gs_solution_free`, and doesn't depend on `puzzle` staying alive.
+ */
+GsError gs_solve(const GsPuzzle *puzzle, const GsBudget *budget, GsSolution **out);
+
+/**
+ * Free a solution returned by `gs_solve`.
+ * Does nothing if `solution` is NULL.
+ */
+void gs_solution_free(GsSolution *solution);
+
+/**
+ * Read whether every label was matched, or why the solver stopped, into
+ * `*out`.
+ */
+GsError gs_solution_status(const GsSolution *solution, GsStatus *out);
+
+/**
+ * Return the number of steps of `solution`, or 0 if it is NULL.
+ */
+uintptr_t gs_solution_num_steps(const GsSolution *solution);
+
+/**
+ * Read the step at `index` of `solution` into `*out`.
+ * The strings of the step belong to the solution.
+ */
+GsError gs_solution_step(const GsSolution *solution, uintptr_t index, GsStep *out);
+
+/**
+ * Read the cell of the grid after every step of `solution` where the label
+ * at index `label1` of `category1` meets the one at index `label2` of
+ * `category2` into `*out`.
+ */
+GsError gs_solution_cell(const GsSolution *solution,
+                         uintptr_t category1,
+                         uintptr_t label1,
+                         uintptr_t category2,
+                         uintptr_t label2,
+                         GsCell *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GRIDSOLVE_H */
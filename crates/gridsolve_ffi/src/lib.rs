@@ -0,0 +1,484 @@
+//! C API of gridsolve, declared in `include/gridsolve.h`.
+//!
+//! Objects returned through an out-parameter belong to the caller, who must
+//! release them with the matching `_free` function.
+//! A `GsSolution` copies everything it needs from its puzzle, so the puzzle
+//! may be freed before it.
+//! Strings returned by the API are NUL-terminated UTF-8, borrowed from the
+//! object they were read from and valid until that object is freed.
+//!
+//! # Safety
+//!
+//! Every pointer passed to the API must be NULL or valid: objects as returned
+//! by the API and not yet freed, strings NUL-terminated and out-parameters
+//! writable.
+#![allow(clippy::missing_safety_doc)]
+
+use gridsolve::{
+    try_solve_within, Budget, Category, Cell, Label, Locale, Puzzle, PuzzleFormat, Status,
+};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+/// Result of a call to the API.
+/// On failure, `gs_last_error_message` describes what went wrong.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GsError {
+    Ok = 0,
+
+    /// A required pointer was NULL.
+    NullArgument,
+
+    /// A string wasn't valid UTF-8.
+    InvalidUtf8,
+
+    /// The puzzle couldn't be parsed.
+    Parse,
+
+    /// The clues of the puzzle are contradictory.
+    Contradiction,
+
+    /// An index was out of range.
+    OutOfRange,
+
+    /// gridsolve panicked, which is a bug.
+    Panic,
+
+    /// The timeout of a budget wasn't a number of seconds.
+    InvalidBudget,
+
+    /// An argument wasn't one of the values it can take.
+    InvalidArgument,
+}
+
+/// Formats in which a puzzle can be written.
+/// Functions take them as a `uint32_t`, so that any value passed from C is
+/// checked instead of being undefined behaviour.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GsFormat {
+    /// The line-oriented `[Categories]`/`[Clues]` format.
+    Text = 0,
+    Json = 1,
+    Toml = 2,
+}
+
+impl GsFormat {
+    /// Return the format with the value `format`, if there is one.
+    fn from_raw(format: u32) -> Option<GsFormat> {
+        [GsFormat::Text, GsFormat::Json, GsFormat::Toml]
+            .iter()
+            .copied()
+            .find(|&f| f as u32 == format)
+    }
+}
+
+/// How far the solver got, see `gridsolve::Status`.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GsStatus {
+    Solved,
+    Partial,
+    StepLimit,
+    PassLimit,
+    TimeLimit,
+    Cancelled,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GsCell {
+    Empty,
+    Yes,
+    No,
+}
+
+/// Limits on solving, where 0 means no limit.
+/// `gs_solve` fails with `InvalidBudget` if the timeout is negative, NaN or
+/// too large.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct GsBudget {
+    pub max_steps: usize,
+    pub max_passes: usize,
+    pub timeout_seconds: f64,
+}
+
+/// A step of a solution.
+/// The strings belong to the solution.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct GsStep {
+    /// The labels of the cell which was set, the one of the lower category
+    /// first.
+    pub label1: *const c_char,
+    pub label2: *const c_char,
+
+    /// Whether the labels were found to belong together.
+    pub yes: bool,
+
+    /// Name of the rule, or kind of the clue, which made the step.
+    pub rule: *const c_char,
+
+    /// Why the cell was set, in English, or "" if unknown.
+    pub description: *const c_char,
+}
+
+/// A parsed puzzle.
+pub struct GsPuzzle {
+    puzzle: Puzzle,
+
+    /// Names of the categories, in order.
+    categories: Vec<CString>,
+
+    /// Names of the labels of every category, in order.
+    labels: Vec<CString>,
+}
+
+/// The steps made solving a puzzle, and the resulting grid.
+pub struct GsSolution {
+    status: GsStatus,
+    steps: Vec<StepStrings>,
+    num_categories: usize,
+    labels_per_category: usize,
+
+    /// Every cell of the grid, indexed by the positions of its labels in
+    /// `GsPuzzle::labels`.
+    cells: Vec<GsCell>,
+}
+
+struct StepStrings {
+    label1: CString,
+    label2: CString,
+    yes: bool,
+    rule: CString,
+    description: CString,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap()
+}
+
+/// Record `message` as the last error of this thread and return `error`.
+fn fail(error: GsError, message: &str) -> GsError {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(c_string(message)));
+    error
+}
+
+/// Run `f`, reporting a panic as `GsError::Panic` rather than unwinding into
+/// C.
+fn catch<F: FnOnce() -> GsError>(f: F) -> GsError {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| fail(GsError::Panic, "gridsolve panicked"))
+}
+
+/// Return the message of the last error on this thread, or NULL if there was
+/// none.
+/// The message is valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn gs_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Parse the puzzle in `text`, written in `format`, one of `GsFormat`, into
+/// `*out`.
+/// The puzzle belongs to the caller, who must free it with `gs_puzzle_free`.
+#[no_mangle]
+pub unsafe extern "C" fn gs_puzzle_parse(
+    text: *const c_char,
+    format: u32,
+    out: *mut *mut GsPuzzle,
+) -> GsError {
+    if text.is_null() || out.is_null() {
+        return fail(GsError::NullArgument, "text and out must not be NULL");
+    }
+    let text = match CStr::from_ptr(text).to_str() {
+        Ok(text) => text,
+        Err(error) => return fail(GsError::InvalidUtf8, &error.to_string()),
+    };
+    let format = match GsFormat::from_raw(format) {
+        Some(GsFormat::Text) => PuzzleFormat::Text,
+        Some(GsFormat::Json) => PuzzleFormat::Json,
+        Some(GsFormat::Toml) => PuzzleFormat::Toml,
+        None => {
+            let message = format!("{} is not a GsFormat", format);
+            return fail(GsError::InvalidArgument, &message);
+        }
+    };
+    catch(|| match Puzzle::parse_as(text, format) {
+        Ok(puzzle) => {
+            let categories = puzzle
+                .categories()
+                .map(|c| c_string(puzzle.lookup_category(c)))
+                .collect();
+            let labels = puzzle
+                .labels()
+                .map(|l| c_string(puzzle.lookup_label(l)))
+                .collect();
+            *out = Box::into_raw(Box::new(GsPuzzle {
+                puzzle,
+                categories,
+                labels,
+            }));
+            GsError::Ok
+        }
+        Err(error) => fail(GsError::Parse, &error.to_string()),
+    })
+}
+
+/// Free a puzzle returned by `gs_puzzle_parse`.
+/// Does nothing if `puzzle` is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gs_puzzle_free(puzzle: *mut GsPuzzle) {
+    if !puzzle.is_null() {
+        drop(Box::from_raw(puzzle));
+    }
+}
+
+/// Return the number of categories of `puzzle`, or 0 if it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gs_puzzle_num_categories(puzzle: *const GsPuzzle) -> usize {
+    puzzle.as_ref().map_or(0, |p| p.puzzle.num_categories())
+}
+
+/// Return the number of labels in each category of `puzzle`, or 0 if it is
+/// NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gs_puzzle_labels_per_category(puzzle: *const GsPuzzle) -> usize {
+    puzzle
+        .as_ref()
+        .map_or(0, |p| p.puzzle.labels_per_category())
+}
+
+/// Return the name of `category`, or NULL if it is out of range.
+/// The name belongs to the puzzle.
+#[no_mangle]
+pub unsafe extern "C" fn gs_puzzle_category_name(
+    puzzle: *const GsPuzzle,
+    category: usize,
+) -> *const c_char {
+    puzzle
+        .as_ref()
+        .and_then(|p| p.categories.get(category))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Return the name of the label at index `label` of `category`, or NULL if
+/// either is out of range.
+/// The name belongs to the puzzle.
+#[no_mangle]
+pub unsafe extern "C" fn gs_puzzle_label_name(
+    puzzle: *const GsPuzzle,
+    category: usize,
+    label: usize,
+) -> *const c_char {
+    puzzle
+        .as_ref()
+        .filter(|p| category < p.categories.len() && label < p.puzzle.labels_per_category())
+        .and_then(|p| {
+            p.labels
+                .get(category * p.puzzle.labels_per_category() + label)
+        })
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Solve `puzzle` into `*out`, stopping early once `budget` is spent.
+/// `budget` may be NULL for no limits.
+/// The solution belongs to the caller, who must free it with
+/// `gs_solution_free`, and doesn't depend on `puzzle` staying alive.
+#[no_mangle]
+pub unsafe extern "C" fn gs_solve(
+    puzzle: *const GsPuzzle,
+    budget: *const GsBudget,
+    out: *mut *mut GsSolution,
+) -> GsError {
+    let puzzle = match puzzle.as_ref() {
+        Some(puzzle) if !out.is_null() => &puzzle.puzzle,
+        _ => return fail(GsError::NullArgument, "puzzle and out must not be NULL"),
+    };
+    catch(|| {
+        let budget = match budget.as_ref() {
+            Some(b) => Budget {
+                max_steps: Some(b.max_steps).filter(|&n| n > 0),
+                max_passes: Some(b.max_passes).filter(|&n| n > 0),
+                time_limit: match b.timeout_seconds {
+                    0.0 => None,
+                    s => match Duration::try_from_secs_f64(s) {
+                        Ok(timeout) => Some(timeout),
+                        Err(_) => {
+                            return fail(
+                                GsError::InvalidBudget,
+                                &format!("timeout is not a number of seconds: {}", s),
+                            )
+                        }
+                    },
+                },
+                ..Budget::default()
+            },
+            None => Budget::default(),
+        };
+        solve(puzzle, &budget, out)
+    })
+}
+
+/// Solve `puzzle` within `budget` into `*out`, for `gs_solve`.
+unsafe fn solve(puzzle: &Puzzle, budget: &Budget, out: *mut *mut GsSolution) -> GsError {
+    match try_solve_within(puzzle, budget) {
+        Ok(solution) => {
+            let locale = Locale::default();
+            let steps = solution
+                .steps
+                .iter()
+                .map(|step| StepStrings {
+                    label1: c_string(step.label1),
+                    label2: c_string(step.label2),
+                    yes: step.yes,
+                    rule: c_string(step.rule),
+                    description: c_string(&locale.describe(step, puzzle)),
+                })
+                .collect();
+            let grid = solution.grid(solution.steps.len());
+            let labels = puzzle.labels().collect::<Vec<_>>();
+            let cells = labels
+                .iter()
+                .flat_map(|&x| labels.iter().map(move |&y| (x, y)))
+                .map(|(x, y)| match *grid.at(x, y) {
+                    Cell::Empty => GsCell::Empty,
+                    Cell::Yes => GsCell::Yes,
+                    Cell::No => GsCell::No,
+                })
+                .collect();
+            *out = Box::into_raw(Box::new(GsSolution {
+                status: match solution.status {
+                    Status::Solved => GsStatus::Solved,
+                    Status::Partial => GsStatus::Partial,
+                    Status::StepLimit => GsStatus::StepLimit,
+                    Status::PassLimit => GsStatus::PassLimit,
+                    Status::TimeLimit => GsStatus::TimeLimit,
+                    Status::Cancelled => GsStatus::Cancelled,
+                },
+                steps,
+                num_categories: puzzle.num_categories(),
+                labels_per_category: puzzle.labels_per_category(),
+                cells,
+            }));
+            GsError::Ok
+        }
+        Err(contradiction) => {
+            let message = match contradiction.clue {
+                Some(index) => format!(
+                    "clues are contradictory, as found by clue {}",
+                    puzzle.constraints()[index].name
+                ),
+                None => "clues are contradictory".to_string(),
+            };
+            fail(GsError::Contradiction, &message)
+        }
+    }
+}
+
+/// Free a solution returned by `gs_solve`.
+/// Does nothing if `solution` is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gs_solution_free(solution: *mut GsSolution) {
+    if !solution.is_null() {
+        drop(Box::from_raw(solution));
+    }
+}
+
+/// Read whether every label was matched, or why the solver stopped, into
+/// `*out`.
+#[no_mangle]
+pub unsafe extern "C" fn gs_solution_status(
+    solution: *const GsSolution,
+    out: *mut GsStatus,
+) -> GsError {
+    match solution.as_ref() {
+        Some(solution) if !out.is_null() => {
+            *out = solution.status;
+            GsError::Ok
+        }
+        _ => fail(GsError::NullArgument, "solution and out must not be NULL"),
+    }
+}
+
+/// Return the number of steps of `solution`, or 0 if it is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gs_solution_num_steps(solution: *const GsSolution) -> usize {
+    solution.as_ref().map_or(0, |s| s.steps.len())
+}
+
+/// Read the step at `index` of `solution` into `*out`.
+/// The strings of the step belong to the solution.
+#[no_mangle]
+pub unsafe extern "C" fn gs_solution_step(
+    solution: *const GsSolution,
+    index: usize,
+    out: *mut GsStep,
+) -> GsError {
+    let solution = match solution.as_ref() {
+        Some(solution) if !out.is_null() => solution,
+        _ => return fail(GsError::NullArgument, "solution and out must not be NULL"),
+    };
+    match solution.steps.get(index) {
+        Some(step) => {
+            *out = GsStep {
+                label1: step.label1.as_ptr(),
+                label2: step.label2.as_ptr(),
+                yes: step.yes,
+                rule: step.rule.as_ptr(),
+                description: step.description.as_ptr(),
+            };
+            GsError::Ok
+        }
+        None => fail(
+            GsError::OutOfRange,
+            &format!(
+                "step {} is out of range, there are {}",
+                index,
+                solution.steps.len()
+            ),
+        ),
+    }
+}
+
+/// Read the cell of the grid after every step of `solution` where the label
+/// at index `label1` of `category1` meets the one at index `label2` of
+/// `category2` into `*out`.
+#[no_mangle]
+pub unsafe extern "C" fn gs_solution_cell(
+    solution: *const GsSolution,
+    category1: usize,
+    label1: usize,
+    category2: usize,
+    label2: usize,
+    out: *mut GsCell,
+) -> GsError {
+    let solution = match solution.as_ref() {
+        Some(solution) if !out.is_null() => solution,
+        _ => return fail(GsError::NullArgument, "solution and out must not be NULL"),
+    };
+    let n = solution.labels_per_category;
+    let in_range = |c: usize, l: usize| c < solution.num_categories && l < n;
+    if !in_range(category1, label1) || !in_range(category2, label2) {
+        return fail(GsError::OutOfRange, "label out of range");
+    }
+    let index = |c: usize, l: usize| Label::new(Category(c), l);
+    let (x, y) = (index(category1, label1), index(category2, label2));
+    let position = |l: Label| l.category.0 * n + l.label;
+    *out = solution.cells[position(x) * n * solution.num_categories + position(y)];
+    GsError::Ok
+}
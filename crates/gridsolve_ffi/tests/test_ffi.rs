@@ -0,0 +1,161 @@
+extern crate gridsolve_ffi;
+
+use gridsolve_ffi::*;
+use std::ffi::{CStr, CString};
+use std::path::PathBuf;
+use std::ptr;
+
+fn parse(text: &str) -> Result<*mut GsPuzzle, (GsError, String)> {
+    parse_as(text, GsFormat::Text)
+}
+
+fn parse_as(text: &str, format: GsFormat) -> Result<*mut GsPuzzle, (GsError, String)> {
+    let text = CString::new(text).unwrap();
+    let mut puzzle = ptr::null_mut();
+    unsafe {
+        match gs_puzzle_parse(text.as_ptr(), format as u32, &mut puzzle) {
+            GsError::Ok => Ok(puzzle),
+            error => Err((error, last_error())),
+        }
+    }
+}
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(gs_last_error_message()) }
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn status(solution: *const GsSolution) -> GsStatus {
+    let mut status = GsStatus::Partial;
+    assert_eq!(
+        unsafe { gs_solution_status(solution, &mut status) },
+        GsError::Ok
+    );
+    status
+}
+
+fn string(s: *const std::os::raw::c_char) -> String {
+    unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_solve() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../puzzles/simple.txt");
+    let text = std::fs::read_to_string(path).unwrap();
+    let puzzle = parse(&text).unwrap();
+    unsafe {
+        assert_eq!(gs_puzzle_num_categories(puzzle), 3);
+        assert_eq!(string(gs_puzzle_category_name(puzzle, 1)), "Country");
+        assert_eq!(string(gs_puzzle_label_name(puzzle, 1, 2)), "United States");
+        assert!(gs_puzzle_label_name(puzzle, 1, 3).is_null());
+
+        let mut solution = ptr::null_mut();
+        assert_eq!(gs_solve(puzzle, ptr::null(), &mut solution), GsError::Ok);
+        // The solution doesn't borrow from the puzzle.
+        gs_puzzle_free(puzzle);
+        assert_eq!(status(solution), GsStatus::Solved);
+
+        let steps = gs_solution_num_steps(solution);
+        assert!(steps > 0);
+        let mut step = std::mem::zeroed::<GsStep>();
+        assert_eq!(gs_solution_step(solution, 0, &mut step), GsError::Ok);
+        assert_eq!(string(step.label1), "United States");
+        assert_eq!(string(step.label2), "1946");
+        assert!(step.yes);
+        assert_eq!(string(step.rule), "yes");
+        assert_ne!(string(step.description), "");
+        assert_eq!(
+            gs_solution_step(solution, steps, &mut step),
+            GsError::OutOfRange
+        );
+
+        let mut cell = GsCell::Empty;
+        assert_eq!(
+            gs_solution_cell(solution, 1, 2, 2, 0, &mut cell),
+            GsError::Ok
+        );
+        assert_eq!(cell, GsCell::Yes);
+        assert_eq!(
+            gs_solution_cell(solution, 2, 0, 1, 2, &mut cell),
+            GsError::Ok
+        );
+        assert_eq!(cell, GsCell::Yes);
+        assert_eq!(
+            gs_solution_cell(solution, 3, 0, 1, 2, &mut cell),
+            GsError::OutOfRange
+        );
+        gs_solution_free(solution);
+
+        let budget = GsBudget {
+            max_steps: 3,
+            max_passes: 0,
+            timeout_seconds: 0.0,
+        };
+        let puzzle = parse(&text).unwrap();
+        let mut solution = ptr::null_mut();
+        assert_eq!(gs_solve(puzzle, &budget, &mut solution), GsError::Ok);
+        assert_eq!(status(solution), GsStatus::StepLimit);
+        assert_eq!(gs_solution_num_steps(solution), 3);
+        gs_solution_free(solution);
+        gs_puzzle_free(puzzle);
+    }
+}
+
+#[test]
+fn test_errors() {
+    let (error, message) = parse("[Categories]\nx").unwrap_err();
+    assert_eq!(error, GsError::Parse);
+    assert!(message.contains("Missing [Clues] marker"), "{}", message);
+    let json = r#"{"categories": [{"name": "A", "labels": []}, {"name": "B", "labels": []}]}"#;
+    let (error, message) = parse_as(json, GsFormat::Json).unwrap_err();
+    assert_eq!(error, GsError::Parse);
+    assert!(message.contains("has no labels"), "{}", message);
+    let text = CString::new("[Categories]\n[Clues]\n").unwrap();
+    let mut puzzle = ptr::null_mut();
+    let error = unsafe { gs_puzzle_parse(text.as_ptr(), 3, &mut puzzle) };
+    assert_eq!(error, GsError::InvalidArgument);
+    assert!(puzzle.is_null());
+    assert_eq!(last_error(), "3 is not a GsFormat");
+
+    let categories = "[Categories]\nA\na1\na2\n\nB\nb1\nb2\n\n[Clues]\n";
+    let puzzle = parse(&format!("{}1,yes,a1,b1\n2,yes,a1,b2\n", categories)).unwrap();
+    unsafe {
+        let mut solution = ptr::null_mut();
+        assert_eq!(
+            gs_solve(puzzle, ptr::null(), &mut solution),
+            GsError::Contradiction
+        );
+        assert!(solution.is_null());
+        assert!(last_error().starts_with("clues are contradictory"));
+        gs_puzzle_free(puzzle);
+
+        let puzzle = parse(categories).unwrap();
+        for timeout in [-1.0, f64::NAN, f64::INFINITY, 1e300] {
+            let budget = GsBudget {
+                max_steps: 0,
+                max_passes: 0,
+                timeout_seconds: timeout,
+            };
+            assert_eq!(
+                gs_solve(puzzle, &budget, &mut solution),
+                GsError::InvalidBudget
+            );
+            assert!(solution.is_null());
+        }
+        gs_puzzle_free(puzzle);
+
+        assert_eq!(
+            gs_solve(ptr::null(), ptr::null(), &mut solution),
+            GsError::NullArgument
+        );
+        let mut status = GsStatus::Solved;
+        assert_eq!(
+            gs_solution_status(ptr::null(), &mut status),
+            GsError::NullArgument
+        );
+        gs_puzzle_free(ptr::null_mut());
+        gs_solution_free(ptr::null_mut());
+    }
+}
@@ -0,0 +1,261 @@
+//! What the server knows about an open puzzle file: the puzzle parsed from
+//! it, the diagnostics, and where each name is written in it.
+//!
+//! Lines are classified and split into names the same way as by the parser,
+//! but leniently, since any mistakes in them are reported by the diagnostics.
+
+use gridsolve::{comment, quote, tokenize_line, Answer, Diagnostic, Puzzle, Token};
+use lsp_types::{Position, Range, TextEdit};
+
+/// The sections of a puzzle file, each starting at its marker line.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Section {
+    /// Before the `[Categories]` marker, where everything is ignored.
+    Preamble,
+    Categories,
+    Clues,
+    Solution,
+}
+
+/// What a line of the document holds.
+#[derive(Debug)]
+pub enum Item {
+    /// A section marker, a comment, a blank line or ignored text.
+    Nothing,
+
+    /// The name of a category.
+    Category(Token),
+
+    /// A label, along with the name of its category.
+    Label { category: String, field: Token },
+
+    /// A clue, with the index in `Puzzle::constraints` of its constraint if
+    /// it was valid.
+    Clue {
+        fields: Vec<Token>,
+        constraint: Option<usize>,
+    },
+
+    /// A row of the answer table.
+    Row(Vec<Token>),
+}
+
+#[derive(Debug)]
+pub struct Line {
+    /// Byte offsets of the line in the document, without its terminator.
+    pub start: usize,
+    pub end: usize,
+
+    pub section: Section,
+    pub item: Item,
+}
+
+impl Line {
+    /// The comma-separated names of a clue or a row of the answer table.
+    pub fn fields(&self) -> &[Token] {
+        match &self.item {
+            Item::Clue { fields, .. } | Item::Row(fields) => fields,
+            _ => &[],
+        }
+    }
+
+    /// Return the index of the comma-separated name at byte offset `offset`,
+    /// along with the name.
+    pub fn field_at(&self, offset: usize) -> Option<(usize, &Token)> {
+        self.fields()
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, field)| field.from <= offset)
+    }
+}
+
+#[derive(Debug)]
+pub struct Document {
+    pub text: String,
+
+    /// Everything in the document which could be parsed.
+    pub puzzle: Puzzle,
+
+    pub diagnostics: Vec<Diagnostic>,
+    pub lines: Vec<Line>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Document {
+        let (puzzle, diagnostics) = Puzzle::parse_partial(&text);
+        let mut lines = vec![];
+        let mut section = Section::Preamble;
+        let mut category: Option<String> = None;
+        let mut clues = 0;
+        let mut start = 0;
+        for (i, raw) in text.split('\n').enumerate() {
+            let line = raw.strip_suffix('\r').unwrap_or(raw);
+            let trimmed = line.trim();
+            let marker = match (section, trimmed) {
+                (Section::Preamble, "[Categories]") => Some(Section::Categories),
+                (Section::Categories, "[Clues]") => Some(Section::Clues),
+                (Section::Clues, "[Solution]") => Some(Section::Solution),
+                _ => None,
+            };
+            let item = if let Some(marker) = marker {
+                section = marker;
+                Item::Nothing
+            } else if comment(line).is_some() {
+                Item::Nothing
+            } else if trimmed.is_empty() {
+                // Categories are separated by blank lines.
+                category = None;
+                Item::Nothing
+            } else {
+                match section {
+                    Section::Preamble => Item::Nothing,
+                    Section::Categories => {
+                        let field = tokenize_line(line, start, i + 1, false).remove(0);
+                        match &category {
+                            Some(category) => Item::Label {
+                                category: category.clone(),
+                                field,
+                            },
+                            None => {
+                                category = Some(field.text.clone());
+                                Item::Category(field)
+                            }
+                        }
+                    }
+                    Section::Clues => {
+                        let valid = !diagnostics.iter().any(|d| d.span.line == i + 1);
+                        let constraint = if valid {
+                            clues += 1;
+                            Some(clues - 1).filter(|&c| c < puzzle.constraints().len())
+                        } else {
+                            None
+                        };
+                        Item::Clue {
+                            fields: tokenize_line(line, start, i + 1, true),
+                            constraint,
+                        }
+                    }
+                    Section::Solution => Item::Row(tokenize_line(line, start, i + 1, true)),
+                }
+            };
+            lines.push(Line {
+                start,
+                end: start + line.len(),
+                section,
+                item,
+            });
+            start += raw.len() + 1;
+        }
+        Document {
+            text,
+            puzzle,
+            diagnostics,
+            lines,
+        }
+    }
+
+    /// Return the index of the line containing byte offset `offset`.
+    fn line_index(&self, offset: usize) -> usize {
+        self.lines.partition_point(|line| line.start <= offset) - 1
+    }
+
+    /// Return the line containing byte offset `offset`.
+    pub fn line_at(&self, offset: usize) -> &Line {
+        &self.lines[self.line_index(offset)]
+    }
+
+    /// Convert `position`, in UTF-16 code units, to a byte offset.
+    /// Positions past the end of their line are moved to the end.
+    pub fn offset(&self, position: Position) -> usize {
+        let line = match self.lines.get(position.line as usize) {
+            Some(line) => line,
+            None => return self.text.len(),
+        };
+        let mut units = 0;
+        for (i, c) in self.text[line.start..line.end].char_indices() {
+            if units >= position.character as usize {
+                return line.start + i;
+            }
+            units += c.len_utf16();
+        }
+        line.end
+    }
+
+    /// Convert byte offset `offset` to a position in UTF-16 code units.
+    pub fn position(&self, offset: usize) -> Position {
+        let index = self.line_index(offset);
+        let line = &self.lines[index];
+        let character = self.text[line.start..offset.min(line.end)]
+            .encode_utf16()
+            .count();
+        Position::new(index as u32, character as u32)
+    }
+
+    /// Convert the byte offsets `start` to `end` to a range.
+    pub fn range(&self, start: usize, end: usize) -> Range {
+        Range::new(self.position(start), self.position(end))
+    }
+
+    /// Find where the label or category referred to by `name` is defined.
+    pub fn definition(&self, name: &str) -> Option<&Token> {
+        let puzzle = &self.puzzle;
+        if let Ok(label) = puzzle.label(name) {
+            let category = puzzle.lookup_category(label.category);
+            let label = puzzle.lookup_label(label);
+            self.lines.iter().find_map(|line| match &line.item {
+                Item::Label { category: c, field } if c == category && field.text == label => {
+                    Some(field)
+                }
+                _ => None,
+            })
+        } else if let Ok(category) = puzzle.category(name) {
+            let category = puzzle.lookup_category(category);
+            self.lines.iter().find_map(|line| match &line.item {
+                Item::Category(field) if field.text == category => Some(field),
+                _ => None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Return the edit which writes `answer` into the `[Solution]` section,
+    /// replacing anything already there, or adds the section if there is
+    /// none.
+    pub fn solution_edit(&self, answer: &Answer) -> TextEdit {
+        let table = answer.table(&self.puzzle);
+        let end = self.position(self.text.len());
+        let marker = self
+            .lines
+            .iter()
+            .position(|line| line.section == Section::Solution);
+        match marker.and_then(|marker| self.lines.get(marker + 1)) {
+            Some(first) => TextEdit::new(Range::new(self.position(first.start), end), table),
+            None => {
+                // Keep one blank line between the clues and the solution.
+                let separator = if marker.is_some() || self.text.ends_with("\n\n") {
+                    ""
+                } else if self.text.ends_with('\n') {
+                    "\n"
+                } else {
+                    "\n\n"
+                };
+                let marker = if marker.is_some() {
+                    "\n"
+                } else {
+                    "[Solution]\n"
+                };
+                TextEdit::new(
+                    Range::new(end, end),
+                    format!("{}{}{}", separator, marker, table),
+                )
+            }
+        }
+    }
+}
+
+/// Quote `name` as a name in a clue or a row of the answer table.
+pub fn quote_field(name: &str) -> String {
+    quote(name, true).into_owned()
+}
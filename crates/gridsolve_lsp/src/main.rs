@@ -0,0 +1,469 @@
+extern crate gridsolve;
+
+mod document;
+
+use document::{quote_field, Document, Item};
+use gridsolve::{
+    clue_kind, try_solve_within, Answer, Budget, ClueKind, Locale, ParamKind, Status, CLUE_KINDS,
+};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics, ShowMessage,
+};
+use lsp_types::request::{
+    ApplyWorkspaceEdit, CodeActionRequest, Completion, ExecuteCommand, GotoDefinition,
+    HoverRequest, Request as _,
+};
+use lsp_types::{
+    ApplyWorkspaceEditParams, CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, Command, CompletionItem, CompletionItemKind,
+    CompletionOptions, CompletionParams, CompletionResponse, CompletionTextEdit,
+    DiagnosticSeverity, ExecuteCommandOptions, ExecuteCommandParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    InitializeParams, Location, MarkupContent, MarkupKind, MessageType, OneOf,
+    PublishDiagnosticsParams, ServerCapabilities, ShowMessageParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+/// Command which solves the puzzle in the document given as its argument.
+const SOLVE_COMMAND: &str = "gridsolve.solve";
+
+/// How long the solve command may run, since no other request is handled
+/// while it does.
+const SOLVE_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![",".to_string()]),
+            ..CompletionOptions::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![SOLVE_COMMAND.to_string()],
+            ..ExecuteCommandOptions::default()
+        }),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// How a clue kind is written, e.g. "after, x, C, y".
+fn signature(kind: &ClueKind) -> String {
+    let mut signature = kind.keyword.to_string();
+    for param in kind.params {
+        signature.push_str(", ");
+        signature.push_str(param.name);
+    }
+    if kind.variadic {
+        signature.push_str(", ...");
+    }
+    signature
+}
+
+fn plain_text(value: String) -> HoverContents {
+    HoverContents::Markup(MarkupContent {
+        kind: MarkupKind::PlainText,
+        value,
+    })
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    documents: HashMap<Url, Document>,
+
+    /// Locale of the client, in which clues are described.
+    locale: Locale,
+
+    /// ID of the next request sent to the client.
+    next_id: i32,
+}
+
+impl<'c> Server<'c> {
+    fn run(&mut self) -> Result<()> {
+        let connection = self.connection;
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                // Nothing is done with the result of applying an edit.
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        let Request { id, method, params } = request;
+        let result = match method.as_str() {
+            Completion::METHOD => call(params, |p| self.completion(p)),
+            HoverRequest::METHOD => call(params, |p| self.hover(p)),
+            GotoDefinition::METHOD => call(params, |p| self.definition(p)),
+            CodeActionRequest::METHOD => call(params, |p| self.code_action(p)),
+            ExecuteCommand::METHOD => call(params, |p| self.execute_command(p)),
+            _ => {
+                let message = format!("unknown request: {}", method);
+                return Response::new_err(id, ErrorCode::MethodNotFound as i32, message);
+            }
+        };
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.update(document.uri, document.text, document.version)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // With full sync, the last change holds the whole text.
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(
+                        params.text_document.uri,
+                        change.text,
+                        params.text_document.version,
+                    ),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(uri, vec![], None))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) -> Result<()> {
+        let notification = Notification::new(N::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> Result<()> {
+        let id = RequestId::from(self.next_id);
+        self.next_id += 1;
+        let request = Request::new(id, R::METHOD.to_string(), params);
+        self.connection.sender.send(request.into())?;
+        Ok(())
+    }
+
+    /// Parse the new `text` of the document at `uri` and publish its
+    /// diagnostics.
+    fn update(&mut self, uri: Url, text: String, version: i32) -> Result<()> {
+        let document = Document::new(text);
+        let diagnostics = document
+            .diagnostics
+            .iter()
+            .map(|d| lsp_types::Diagnostic {
+                range: document.range(d.span.start, d.span.end),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("gridsolve".to_string()),
+                message: match &d.suggestion {
+                    Some(suggestion) => format!("{} ({})", d.error, suggestion),
+                    None => d.error.to_string(),
+                },
+                ..lsp_types::Diagnostic::default()
+            })
+            .collect();
+        self.documents.insert(uri.clone(), document);
+        self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+            uri,
+            diagnostics,
+            Some(version),
+        ))
+    }
+
+    /// Complete the clue kind, label or category expected where the cursor
+    /// is in a clue, or the label in a row of the answer table.
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let puzzle = &document.puzzle;
+        let offset = document.offset(position.position);
+        let line = document.line_at(offset);
+        let (index, field) = line.field_at(offset)?;
+        let range = document.range(field.span.start.min(offset), field.span.end.max(offset));
+        let item = |label: String, kind, detail: String, text: String| CompletionItem {
+            label,
+            kind: Some(kind),
+            detail: Some(detail),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, text))),
+            ..CompletionItem::default()
+        };
+        let labels = || {
+            puzzle.labels().map(|l| {
+                let reference = puzzle.label_reference(l);
+                let category = puzzle.lookup_category(l.category).to_string();
+                let text = quote_field(&reference);
+                item(reference, CompletionItemKind::ENUM_MEMBER, category, text)
+            })
+        };
+        let categories = || {
+            puzzle.categories().map(|c| {
+                let name = puzzle.lookup_category(c).to_string();
+                let text = quote_field(&name);
+                item(name, CompletionItemKind::ENUM, "category".to_string(), text)
+            })
+        };
+
+        let items = match &line.item {
+            Item::Clue { .. } if index == 1 => CLUE_KINDS
+                .iter()
+                .map(|kind| CompletionItem {
+                    documentation: Some(lsp_types::Documentation::String(
+                        kind.description.to_string(),
+                    )),
+                    ..item(
+                        kind.keyword.to_string(),
+                        CompletionItemKind::KEYWORD,
+                        signature(kind),
+                        kind.keyword.to_string(),
+                    )
+                })
+                .collect(),
+            Item::Clue { fields, .. } if index >= 2 => match clue_kind(&fields[1].text) {
+                Some(kind) => {
+                    let count = (fields.len() - 2).max(index - 1);
+                    match kind.param_kinds(count).get(index - 2)? {
+                        ParamKind::Label => labels().collect(),
+                        ParamKind::Category => categories().collect(),
+                        ParamKind::Number => return None,
+                    }
+                }
+                None => labels().chain(categories()).collect(),
+            },
+            Item::Row(_) => labels().collect(),
+            _ => return None,
+        };
+        Some(CompletionResponse::Array(items))
+    }
+
+    /// Show what the clue under the cursor means, or how its kind is written
+    /// if the clue isn't valid.
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let offset = document.offset(position.position);
+        let line = document.line_at(offset);
+        let (index, field) = line.field_at(offset)?;
+        match &line.item {
+            Item::Clue {
+                constraint: Some(c),
+                ..
+            } => {
+                let constraint = &document.puzzle.constraints()[*c];
+                Some(Hover {
+                    contents: plain_text(constraint.describe(&document.puzzle, self.locale)),
+                    range: Some(document.range(line.start, line.end)),
+                })
+            }
+            Item::Clue { .. } if index == 1 => {
+                let kind = clue_kind(&field.text)?;
+                Some(Hover {
+                    contents: plain_text(format!("{}\n\n{}", signature(kind), kind.description)),
+                    range: Some(document.range(field.span.start, field.span.end)),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Go from a label or category in a clue or a row of the answer table to
+    /// where it is defined in the `[Categories]` section.
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let document = self.documents.get(&uri)?;
+        let offset = document.offset(position.position);
+        let line = document.line_at(offset);
+        let (index, field) = line.field_at(offset)?;
+        if let Item::Clue { .. } = line.item {
+            // The name and kind of the clue don't refer to anything.
+            if index < 2 {
+                return None;
+            }
+        }
+        let definition = document.definition(&field.text)?;
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri,
+            document.range(definition.span.start, definition.span.end),
+        )))
+    }
+
+    /// Offer to apply the suggestion of each diagnostic in the range, and to
+    /// solve the puzzle if it has no errors.
+    fn code_action(&self, params: CodeActionParams) -> Option<CodeActionResponse> {
+        let uri = params.text_document.uri;
+        let document = self.documents.get(&uri)?;
+        let start = document.offset(params.range.start);
+        let end = document.offset(params.range.end);
+        let mut actions = vec![];
+        for diagnostic in &document.diagnostics {
+            let span = diagnostic.span;
+            let suggestion = match &diagnostic.suggestion {
+                Some(suggestion) if span.start <= end && start <= span.end => suggestion,
+                _ => continue,
+            };
+            let edit = TextEdit::new(
+                document.range(span.start, span.end),
+                quote_field(&suggestion.replacement),
+            );
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!(
+                    "Replace with {} `{}`",
+                    suggestion.kind, suggestion.replacement
+                ),
+                kind: Some(CodeActionKind::QUICKFIX),
+                edit: Some(workspace_edit(uri.clone(), edit)),
+                is_preferred: Some(true),
+                ..CodeAction::default()
+            }));
+        }
+        if document.diagnostics.is_empty() {
+            let title = "Solve puzzle".to_string();
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: title.clone(),
+                kind: Some(CodeActionKind::SOURCE),
+                command: Some(Command::new(
+                    title,
+                    SOLVE_COMMAND.to_string(),
+                    Some(vec![Value::String(uri.to_string())]),
+                )),
+                ..CodeAction::default()
+            }));
+        }
+        Some(actions)
+    }
+
+    fn execute_command(&mut self, params: ExecuteCommandParams) -> Option<Value> {
+        if params.command != SOLVE_COMMAND {
+            return None;
+        }
+        let uri = params
+            .arguments
+            .first()
+            .and_then(|uri| serde_json::from_value(uri.clone()).ok())?;
+        // Failing to reach the client isn't the fault of the request.
+        let _ = self.solve(uri);
+        Some(Value::Null)
+    }
+
+    /// Solve the puzzle in the document at `uri`, writing the answer into its
+    /// `[Solution]` section if it was solved, and tell the user how it went.
+    fn solve(&mut self, uri: Url) -> Result<()> {
+        let document = match self.documents.get(&uri) {
+            Some(document) => document,
+            None => return Ok(()),
+        };
+        let puzzle = &document.puzzle;
+        let mut edit = None;
+        let budget = Budget {
+            time_limit: Some(SOLVE_TIME_LIMIT),
+            ..Budget::default()
+        };
+        let (typ, message) = match try_solve_within(puzzle, &budget) {
+            Ok(solution) => {
+                let steps = solution.steps.len();
+                if solution.status == Status::Solved {
+                    edit = Answer::from_grid(&solution.grid(steps))
+                        .map(|answer| document.solution_edit(&answer));
+                    (MessageType::INFO, format!("Solved in {} steps.", steps))
+                } else {
+                    let message = format!("Stopped after {} steps: {}.", steps, solution.status);
+                    (MessageType::WARNING, message)
+                }
+            }
+            Err(contradiction) => {
+                let message = match contradiction.clue {
+                    Some(clue) => format!(
+                        "The clues contradict each other, as found by clue {}.",
+                        puzzle.constraints()[clue].name
+                    ),
+                    None => format!(
+                        "The clues contradict each other, as found by the {} rule.",
+                        contradiction.rule
+                    ),
+                };
+                (MessageType::ERROR, message)
+            }
+        };
+        if let Some(edit) = edit {
+            self.request::<ApplyWorkspaceEdit>(ApplyWorkspaceEditParams {
+                label: Some("Solve puzzle".to_string()),
+                edit: workspace_edit(uri, edit),
+            })?;
+        }
+        self.notify::<ShowMessage>(ShowMessageParams { typ, message })
+    }
+}
+
+fn workspace_edit(uri: Url, edit: TextEdit) -> WorkspaceEdit {
+    WorkspaceEdit {
+        changes: Some(HashMap::from([(uri, vec![edit])])),
+        ..WorkspaceEdit::default()
+    }
+}
+
+/// Call `handler` with the `params` of a request, returning its result as
+/// JSON.
+fn call<P: DeserializeOwned, R: serde::Serialize>(
+    params: Value,
+    handler: impl FnOnce(P) -> R,
+) -> serde_json::Result<Value> {
+    serde_json::to_value(handler(serde_json::from_value(params)?))
+}
+
+fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let params = connection.initialize(serde_json::to_value(capabilities())?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let locale = params
+        .locale
+        .as_deref()
+        .and_then(Locale::from_language_tag)
+        .unwrap_or_default();
+    Server {
+        connection: &connection,
+        documents: HashMap::new(),
+        locale,
+        next_id: 0,
+    }
+    .run()?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
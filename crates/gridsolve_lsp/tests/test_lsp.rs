@@ -0,0 +1,281 @@
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const URI: &str = "file:///puzzle.txt";
+
+/// A language server talking over its stdin and stdout, killed when dropped.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+
+    /// Messages from the server which haven't been looked at yet.
+    messages: VecDeque<Value>,
+}
+
+impl Client {
+    fn start() -> Client {
+        Client::start_with(json!({ "capabilities": {} }))
+    }
+
+    /// Start the server, initializing it with `params`.
+    fn start_with(params: Value) -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_gridsolve_lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut client = Client {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            next_id: 0,
+            messages: VecDeque::new(),
+        };
+        let result = client.request("initialize", params);
+        assert_eq!(result["capabilities"]["hoverProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Send a request and return the result of its response.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message["id"] == id && message.get("method").is_none() {
+                assert_eq!(message.get("error"), None);
+                return message["result"].clone();
+            }
+            self.messages.push_back(message);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Return the params of the next message from the server with `method`.
+    fn message(&mut self, method: &str) -> Value {
+        if let Some(i) = self.messages.iter().position(|m| m["method"] == method) {
+            return self.messages.remove(i).unwrap()["params"].clone();
+        }
+        loop {
+            let message = self.receive();
+            if message["method"] == method {
+                return message["params"].clone();
+            }
+            self.messages.push_back(message);
+        }
+    }
+
+    /// Open a document with `text`, returning its diagnostics.
+    fn open(&mut self, text: &str) -> Value {
+        let document = json!({ "uri": URI, "languageId": "gridsolve", "version": 1, "text": text });
+        self.notify("textDocument/didOpen", json!({ "textDocument": document }));
+        self.message("textDocument/publishDiagnostics")["diagnostics"].clone()
+    }
+
+    /// Send a request about the position `line`, `character` of the document.
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        let position = json!({ "line": line, "character": character });
+        self.request(
+            method,
+            json!({ "textDocument": { "uri": URI }, "position": position }),
+        )
+    }
+
+    fn code_actions(&mut self, start: Value, end: Value) -> Value {
+        let params = json!({
+            "textDocument": { "uri": URI },
+            "range": { "start": start, "end": end },
+            "context": { "diagnostics": [] },
+        });
+        self.request("textDocument/codeAction", params)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn simple() -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../puzzles/simple.txt");
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut client = Client::start();
+    let puzzle = simple().replace("Year of Birth,Germany", "Year of Birth,Germny");
+
+    let diagnostics = client.open(&puzzle);
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    let range = &diagnostics[0]["range"];
+    assert_eq!(range["start"], json!({ "line": 20, "character": 26 }));
+    assert_eq!(range["end"], json!({ "line": 20, "character": 32 }));
+    assert_eq!(
+        diagnostics[0]["message"],
+        "Label not found: Germny (did you mean label `Germany`?)"
+    );
+
+    // Only a quick fix is offered, since the puzzle can't be solved yet.
+    let actions = client.code_actions(range["start"].clone(), range["start"].clone());
+    assert_eq!(actions.as_array().unwrap().len(), 1);
+    assert_eq!(actions[0]["kind"], "quickfix");
+    let edit = &actions[0]["edit"]["changes"][URI][0];
+    assert_eq!(edit["newText"], "Germany");
+    assert_eq!(edit["range"], *range);
+
+    let change = json!({
+        "textDocument": { "uri": URI, "version": 2 },
+        "contentChanges": [{ "text": simple() }],
+    });
+    client.notify("textDocument/didChange", change);
+    let params = client.message("textDocument/publishDiagnostics");
+    assert_eq!(params["version"], 2);
+    assert_eq!(params["diagnostics"], json!([]));
+}
+
+#[test]
+fn test_navigation() {
+    let mut client = Client::start();
+    client.open(&simple());
+
+    let hover = client.at("textDocument/hover", 20, 3);
+    assert_eq!(
+        hover["contents"]["value"],
+        "In Year of Birth, Leo (First Name) appears somewhere after Germany (Country)."
+    );
+    assert_eq!(client.at("textDocument/hover", 0, 3), Value::Null);
+
+    // Clues are described in the locale of the client.
+    let mut french = Client::start_with(json!({ "capabilities": {}, "locale": "fr-FR" }));
+    french.open(&simple());
+    assert_eq!(
+        french.at("textDocument/hover", 20, 3)["contents"]["value"],
+        "Dans Year of Birth, Leo (First Name) apparaît quelque part après Germany (Country)."
+    );
+
+    // From "United States" in the first clue to its label.
+    let definition = client.at("textDocument/definition", 18, 8);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 9, "character": 0 })
+    );
+    assert_eq!(
+        definition["range"]["end"],
+        json!({ "line": 9, "character": 13 })
+    );
+    // From "Year of Birth" in the second clue to its category.
+    let definition = client.at("textDocument/definition", 20, 14);
+    assert_eq!(definition["range"]["start"]["line"], 11);
+    // The kind of a clue doesn't refer to anything.
+    assert_eq!(client.at("textDocument/definition", 20, 4), Value::Null);
+
+    let completion = client.at("textDocument/completion", 22, 3);
+    let keywords = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(keywords.contains(&"exactlyone"), "{:?}", keywords);
+    // The second argument of `after` is a category.
+    let completion = client.at("textDocument/completion", 20, 12);
+    let categories = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(categories, ["First Name", "Country", "Year of Birth"]);
+    let edit = &completion[0]["textEdit"];
+    assert_eq!(
+        edit["range"]["start"],
+        json!({ "line": 20, "character": 12 })
+    );
+    assert_eq!(edit["range"]["end"], json!({ "line": 20, "character": 25 }));
+
+    assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn test_solve() {
+    let mut client = Client::start();
+    let puzzle = simple();
+    let (clues, solution) = puzzle.split_once("[Solution]").unwrap();
+    client.open(clues);
+
+    let start = json!({ "line": 0, "character": 0 });
+    let actions = client.code_actions(start.clone(), start);
+    assert_eq!(actions[0]["title"], "Solve puzzle");
+    let command = actions[0]["command"].clone();
+    assert_eq!(command["arguments"], json!([URI]));
+    client.request("workspace/executeCommand", command);
+
+    let edit = client.message("workspace/applyEdit")["edit"]["changes"][URI][0].clone();
+    assert_eq!(edit["range"]["start"]["line"], 24);
+    assert_eq!(
+        edit["newText"].as_str().unwrap(),
+        format!("[Solution]{}", solution)
+    );
+    let message = client.message("window/showMessage");
+    assert_eq!(message["type"], 3);
+    assert!(message["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Solved in "));
+
+    let contradictory =
+        "[Categories]\nA\na1\na2\n\nB\nb1\nb2\n\n[Clues]\n1,yes,a1,b1\n2,no,a1,b1\n";
+    let change = json!({
+        "textDocument": { "uri": URI, "version": 2 },
+        "contentChanges": [{ "text": contradictory }],
+    });
+    client.notify("textDocument/didChange", change);
+    let command = json!({ "command": "gridsolve.solve", "arguments": [URI] });
+    client.request("workspace/executeCommand", command);
+    let message = client.message("window/showMessage");
+    assert_eq!(message["type"], 1);
+    assert_eq!(
+        message["message"],
+        "The clues contradict each other, as found by clue 2."
+    );
+}
@@ -0,0 +1,94 @@
+use gridsolve::PuzzleError;
+use serde_json::json;
+
+/// Reasons for which a request can fail.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no endpoint at {}", path)]
+    NotFound { path: String },
+    #[error("{} only accepts POST", path)]
+    MethodNotAllowed { path: String },
+    #[error("invalid request: {}", message)]
+    BadRequest { message: String },
+    #[error("request body is larger than {} bytes", limit)]
+    TooLarge { limit: usize },
+    #[error("{}", error)]
+    Puzzle { error: PuzzleError },
+    #[error("clues are contradictory")]
+    Contradiction {
+        /// Name of the clue being applied when the contradiction was found.
+        clue: Option<String>,
+    },
+    #[error("internal error, see the server's log")]
+    Internal,
+}
+
+impl Error {
+    pub fn bad_request(message: impl ToString) -> Error {
+        Error::BadRequest {
+            message: message.to_string(),
+        }
+    }
+
+    /// HTTP status code of the response reporting the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Error::NotFound { .. } => 404,
+            Error::MethodNotAllowed { .. } => 405,
+            Error::BadRequest { .. } => 400,
+            Error::TooLarge { .. } => 413,
+            Error::Puzzle { .. } | Error::Contradiction { .. } => 422,
+            Error::Internal => 500,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::NotFound { .. } => "not_found",
+            Error::MethodNotAllowed { .. } => "method_not_allowed",
+            Error::BadRequest { .. } => "bad_request",
+            Error::TooLarge { .. } => "too_large",
+            Error::Puzzle {
+                error: PuzzleError::Parse { .. },
+            } => "parse",
+            Error::Puzzle { .. } => "puzzle",
+            Error::Contradiction { .. } => "contradiction",
+            Error::Internal => "internal",
+        }
+    }
+
+    /// Body of the response reporting the error, in the format used by the
+    /// CLI's `--json` errors.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = json!({
+            "error": self.kind(),
+            "message": self.to_string(),
+        });
+        match self {
+            Error::Puzzle {
+                error: PuzzleError::Parse { diagnostics },
+            } => {
+                value["diagnostics"] = diagnostics
+                    .iter()
+                    .map(|diagnostic| {
+                        json!({
+                            "line": diagnostic.span.line,
+                            "column": diagnostic.span.column,
+                            "message": diagnostic.error.to_string(),
+                            "suggestion": diagnostic.suggestion.as_ref().map(|s| &s.replacement),
+                        })
+                    })
+                    .collect();
+            }
+            Error::Contradiction { clue } => value["clue"] = json!(clue),
+            _ => {}
+        }
+        value
+    }
+}
+
+impl From<PuzzleError> for Error {
+    fn from(error: PuzzleError) -> Error {
+        Error::Puzzle { error }
+    }
+}
@@ -0,0 +1,332 @@
+extern crate gridsolve;
+
+mod error;
+
+use error::Error;
+use gridsolve::{
+    count_solutions_within, lint, render_grid, try_solve_within, Budget, Charset, ColumnLabels,
+    GridStyle, Locale, Puzzle, PuzzleFormat, Solution, SolutionReport,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::panic;
+use std::sync::Arc;
+use std::time::Duration;
+use structopt::StructOpt;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "gridsolve_server",
+    about = "Serve the grid logic puzzle solver as JSON over HTTP"
+)]
+struct Opt {
+    /// Address to listen on
+    #[structopt(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on, or 0 for any free port
+    #[structopt(long, default_value = "8080")]
+    port: u16,
+
+    /// Number of requests to handle at once. Defaults to the number of CPUs
+    #[structopt(short, long)]
+    jobs: Option<usize>,
+
+    #[structopt(flatten)]
+    limits: Limits,
+}
+
+// Limits on the work done for each request, which requests can only lower.
+#[derive(Debug, StructOpt)]
+struct Limits {
+    /// Stop solving after making N steps
+    #[structopt(long, value_name = "N")]
+    max_steps: Option<usize>,
+
+    /// Stop solving after N passes over the clues and deduction rules
+    #[structopt(long, value_name = "N")]
+    max_passes: Option<usize>,
+
+    /// Stop solving a puzzle after this many seconds, or 0 for no limit
+    #[structopt(long, value_name = "SECONDS", default_value = "5")]
+    timeout: f64,
+
+    /// Largest number of solutions which `/count` may look for
+    #[structopt(long, value_name = "N", default_value = "100")]
+    max_count: usize,
+
+    /// Largest request body accepted, in bytes
+    #[structopt(long, value_name = "BYTES", default_value = "1048576")]
+    max_body: usize,
+}
+
+// Fields shared by the body of every request, giving the puzzle to work on.
+#[derive(Debug, Deserialize)]
+struct PuzzleRequest {
+    /// The puzzle file.
+    puzzle: String,
+
+    /// Format of `puzzle`: text, json, toml or url. Defaults to text.
+    format: Option<String>,
+
+    /// Language of the step-by-step explanations, falling back to English.
+    locale: Option<String>,
+
+    #[serde(default)]
+    budget: BudgetRequest,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BudgetRequest {
+    max_steps: Option<usize>,
+    max_passes: Option<usize>,
+
+    /// In seconds.
+    timeout: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HintRequest {
+    #[serde(flatten)]
+    puzzle: PuzzleRequest,
+
+    /// Number of steps which have already been made.
+    #[serde(default)]
+    after: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountRequest {
+    #[serde(flatten)]
+    puzzle: PuzzleRequest,
+
+    /// Stop counting after this many solutions.
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderRequest {
+    #[serde(flatten)]
+    puzzle: PuzzleRequest,
+
+    /// Show the grid after only the first `step` steps of the solution.
+    step: Option<usize>,
+
+    /// Draw the grid with ASCII characters only.
+    #[serde(default)]
+    ascii: bool,
+
+    /// Write the column labels horizontally, cut down to this many
+    /// characters, instead of vertically.
+    abbreviate: Option<usize>,
+}
+
+/// Return the lower of two optional limits, where `None` is no limit.
+fn lower<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+impl PuzzleRequest {
+    fn parse(&self) -> Result<Puzzle, Error> {
+        let format = match &self.format {
+            Some(format) => format.parse().map_err(Error::bad_request)?,
+            None => PuzzleFormat::Text,
+        };
+        Ok(Puzzle::parse_as(&self.puzzle, format)?)
+    }
+
+    fn locale(&self) -> Locale {
+        self.locale
+            .as_deref()
+            .and_then(Locale::from_language_tag)
+            .unwrap_or_default()
+    }
+
+    /// Return the budget asked for, within the server's `limits`.
+    fn budget(&self, limits: &Limits) -> Result<Budget, Error> {
+        let timeout = match self.budget.timeout {
+            Some(timeout) => Some(
+                Duration::try_from_secs_f64(timeout)
+                    .map_err(|_| Error::bad_request("timeout must be a number of seconds"))?,
+            ),
+            None => None,
+        };
+        Ok(Budget {
+            max_steps: lower(self.budget.max_steps, limits.max_steps),
+            max_passes: lower(self.budget.max_passes, limits.max_passes),
+            time_limit: lower(
+                timeout,
+                Duration::try_from_secs_f64(limits.timeout)
+                    .ok()
+                    .filter(|limit| !limit.is_zero()),
+            ),
+            ..Budget::default()
+        })
+    }
+}
+
+/// Solve `puzzle` within `budget`, naming the clue which found a
+/// contradiction if there is one.
+fn solve<'p>(puzzle: &'p Puzzle, budget: &Budget) -> Result<Solution<'p>, Error> {
+    try_solve_within(puzzle, budget).map_err(|contradiction| Error::Contradiction {
+        clue: contradiction
+            .clue
+            .map(|index| puzzle.constraints()[index].name.clone()),
+    })
+}
+
+/// Parse the JSON `body` of a request.
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, Error> {
+    serde_json::from_str(body).map_err(Error::bad_request)
+}
+
+/// Handle a POST of `body` to `path`, returning the body of the response.
+fn handle(path: &str, body: &str, limits: &Limits) -> Result<serde_json::Value, Error> {
+    match path {
+        "/parse" => {
+            let request: PuzzleRequest = parse_body(body)?;
+            let puzzle = request.parse()?;
+            Ok(json!({
+                "puzzle": puzzle,
+                "warnings": lint(&puzzle)
+                    .iter()
+                    .map(|w| json!({"clue": w.name, "message": w.lint.to_string()}))
+                    .collect::<Vec<_>>(),
+            }))
+        }
+
+        "/solve" => {
+            let request: PuzzleRequest = parse_body(body)?;
+            let puzzle = request.parse()?;
+            let solution = solve(&puzzle, &request.budget(limits)?)?;
+            Ok(json!(SolutionReport::new(&solution, request.locale())))
+        }
+
+        "/hint" => {
+            let request: HintRequest = parse_body(body)?;
+            let puzzle = request.puzzle.parse()?;
+            let solution = solve(&puzzle, &request.puzzle.budget(limits)?)?;
+            let mut report = SolutionReport::new(&solution, request.puzzle.locale());
+            let hint = (request.after < report.steps.len())
+                .then(|| report.steps.swap_remove(request.after));
+            Ok(json!({ "step": hint, "status": report.status }))
+        }
+
+        "/count" => {
+            let request: CountRequest = parse_body(body)?;
+            let puzzle = request.puzzle.parse()?;
+            let limit = lower(request.limit, Some(limits.max_count)).unwrap();
+            // Steps mean guesses when counting, so only the time is limited.
+            let budget = Budget {
+                time_limit: request.puzzle.budget(limits)?.time_limit,
+                ..Budget::default()
+            };
+            let (count, stopped) = count_solutions_within(&puzzle, limit, &budget);
+            Ok(json!({ "count": count, "limit": limit, "status": stopped }))
+        }
+
+        "/render" => {
+            let request: RenderRequest = parse_body(body)?;
+            let puzzle = request.puzzle.parse()?;
+            let solution = solve(&puzzle, &request.puzzle.budget(limits)?)?;
+            let grid = solution.grid(request.step.unwrap_or(solution.steps.len()));
+            let style = GridStyle {
+                charset: if request.ascii {
+                    Charset::Ascii
+                } else {
+                    Charset::Unicode
+                },
+                column_labels: match request.abbreviate {
+                    Some(len) => ColumnLabels::Abbreviated(len),
+                    None => ColumnLabels::Rotated,
+                },
+                cursor: None,
+            };
+            Ok(json!({ "grid": render_grid(&grid, style), "status": solution.status }))
+        }
+
+        _ => Err(Error::NotFound {
+            path: path.to_string(),
+        }),
+    }
+}
+
+/// Read the body of `request`, up to `limit` bytes.
+fn read_body(request: &mut Request, limit: usize) -> Result<String, Error> {
+    if request.body_length().is_some_and(|length| length > limit) {
+        return Err(Error::TooLarge { limit });
+    }
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(limit as u64 + 1)
+        .read_to_string(&mut body)
+        .map_err(|_| Error::bad_request("body must be UTF-8"))?;
+    if body.len() > limit {
+        return Err(Error::TooLarge { limit });
+    }
+    Ok(body)
+}
+
+fn respond(mut request: Request, limits: &Limits) {
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let result = if *request.method() == Method::Post {
+        read_body(&mut request, limits.max_body).and_then(|body| {
+            // A bug met by one request shouldn't take down the server.
+            panic::catch_unwind(|| handle(&path, &body, limits)).unwrap_or(Err(Error::Internal))
+        })
+    } else if ["/parse", "/solve", "/hint", "/count", "/render"].contains(&path.as_str()) {
+        Err(Error::MethodNotAllowed { path })
+    } else {
+        Err(Error::NotFound { path })
+    };
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err(error) => (error.status(), error.to_json()),
+    };
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(content_type);
+    // The client may have gone away, in which case there is no one to tell.
+    let _ = request.respond(response);
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    let server = match Server::http((opt.host.as_str(), opt.port)) {
+        Ok(server) => Arc::new(server),
+        Err(error) => {
+            eprintln!("error: {}:{}: {}", opt.host, opt.port, error);
+            std::process::exit(1);
+        }
+    };
+    if let Some(addr) = server.server_addr().to_ip() {
+        println!("listening on http://{}", addr);
+        std::io::stdout().flush().unwrap();
+    }
+    let jobs = opt
+        .jobs
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let limits = Arc::new(opt.limits);
+    let workers = (0..jobs.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let limits = Arc::clone(&limits);
+            std::thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    respond(request, &limits);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
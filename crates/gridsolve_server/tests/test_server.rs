@@ -0,0 +1,174 @@
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+/// A server listening on a free port, killed when dropped.
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_gridsolve_server"))
+            .args(["--port", "0"])
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line
+            .trim()
+            .strip_prefix("listening on http://")
+            .unwrap()
+            .to_string();
+        Server { child, addr }
+    }
+
+    /// Send `body` with `method` to `path`, returning the status code and the
+    /// JSON body of the response.
+    fn request(&self, method: &str, path: &str, body: &Value) -> (u16, Value) {
+        let body = body.to_string();
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            self.addr,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    fn post(&self, path: &str, body: &Value) -> (u16, Value) {
+        self.request("POST", path, body)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn simple() -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../puzzles/simple.txt");
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_endpoints() {
+    let server = Server::start(&[]);
+    let puzzle = simple();
+
+    let (status, body) = server.post("/parse", &json!({ "puzzle": puzzle }));
+    assert_eq!(status, 200);
+    assert_eq!(body["puzzle"]["categories"][1]["name"], "Country");
+    assert_eq!(body["warnings"], json!([]));
+
+    let (status, body) = server.post("/solve", &json!({ "puzzle": puzzle, "locale": "fr" }));
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "solved");
+    assert_eq!(body["entities"][0]["labels"][1], "Germany");
+    let steps = body["steps"].as_array().unwrap().len();
+
+    let (status, body) = server.post("/hint", &json!({ "puzzle": puzzle, "after": 1 }));
+    assert_eq!(status, 200);
+    assert_eq!(body["step"]["index"], 2);
+    let (_, body) = server.post("/hint", &json!({ "puzzle": puzzle, "after": steps }));
+    assert_eq!(body["step"], Value::Null);
+
+    let (status, body) = server.post("/count", &json!({ "puzzle": puzzle, "limit": 1000 }));
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({ "count": 1, "limit": 100, "status": null }));
+
+    let request = json!({ "puzzle": puzzle, "ascii": true, "step": 0 });
+    let (status, body) = server.post("/render", &request);
+    assert_eq!(status, 200);
+    let grid = body["grid"].as_str().unwrap();
+    assert!(grid.contains("Leo") && !grid.contains('O'), "{}", grid);
+}
+
+#[test]
+fn test_budget() {
+    let server = Server::start(&["--max-steps", "4"]);
+    let puzzle = simple();
+
+    let (_, body) = server.post("/solve", &json!({ "puzzle": puzzle }));
+    assert_eq!(body["status"], "step_limit");
+    assert_eq!(body["steps"].as_array().unwrap().len(), 4);
+
+    // Requests can lower the server's limits, but not raise them.
+    let budget = json!({ "puzzle": puzzle, "budget": { "max_steps": 2 } });
+    let (_, body) = server.post("/solve", &budget);
+    assert_eq!(body["steps"].as_array().unwrap().len(), 2);
+    let budget = json!({ "puzzle": puzzle, "budget": { "max_steps": 100 } });
+    let (_, body) = server.post("/solve", &budget);
+    assert_eq!(body["steps"].as_array().unwrap().len(), 4);
+
+    // Counting is only limited in time, as it has no steps.
+    let (clues, _) = puzzle.split_once("[Clues]").unwrap();
+    let open = format!("{}[Clues]\n", clues);
+    let budget = json!({ "puzzle": open, "budget": { "timeout": 1e-9 } });
+    let (status, body) = server.post("/count", &budget);
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "time_limit");
+    let (_, body) = server.post("/count", &json!({ "puzzle": open, "limit": 5 }));
+    assert_eq!(body["count"], 5);
+
+    let budget = json!({ "puzzle": puzzle, "budget": { "timeout": -1 } });
+    let (status, body) = server.post("/solve", &budget);
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "bad_request");
+}
+
+#[test]
+fn test_errors() {
+    let server = Server::start(&[]);
+
+    let (status, body) = server.post("/solve", &json!({ "puzzle": "[Categories]\nx" }));
+    assert_eq!(status, 422);
+    assert_eq!(body["error"], "parse");
+    assert_eq!(body["diagnostics"][0]["line"], 2);
+
+    let categories = "[Categories]\nA\na1\na2\n\nB\nb1\nb2\n\n[Clues]\n";
+    let contradictory = format!("{}1,yes,a1,b1\n2,yes,a1,b2\n", categories);
+    let (status, body) = server.post("/solve", &json!({ "puzzle": contradictory }));
+    assert_eq!(status, 422);
+    assert_eq!(body["error"], "contradiction");
+    // Found by a deduction rule rather than while applying a clue.
+    assert_eq!(body["clue"], Value::Null);
+
+    let (status, body) = server.post("/solve", &json!({ "text": "" }));
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "bad_request");
+
+    let (status, body) = server.post("/solve", &json!({ "puzzle": "", "format": "xml" }));
+    assert_eq!(status, 400);
+    assert_eq!(
+        body["message"],
+        "invalid request: Unknown puzzle format: xml"
+    );
+
+    let (status, body) = server.request("GET", "/solve", &json!({}));
+    assert_eq!(status, 405);
+    assert_eq!(body["error"], "method_not_allowed");
+
+    let (status, body) = server.post("/nowhere", &json!({}));
+    assert_eq!(status, 404);
+    assert_eq!(body["error"], "not_found");
+}
@@ -1,22 +1,340 @@
-use gridsolve::{solve, Puzzle};
-use serde::Serialize;
+mod solver;
+
+use gridsolve::{
+    try_solve_within, Budget, Contradiction, History, Locale, LocalizedSolution, LocalizedStep,
+    Puzzle, PuzzleError, SharedPuzzle, Solution, Step, CLUE_KINDS,
+};
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+pub use solver::WasmSolver;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+/** A label or category, by its index. */
+export type MessageArg =
+  | { label: { category: number; label: number } }
+  | { category: number }
+  | { clue: string }
+  | { number: number };
+
+/** Why a step was made, without any presentation. */
+export interface Message {
+  id: string;
+  args: Record<string, MessageArg>;
+}
+
+export interface Step {
+  label1: string;
+  label2: string;
+  yes: boolean;
+  /**
+   * Name of the rule, or kind of the clue, which made the step, or "hand" for
+   * a cell set with `WasmSolver.set_cell`.
+   */
+  rule: string;
+  message: Message | null;
+  /** The message rendered in the requested locale, or "" if there is none. */
+  description: string;
+}
+
+export interface Solution {
+  /**
+   * "partial" if the solver got stuck before matching every label, or the
+   * limit of the `Budget` which stopped it.
+   */
+  status:
+    | "solved"
+    | "partial"
+    | "step_limit"
+    | "pass_limit"
+    | "time_limit"
+    | "cancelled";
+  /** For each entity, the label of each category, or null if unknown. */
+  solution: Array<Record<string, string | null>>;
+  steps: Step[];
+  /** The grid after every step, for jumping to any of them. */
+  history: History;
+}
+
+/**
+ * Every cell of the grid, in the order of `History.layout`, as one character
+ * each: "y", "n" or "." for an empty cell.
+ */
+export interface Snapshot {
+  /** Number of steps made when the snapshot was taken. */
+  step: number;
+  cells: string;
+}
+
+/** The cell set by a step, as an index in `History.layout`. */
+export interface Delta {
+  cell: number;
+  yes: boolean;
+}
+
+/**
+ * The grid after the first `n` steps is the snapshot at index
+ * `Math.floor(n / interval)`, with the deltas from its `step` to `n` applied.
+ */
+export interface History {
+  interval: number;
+  /** The labels of each cell. */
+  layout: [string, string][];
+  snapshots: Snapshot[];
+  deltas: Delta[];
+}
+
+/** Limits on solving, which leave the solution partial when reached. */
+export interface Budget {
+  /** Maximum number of steps to make. */
+  maxSteps?: number;
+  /** Maximum number of passes over the clues and deduction rules. */
+  maxPasses?: number;
+}
+
+export interface Diagnostic {
+  /** Line and column of the start of the error, starting from 1. */
+  line: number;
+  column: number;
+  /** Number of characters covered by the error. */
+  length: number;
+  message: string;
+  suggestion: string | null;
+}
+
+/** An argument of a kind of clue. */
+export interface Param {
+  /** Name of the argument, as used in the description of the clue. */
+  name: string;
+  kind: "label" | "category" | "number";
+  /** Smallest value of a number argument. */
+  min: number;
+}
+
+/** A kind of clue, and the arguments it takes. */
+export interface ClueKind {
+  keyword: string;
+  params: Param[];
+  /** Whether `params` may be repeated, at least twice. */
+  variadic: boolean;
+  description: string;
+}
+
+/** The value of a cell of the grid. */
+export type CellValue = "yes" | "no" | "empty";
+
+/** A cell of the grid, with its labels written as in a clue. */
+export interface GridCell {
+  x: string;
+  y: string;
+  value: CellValue;
+}
+
+/** Thrown by `solve_puzzle` and the methods of `WasmSolver`. */
+export type SolveError =
+  | { kind: "parse"; message: string; diagnostics: Diagnostic[] }
+  | { kind: "input"; message: string }
+  | {
+      kind: "contradiction";
+      message: string;
+      /** The clue being applied when the contradiction was found, if any. */
+      clue: { index: number; name: string } | null;
+      rule: string;
+      /** The labels of the cell which would have to be both yes and no. */
+      cell: [string, string] | null;
+      /** The steps made before the contradiction was found. */
+      steps: Step[];
+    };
+"#;
+
+/// Number of steps between the snapshots of the `History` of a solution.
+const SNAPSHOT_INTERVAL: usize = 16;
+
 #[derive(Serialize)]
-#[wasm_bindgen]
-pub struct PuzzleError {
-    error: String,
+pub(crate) struct SolveResult<'s, 'p> {
+    #[serde(flatten)]
+    pub solution: LocalizedSolution<'s, 'p>,
+    pub history: History,
 }
 
-impl PuzzleError {
-    fn from_str(str: String) -> JsValue {
-        JsValue::from_serde(&PuzzleError { error: str }).unwrap()
+impl<'s, 'p> SolveResult<'s, 'p> {
+    pub fn new(solution: &'s Solution<'p>, locale: Locale) -> Self {
+        SolveResult {
+            solution: locale.localize(solution),
+            history: solution.history(SNAPSHOT_INTERVAL),
+        }
     }
 }
 
+/// Mirrors the `Budget` type of the TS definitions.
+/// There is no time limit, as there is no clock to measure it with.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct BudgetSpec {
+    max_steps: Option<usize>,
+    max_passes: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Diagnostic {
+    line: usize,
+    column: usize,
+    length: usize,
+    message: String,
+    suggestion: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Clue<'p> {
+    index: usize,
+    name: &'p str,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum SolveError<'s, 'p> {
+    Parse {
+        message: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    Contradiction {
+        message: String,
+        clue: Option<Clue<'p>>,
+        rule: &'static str,
+        cell: Option<(&'p str, &'p str)>,
+        steps: Vec<LocalizedStep<'s, 'p>>,
+    },
+    /// Invalid arguments passed from JS.
+    Input { message: String },
+}
+
+/// Convert `value` to a plain JS object.
+pub(crate) fn to_js(value: &impl Serialize) -> JsValue {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap()
+}
+
+pub(crate) fn parse_error(input: &str, error: PuzzleError) -> JsValue {
+    let diagnostics = match &error {
+        PuzzleError::Parse { diagnostics } => diagnostics
+            .iter()
+            .map(|d| Diagnostic {
+                line: d.span.line,
+                column: d.span.column,
+                length: input[d.span.start..d.span.end].chars().count(),
+                message: d.error.to_string(),
+                suggestion: d.suggestion.as_ref().map(|s| s.replacement.clone()),
+            })
+            .collect(),
+        _ => vec![],
+    };
+    to_js(&SolveError::Parse {
+        message: error.to_string(),
+        diagnostics,
+    })
+}
+
+pub(crate) fn localize_steps<'s, 'p>(
+    steps: &'s [Step<'p>],
+    puzzle: &'p Puzzle,
+    locale: Locale,
+) -> Vec<LocalizedStep<'s, 'p>> {
+    steps
+        .iter()
+        .map(|step| LocalizedStep {
+            step,
+            description: locale.describe(step, puzzle),
+        })
+        .collect()
+}
+
+/// Return the locale for a language tag, falling back to English.
+pub(crate) fn parse_locale(tag: Option<&str>) -> Locale {
+    tag.and_then(Locale::from_language_tag).unwrap_or_default()
+}
+
+/// Read a `Budget` given to an exported function, which may be left out.
+/// Throws a `SolveError` if it isn't valid.
+pub(crate) fn parse_budget(budget: JsValue) -> Result<Budget, JsValue> {
+    let budget = if budget.is_undefined() || budget.is_null() {
+        BudgetSpec::default()
+    } else {
+        serde_wasm_bindgen::from_value(budget).map_err(|e| {
+            to_js(&SolveError::Input {
+                message: format!("Invalid budget: {}", e),
+            })
+        })?
+    };
+    Ok(Budget {
+        max_steps: budget.max_steps,
+        max_passes: budget.max_passes,
+        ..Budget::default()
+    })
+}
+
+pub(crate) fn contradiction_error(
+    contradiction: &Contradiction,
+    puzzle: &Puzzle,
+    locale: Locale,
+) -> JsValue {
+    let clue = contradiction.clue.map(|index| Clue {
+        index,
+        name: &puzzle.constraints()[index].name,
+    });
+    let message = match &clue {
+        Some(clue) => format!("clues are contradictory, as found by clue {}", clue.name),
+        None => "clues are contradictory".to_string(),
+    };
+    to_js(&SolveError::Contradiction {
+        message,
+        clue,
+        rule: contradiction.rule,
+        cell: contradiction
+            .cell
+            .map(|(x, y)| (puzzle.lookup_label(x), puzzle.lookup_label(y))),
+        steps: localize_steps(&contradiction.steps, puzzle, locale),
+    })
+}
+
+/// Solve the puzzle in `input`, describing each step in the language given by
+/// the `locale` tag (e.g. `navigator.language`).
+/// Unsupported or missing locales fall back to English.
+/// The solver stops early, leaving the solution partial, once the optional
+/// `budget` is spent.
+/// Throws a `SolveError` if the puzzle can't be parsed, its clues are
+/// contradictory or the budget is invalid.
+#[wasm_bindgen(unchecked_return_type = "Solution")]
+pub fn solve_puzzle(
+    input: &str,
+    locale: Option<String>,
+    #[wasm_bindgen(unchecked_param_type = "Budget | undefined")] budget: JsValue,
+) -> Result<JsValue, JsValue> {
+    let locale = parse_locale(locale.as_deref());
+    let budget = parse_budget(budget)?;
+    let puzzle = Puzzle::parse(input).map_err(|e| parse_error(input, e))?;
+    match try_solve_within(&puzzle, &budget) {
+        Ok(solution) => Ok(to_js(&SolveResult::new(&solution, locale))),
+        Err(contradiction) => Err(contradiction_error(&contradiction, &puzzle, locale)),
+    }
+}
+
+/// Return every kind of clue, so that forms for writing clues can be built
+/// from them.
+#[wasm_bindgen(unchecked_return_type = "ClueKind[]")]
+pub fn clue_kinds() -> JsValue {
+    to_js(&CLUE_KINDS)
+}
+
+/// Write the puzzle of the web app, given as the JSON kept in the hash of its
+/// URL, in the text format.
+/// Throws a `SolveError` if the JSON doesn't describe a valid puzzle.
 #[wasm_bindgen]
-pub fn solve_puzzle(input: &str) -> Result<String, JsValue> {
-    let puzzle = Puzzle::parse(input).map_err(|e| PuzzleError::from_str(e.to_string()))?;
-    let solution = solve(&puzzle).unwrap();
-    Ok(serde_json::to_string(&solution).unwrap())
+pub fn shared_puzzle_text(json: &str) -> Result<String, JsValue> {
+    let invalid = |message: String| to_js(&SolveError::Input { message });
+    let shared: SharedPuzzle =
+        serde_json::from_str(json).map_err(|e| invalid(format!("Invalid puzzle: {}", e)))?;
+    let puzzle = shared.to_puzzle().map_err(|e| invalid(e.to_string()))?;
+    Ok(puzzle.to_string())
 }
@@ -0,0 +1,356 @@
+use crate::*;
+use gridsolve::{Budget, Cell, Grid, Label, Message, Status};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Something which happened to the grid.
+#[derive(Debug, Clone)]
+enum Action {
+    /// A deduction made by the solver.
+    Step {
+        cell: (Label, Label),
+        yes: bool,
+        rule: &'static str,
+        message: Option<Message>,
+    },
+
+    /// A cell set by hand.
+    Set(Label, Label, Cell),
+}
+
+/// Rule of the steps which stand for cells set by hand.
+const SET_BY_HAND: &str = "hand";
+
+impl Action {
+    fn from_step(step: &Step) -> Action {
+        Action::Step {
+            cell: step.cell,
+            yes: step.yes,
+            rule: step.rule,
+            message: step.message.clone(),
+        }
+    }
+
+    /// The cell which the action changed, and its new value.
+    fn change(&self) -> ((Label, Label), Cell) {
+        match *self {
+            Action::Step { cell, yes, .. } => (cell, if yes { Cell::Yes } else { Cell::No }),
+            Action::Set(x, y, value) => ((x, y), value),
+        }
+    }
+
+    /// The step which the action made, or `None` if it emptied a cell.
+    fn to_step<'p>(&self, puzzle: &'p Puzzle) -> Option<Step<'p>> {
+        match *self {
+            Action::Step {
+                cell,
+                yes,
+                rule,
+                ref message,
+            } => Some(Step::new(puzzle, cell, yes, rule, message.clone())),
+            Action::Set(_, _, Cell::Empty) => None,
+            Action::Set(x, y, value) => Some(Step::new(
+                puzzle,
+                (x, y),
+                value == Cell::Yes,
+                SET_BY_HAND,
+                None,
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GridCell<'p> {
+    x: String,
+    y: String,
+    value: &'p str,
+}
+
+/// Solves a puzzle one step at a time, and lets cells be set by hand between
+/// the steps.
+#[wasm_bindgen]
+pub struct WasmSolver {
+    puzzle: Puzzle,
+    locale: Locale,
+
+    /// Everything which happened to the grid since it was last reset, in order.
+    history: Vec<Action>,
+
+    /// The cells of the grid as the history leaves them, in the order of
+    /// `Grid::cells`.
+    cells: Vec<Cell>,
+
+    /// The index of each cell in `cells`.
+    index: BTreeMap<(Label, Label), usize>,
+
+    /// The steps which can be deduced from the grid but haven't been made
+    /// yet, or `None` if the grid changed since they were deduced.
+    pending: Option<VecDeque<Action>>,
+
+    /// Limits on the steps made since the grid was last reset, and on the
+    /// passes of each deduction.
+    budget: Budget,
+
+    /// Why the pending steps were cut short, if the budget ran out.
+    stopped: Option<Status>,
+}
+
+impl WasmSolver {
+    /// Build the grid resulting from the history, with a step for each cell
+    /// set by hand.
+    /// Emptying a cell by hand drops the steps which set it before.
+    fn build_grid(&self) -> Grid<'_> {
+        let mut grid = Grid::new(&self.puzzle);
+        let cells = grid.cells().collect::<Vec<_>>();
+        for ((x, y), &value) in cells.into_iter().zip(&self.cells) {
+            grid.put(x, y, value);
+        }
+        for action in &self.history {
+            match action.to_step(&self.puzzle) {
+                Some(step) => grid.steps.push(step),
+                None => {
+                    let (cell, _) = action.change();
+                    grid.steps.retain(|step| step.cell != cell);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Make `action`, keeping the cells up to date.
+    fn apply(&mut self, action: Action) {
+        let (cell, value) = action.change();
+        self.cells[self.index[&cell]] = value;
+        self.history.push(action);
+    }
+
+    /// Rebuild the cells from the history, after undoing some of it.
+    fn replay(&mut self) {
+        self.cells.iter_mut().for_each(|cell| *cell = Cell::Empty);
+        for action in &self.history {
+            let (cell, value) = action.change();
+            self.cells[self.index[&cell]] = value;
+        }
+        self.pending = None;
+    }
+
+    /// Return the steps which can be deduced from the grid, deducing them if
+    /// the grid changed.
+    fn pending(&mut self) -> Result<&mut VecDeque<Action>, JsValue> {
+        if self.pending.is_none() {
+            let mut grid = self.build_grid();
+            let known = grid.steps.len();
+            let made = self.num_steps();
+            let budget = Budget {
+                max_steps: self.budget.max_steps.map(|max| max.saturating_sub(made)),
+                ..self.budget.clone()
+            };
+            let status = match grid.try_deduce_within(&budget) {
+                Ok(status) => status,
+                Err(contradiction) => {
+                    return Err(contradiction_error(
+                        &contradiction,
+                        &self.puzzle,
+                        self.locale,
+                    ))
+                }
+            };
+            let pending = grid.steps[known..].iter().map(Action::from_step).collect();
+            self.pending = Some(pending);
+            self.stopped = Some(status).filter(|status| status.is_out_of_budget());
+        }
+        Ok(self.pending.as_mut().unwrap())
+    }
+
+    fn num_steps(&self) -> usize {
+        self.history
+            .iter()
+            .filter(|action| matches!(action, Action::Step { .. }))
+            .count()
+    }
+
+    fn cancel_flag(&self) -> &AtomicBool {
+        self.budget.cancel.as_ref().unwrap()
+    }
+
+    fn input_error(message: String) -> JsValue {
+        to_js(&SolveError::Input { message })
+    }
+
+    fn label(&self, name: &str) -> Result<Label, JsValue> {
+        self.puzzle
+            .label(name)
+            .map_err(|e| WasmSolver::input_error(e.to_string()))
+    }
+}
+
+#[wasm_bindgen]
+impl WasmSolver {
+    /// Parse the puzzle in `input`, describing steps in the language given by
+    /// the `locale` tag.
+    /// The solver makes no more steps once the optional `budget` is spent,
+    /// counting the steps made since the grid was last reset.
+    /// Throws a `SolveError` if the puzzle can't be parsed or the budget is
+    /// invalid.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        input: &str,
+        locale: Option<String>,
+        #[wasm_bindgen(unchecked_param_type = "Budget | undefined")] budget: JsValue,
+    ) -> Result<WasmSolver, JsValue> {
+        let budget = Budget {
+            cancel: Some(Arc::new(AtomicBool::new(false))),
+            ..parse_budget(budget)?
+        };
+        let puzzle = Puzzle::parse(input).map_err(|e| parse_error(input, e))?;
+        let index = Grid::new(&puzzle)
+            .cells()
+            .enumerate()
+            .map(|(i, cell)| (cell, i))
+            .collect::<BTreeMap<_, _>>();
+        Ok(WasmSolver {
+            puzzle,
+            locale: parse_locale(locale.as_deref()),
+            history: vec![],
+            cells: vec![Cell::Empty; index.len()],
+            index,
+            pending: None,
+            budget,
+            stopped: None,
+        })
+    }
+
+    /// Make the next step, and return it, or `null` if nothing more can be
+    /// deduced.
+    /// Throws a `SolveError` if the grid contradicts the clues.
+    #[wasm_bindgen(unchecked_return_type = "Step | null")]
+    pub fn step(&mut self) -> Result<JsValue, JsValue> {
+        let action = match self.pending()?.pop_front() {
+            Some(action) => action,
+            None => return Ok(JsValue::NULL),
+        };
+        // Only steps made by the solver are pending.
+        let step = action.to_step(&self.puzzle).unwrap();
+        let step = to_js(&localize_steps(&[step], &self.puzzle, self.locale)[0]);
+        self.apply(action);
+        Ok(step)
+    }
+
+    /// Make steps until `n` have been made since the last reset, or nothing
+    /// more can be deduced, and return the number of steps made.
+    /// If more than `n` steps have been made, go back to just after the `n`th
+    /// step, undoing any cells set by hand since then.
+    pub fn run_until(&mut self, n: usize) -> Result<usize, JsValue> {
+        let made = self.num_steps();
+        if n < made {
+            let mut steps = 0;
+            let end = self
+                .history
+                .iter()
+                .position(|action| {
+                    if let Action::Step { .. } = action {
+                        steps += 1;
+                    }
+                    steps > n
+                })
+                .unwrap_or(self.history.len());
+            self.history.truncate(end);
+            self.replay();
+            return Ok(n);
+        }
+        let pending = self.pending()?;
+        let count = (n - made).min(pending.len());
+        let steps = pending.drain(..count).collect::<Vec<_>>();
+        for action in steps {
+            self.apply(action);
+        }
+        Ok(made + count)
+    }
+
+    /// Return every cell of the grid, with the labels of the lower category
+    /// first.
+    #[wasm_bindgen(unchecked_return_type = "GridCell[]")]
+    pub fn grid(&self) -> JsValue {
+        let grid = Grid::new(&self.puzzle);
+        let cells = grid
+            .cells()
+            .zip(&self.cells)
+            .map(|((x, y), value)| GridCell {
+                x: self.puzzle.label_reference(x),
+                y: self.puzzle.label_reference(y),
+                value: match value {
+                    Cell::Yes => "yes",
+                    Cell::No => "no",
+                    Cell::Empty => "empty",
+                },
+            })
+            .collect::<Vec<_>>();
+        to_js(&cells)
+    }
+
+    /// Set the cell of the labels `x` and `y` by hand, overriding any step.
+    /// Throws a `SolveError` if the labels or the value aren't valid.
+    pub fn set_cell(
+        &mut self,
+        x: &str,
+        y: &str,
+        #[wasm_bindgen(unchecked_param_type = "CellValue")] value: &str,
+    ) -> Result<(), JsValue> {
+        let (mut x, mut y) = (self.label(x)?, self.label(y)?);
+        if x.category > y.category {
+            core::mem::swap(&mut x, &mut y);
+        }
+        if x.category == y.category {
+            return Err(WasmSolver::input_error(format!(
+                "{} and {} are in the same category",
+                self.puzzle.label_reference(x),
+                self.puzzle.label_reference(y)
+            )));
+        }
+        let value = match value {
+            "yes" => Cell::Yes,
+            "no" => Cell::No,
+            "empty" => Cell::Empty,
+            _ => {
+                return Err(WasmSolver::input_error(format!(
+                    "Invalid cell value: {}",
+                    value
+                )))
+            }
+        };
+        self.apply(Action::Set(x, y, value));
+        self.pending = None;
+        Ok(())
+    }
+
+    /// Stop making steps until the grid is reset.
+    pub fn cancel(&mut self) {
+        self.cancel_flag().store(true, Ordering::Relaxed);
+        self.pending = None;
+    }
+
+    /// Clear the grid.
+    pub fn reset(&mut self) {
+        self.cancel_flag().store(false, Ordering::Relaxed);
+        self.history.clear();
+        self.replay();
+    }
+
+    /// Return the solution as it stands, with the steps made so far,
+    /// including a step of the rule `"hand"` for each cell set by hand.
+    /// Its status says why the solver stopped if its budget ran out and
+    /// every step it deduced has been made.
+    #[wasm_bindgen(unchecked_return_type = "Solution")]
+    pub fn solution(&self) -> JsValue {
+        let mut solution = Solution::from_grid(self.build_grid());
+        if self.pending.as_ref().is_some_and(VecDeque::is_empty) {
+            if let Some(status) = self.stopped {
+                solution.status = status;
+            }
+        }
+        to_js(&SolveResult::new(&solution, self.locale))
+    }
+}
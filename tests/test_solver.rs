@@ -1,8 +1,16 @@
 extern crate gridsolve;
 
-use gridsolve::{solve, Puzzle};
+use gridsolve::{
+    check_solution, clue_kind, count_solutions, count_solutions_within, lint, render_grid, solve,
+    test_dir, try_solve, try_solve_all, try_solve_within, verify, Answer, Budget, Cell, Charset,
+    ColumnLabels, Grid, GridStyle, Lint, Locale, MessageId, Outcome, OwnedSolution, Puzzle,
+    PuzzleError, PuzzleFormat, SharedClue, SharedPuzzle, Snapshot, Solution, SolutionReport,
+    Status, CLUE_KINDS, SCHEMA_VERSION, SHARE_URL,
+};
 use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 macro_rules! check_solution {
     ($puzzle: expr, $solution: expr, $prim: expr, $($sec: expr), +) => {
@@ -22,11 +30,825 @@ macro_rules! check_solution {
     };
 }
 
+fn puzzle_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../puzzles")
+        .join(name)
+}
+
 #[test]
 fn test_simple() {
-    let puz = Puzzle::from_file(&PathBuf::from("puzzles/simple.txt")).unwrap();
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
     let sol = solve(&puz).unwrap();
     check_solution!(puz, sol, "Angela", "Germany", "1954");
     check_solution!(puz, sol, "Donald", "United States", "1946");
     check_solution!(puz, sol, "Leo", "Ireland", "1979");
 }
+
+#[test]
+fn test_structured_formats() {
+    let text = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let expected = serde_json::to_value(&text).unwrap();
+    for name in &["simple.json", "simple.toml"] {
+        let puz = Puzzle::from_file(&puzzle_path(name)).unwrap();
+        assert_eq!(serde_json::to_value(&puz).unwrap(), expected, "{}", name);
+        let sol = solve(&puz).unwrap();
+        check_solution!(puz, sol, "Leo", "Ireland", "1979");
+    }
+
+    let json = serde_json::to_string(&text).unwrap();
+    let puz = Puzzle::parse_as(&json, PuzzleFormat::Json).unwrap();
+    assert_eq!(serde_json::to_value(&puz).unwrap(), expected);
+    let toml = text.to_string_as(PuzzleFormat::Toml).unwrap();
+    let puz = Puzzle::parse_as(&toml, PuzzleFormat::Toml).unwrap();
+    assert_eq!(serde_json::to_value(&puz).unwrap(), expected);
+
+    // Numbers stay numbers, and the pairs of variadic kinds are grouped.
+    let text = Puzzle::parse(
+        "[Categories]\nA\na1\na2\na3\n\nB\nb1\nb2\nb3\n\n[Clues]\n\
+         1,distance,a1,B,a2,2\n2,exactlyone,a1,b1,a2,b1\n",
+    )
+    .unwrap();
+    let json = serde_json::to_value(&text).unwrap();
+    assert_eq!(
+        json["clues"],
+        serde_json::json!([
+            { "name": "1", "kind": "distance", "args": ["a1", "B", "a2", 2] },
+            { "name": "2", "kind": "exactlyone", "args": [["a1", "b1"], ["a2", "b1"]] },
+        ])
+    );
+    for format in &[PuzzleFormat::Json, PuzzleFormat::Toml] {
+        let written = text.to_string_as(*format).unwrap();
+        let puz = Puzzle::parse_as(&written, *format).unwrap();
+        assert_eq!(puz.to_string(), text.to_string(), "{:?}", format);
+    }
+}
+
+#[test]
+fn test_structured_errors() {
+    let parse = |labels: &str, clues: &str| {
+        let json = format!(
+            r#"{{"categories": [{{"name": "A", "labels": [{0}]}}, {{"name": "B", "labels": [{0}]}}],
+                "clues": [{1}]}}"#,
+            labels, clues
+        );
+        Puzzle::parse_as(&json, PuzzleFormat::Json)
+            .unwrap_err()
+            .to_string()
+    };
+    let error = parse("", "");
+    assert!(
+        error.starts_with("Category \"A\" has no labels"),
+        "{}",
+        error
+    );
+
+    let labels = r#""1", "2""#;
+    let errors = [
+        (
+            r#"["A:1", "B", "A:2", 0]"#,
+            "distance",
+            "Invalid distance: 0",
+        ),
+        (
+            r#"["A:1", "B", "A:2", 2]"#,
+            "distance",
+            "Invalid distance: 2",
+        ),
+        (
+            r#"[["A:1", "B:1"]]"#,
+            "exactlyone",
+            "Invalid Clue: expected 2 arguments but found 1",
+        ),
+        (
+            r#"[["A:1", "B:1"], ["A:2"]]"#,
+            "exactlyone",
+            "Invalid Clue: expected 2 arguments but found 1",
+        ),
+        (
+            r#"["A:1", "B:1", "A:2", "B:1"]"#,
+            "exactlyone",
+            "Invalid argument 1 of clue: expected a list of arguments",
+        ),
+        (
+            r#"["A:1", 1]"#,
+            "yes",
+            "Invalid argument 2 of clue: expected a label",
+        ),
+        (
+            r#"["A:1"]"#,
+            "yes",
+            "Invalid Clue: expected 2 arguments but found 1",
+        ),
+    ];
+    for (args, kind, expected) in &errors {
+        let clue = format!(r#"{{"name": "1", "kind": "{}", "args": {}}}"#, kind, args);
+        let error = parse(labels, &clue);
+        assert!(error.starts_with(expected), "{}: {}", clue, error);
+    }
+}
+
+#[test]
+fn test_canonical_text() {
+    let path = puzzle_path("simple.txt");
+    let original = std::fs::read_to_string(&path).unwrap();
+    let puz = Puzzle::from_file(&path).unwrap();
+    assert_eq!(puz.to_string(), original);
+
+    let messy = original
+        .lines()
+        .map(|line| {
+            if line.starts_with('#') {
+                line.to_string()
+            } else {
+                line.replace(',', " , ")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    let puz = Puzzle::parse(&messy).unwrap();
+    assert_eq!(puz.to_string(), original);
+
+    let json = Puzzle::from_file(&puzzle_path("simple.json")).unwrap();
+    assert_eq!(json.to_string_as(PuzzleFormat::Text).unwrap(), original);
+}
+
+#[test]
+fn test_diagnostics() {
+    let source = "[Categories]\nName\nAngela\nDonald\n\nCountry\nGermany\nIreland\n\n\
+                  [Clues]\n1,yes,Angela,Irland\n2,afteratleast,Donald,Country,Angela\n";
+    let diagnostics = match Puzzle::parse(source) {
+        Err(PuzzleError::Parse { diagnostics }) => diagnostics,
+        other => panic!("expected parse errors, got {:?}", other),
+    };
+    assert_eq!(diagnostics.len(), 2);
+
+    let first = &diagnostics[0];
+    assert!(matches!(first.error, PuzzleError::LabelNotFound { .. }));
+    assert_eq!((first.span.line, first.span.column), (11, 14));
+    assert_eq!(&source[first.span.start..first.span.end], "Irland");
+    assert_eq!(
+        first.suggestion.as_ref().unwrap().to_string(),
+        "did you mean label `Ireland`?"
+    );
+
+    let second = &diagnostics[1];
+    assert!(matches!(
+        second.error,
+        PuzzleError::InvalidClueCount {
+            expected: 4,
+            found: 3
+        }
+    ));
+    assert_eq!(second.span.line, 12);
+}
+
+#[test]
+fn test_comments_and_quotes() {
+    let source = "# Surnames\r\n[Categories]\r\nName\r\n\"Smith, Jr.\"\r\n# Not \"Smith\"\r\nJones\r\n\r\n\r\n\
+                  Title\r\n\"#1 \\\"Doc\\\"\"\r\nChief\r\n\r\n[Clues]\r\n# Smith is the doctor.\r\n\
+                  1,yes,\"Smith, Jr.\", \"#1 \\\"Doc\\\"\" \r\n";
+    let puz = Puzzle::parse(source).unwrap();
+    assert_eq!(puz.constraints().len(), 1);
+
+    let expected = "# Surnames\n[Categories]\nName\nSmith, Jr.\n# Not \"Smith\"\nJones\n\n\
+                    Title\n\"#1 \\\"Doc\\\"\"\nChief\n\n[Clues]\n# Smith is the doctor.\n\
+                    1,yes,\"Smith, Jr.\",\"#1 \\\"Doc\\\"\"\n";
+    assert_eq!(puz.to_string(), expected);
+    assert_eq!(Puzzle::parse(expected).unwrap().to_string(), expected);
+
+    let sol = solve(&puz).unwrap();
+    check_solution!(puz, sol, "Smith, Jr.", "#1 \"Doc\"");
+
+    let diagnostics = match Puzzle::parse(&expected.replace("Doc\\\"\"\n", "Doc\n")) {
+        Err(PuzzleError::Parse { diagnostics }) => diagnostics,
+        other => panic!("expected parse errors, got {:?}", other),
+    };
+    assert!(matches!(
+        diagnostics[0].error,
+        PuzzleError::UnterminatedQuote
+    ));
+}
+
+#[test]
+fn test_solution_comments() {
+    let source = "[Categories]\nName\n\"Smith, Jr.\"\nJones\n\nTitle\nDoc\nChief\n\n\
+                  [Clues]\n1,yes,Jones,Chief\n\n[Solution]\n# Jones is in charge.\n\
+                  Jones,Chief\n\n#Smith is the doctor.\nDoc , \"Smith, Jr.\"\n# The end.\n";
+    let puz = Puzzle::parse(source).unwrap();
+    let expected = "[Categories]\nName\nSmith, Jr.\nJones\n\nTitle\nDoc\nChief\n\n\
+                    [Clues]\n1,yes,Jones,Chief\n\n[Solution]\n# Smith is the doctor.\n\
+                    \"Smith, Jr.\",Doc\n# Jones is in charge.\nJones,Chief\n# The end.\n";
+    assert_eq!(puz.to_string(), expected);
+    assert_eq!(Puzzle::parse(expected).unwrap().to_string(), expected);
+    assert!(matches!(check_solution(&puz), Outcome::Passed));
+}
+
+#[test]
+fn test_qualified_labels() {
+    let source = "[Categories]\nPerson\nMax\nAnna\n\nPet\nMax\nRex\n\n\
+                  [Clues]\n1,yes,Person:Max,Rex\n2,yes,Anna,Pet : Max\n";
+    let puz = Puzzle::parse(source).unwrap();
+    let sol = solve(&puz).unwrap();
+    check_solution!(puz, sol, "Anna", "Max");
+
+    let expected = source.replace("Pet : Max", "Pet:Max");
+    assert_eq!(puz.to_string(), expected);
+
+    let diagnostics = match Puzzle::parse(&source.replace("Person:Max", "Max")) {
+        Err(PuzzleError::Parse { diagnostics }) => diagnostics,
+        other => panic!("expected parse errors, got {:?}", other),
+    };
+    assert_eq!(diagnostics.len(), 1);
+    match &diagnostics[0].error {
+        PuzzleError::AmbiguousLabel { references, .. } => {
+            assert_eq!(references, &["Person:Max", "Pet:Max"])
+        }
+        other => panic!("expected an ambiguous label, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_localized_steps() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let sol = solve(&puz).unwrap();
+
+    let first = sol.steps[0].message.as_ref().unwrap();
+    assert_eq!(first.id, MessageId::DirectYes);
+    assert_eq!(
+        Locale::English.render(first, &puz),
+        "Constraint 1 => Direct confirmation on United States (Country) and 1946 (Year of Birth)"
+    );
+    assert_eq!(
+        Locale::French.render(first, &puz),
+        "Contrainte 1 => Confirmation directe de United States (Country) et 1946 (Year of Birth)"
+    );
+
+    for &locale in Locale::ALL {
+        for step in &sol.steps {
+            let description = locale.describe(step, &puz);
+            assert!(!description.contains('{'), "{}", description);
+            assert_eq!(description.trim(), description);
+        }
+    }
+
+    assert_eq!("fr-CA".parse(), Ok(Locale::French));
+    assert!("xx".parse::<Locale>().is_err());
+}
+
+#[test]
+fn test_count_solutions() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    assert_eq!(count_solutions(&puz, 10), 1);
+
+    let categories = "[Categories]\nA\na1\na2\na3\n\nB\nb1\nb2\nb3\n\n[Clues]\n";
+    let open = Puzzle::parse(&format!("{}1,yes,a1,b1\n", categories)).unwrap();
+    assert_eq!(count_solutions(&open, 10), 2);
+    assert_eq!(count_solutions(&open, 1), 1);
+    let budget = Budget {
+        max_steps: Some(1),
+        ..Budget::default()
+    };
+    assert_eq!(
+        count_solutions_within(&open, 10, &budget),
+        (1, Some(Status::StepLimit))
+    );
+    assert_eq!(
+        count_solutions_within(&open, 10, &Budget::default()),
+        (2, None)
+    );
+
+    let contradictory = format!("{}1,yes,a1,b1\n2,yes,a1,b2\n", categories);
+    let contradictory = Puzzle::parse(&contradictory).unwrap();
+    assert_eq!(count_solutions(&contradictory, 10), 0);
+}
+
+#[test]
+fn test_lint() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    assert!(lint(&puz).is_empty());
+
+    let puz = Puzzle::parse(
+        "[Categories]\nA\na1\na2\n\nB\nb1\nb2\n\n[Clues]\n\
+         1,no,a1,a2\n2,or,a1,b1,b1\n3,no,a1,a2\n",
+    )
+    .unwrap();
+    let warnings = lint(&puz);
+    assert_eq!(warnings.len(), 4);
+    assert!(matches!(warnings[0].lint, Lint::SameCategory { .. }));
+    assert!(matches!(warnings[1].lint, Lint::RepeatedLabel { .. }));
+    assert_eq!(warnings[2].name, "3");
+    assert!(matches!(warnings[2].lint, Lint::SameCategory { .. }));
+    assert!(matches!(&warnings[3].lint, Lint::Duplicate { name } if name == "1"));
+}
+
+#[test]
+fn test_render_grid() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let sol = solve(&puz).unwrap();
+    let style = GridStyle {
+        charset: Charset::Ascii,
+        column_labels: ColumnLabels::Abbreviated(3),
+        cursor: None,
+    };
+    let expected = "\
+\x20                      |Country        |Year of Birth
+                       | Ger  Ire  Uni | 194  195  197
+--------------+--------+---------------+---------------
+First Name    | Angela |  O    X    X  |  X    O    X
+              | Donald |  X    X    O  |  O    X    X
+              | Leo    |  X    O    X  |  X    X    O
+--------------+--------+---------------+---------------
+Year of Birth | 1946   |  X    X    O
+              | 1954   |  O    X    X
+              | 1979   |  X    O    X
+--------------+--------+---------------
+";
+    assert_eq!(render_grid(&sol.grid(sol.steps.len()), style), expected);
+
+    let first = render_grid(&sol.grid(1), style);
+    assert_eq!(first.matches('O').count(), 1);
+    assert_eq!(first.matches('X').count(), 0);
+    assert!(render_grid(&sol.grid(0), GridStyle::default()).contains("│ G  I  U │"));
+}
+
+#[test]
+fn test_marked_grid() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let donald = puz.label("Donald").unwrap();
+    let usa = puz.label("United States").unwrap();
+    let germany = puz.label("Germany").unwrap();
+
+    let mut grid = Grid::new(&puz);
+    grid.put(donald, usa, Cell::Yes);
+    assert!(grid.clone().deduce().is_some());
+
+    grid.put(donald, germany, Cell::Yes);
+    assert!(grid.clone().deduce().is_none());
+
+    grid.put(donald, germany, Cell::Empty);
+    let mut deduced = grid.clone();
+    deduced.deduce().unwrap();
+    assert!(deduced.is_solved());
+    assert_eq!(*deduced.at(donald, germany), Cell::No);
+    assert!(deduced.steps.iter().all(|step| step.cell != (donald, usa)));
+}
+
+#[test]
+fn test_verify() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let sol = solve(&puz).unwrap();
+    assert_eq!(sol.verify(), Some(vec![]));
+
+    let answer = Answer::parse(
+        &puz,
+        "Angela,Germany,1954\nDonald,United States,1946\nLeo,Ireland,1979\n",
+    )
+    .unwrap();
+    assert_eq!(
+        Some(&answer),
+        Answer::from_grid(&sol.grid(sol.steps.len())).as_ref()
+    );
+    assert!(verify(&puz, &answer).is_empty());
+
+    let answer = Answer::parse(
+        &puz,
+        "Angela,Ireland,1954\nDonald,United States,1946\nLeo,Germany,1979\n",
+    )
+    .unwrap();
+    let violations = verify(&puz, &answer);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].name, "2");
+    assert_eq!(violations[0].kind, "after");
+
+    let diagnostics = match Answer::parse(&puz, "Angela,Germany,1954\nDonald,Germany,1946\n") {
+        Err(PuzzleError::Parse { diagnostics }) => diagnostics,
+        other => panic!("expected diagnostics, got {:?}", other),
+    };
+    assert!(matches!(
+        diagnostics[0].error,
+        PuzzleError::ReusedLabel { .. }
+    ));
+    assert_eq!(diagnostics[0].span.line, 2);
+    assert!(matches!(
+        diagnostics[1].error,
+        PuzzleError::InvalidEntityCount {
+            expected: 3,
+            found: 2
+        }
+    ));
+}
+
+#[test]
+fn test_conformance() {
+    let results = test_dir(&puzzle_path("")).unwrap();
+    assert_eq!(results.len(), 3);
+    for result in &results {
+        assert!(
+            matches!(result.outcome, Outcome::Passed),
+            "{}: {:?}",
+            result.path.display(),
+            result.outcome
+        );
+        // The solver's deductions must agree with the verifier's.
+        let puz = Puzzle::from_file(&result.path).unwrap();
+        let budget = Budget {
+            self_check: true,
+            ..Budget::default()
+        };
+        let violations = try_solve_within(&puz, &budget).unwrap().verify().unwrap();
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    let source = std::fs::read_to_string(puzzle_path("simple.txt")).unwrap();
+    let partial = Puzzle::parse(&source.replace("3,or,Donald,1946,Ireland\n", "")).unwrap();
+    match check_solution(&partial) {
+        Outcome::Mismatch(differences) => {
+            assert_eq!(differences.len(), 4);
+            assert_eq!(
+                differences[0].to_string(),
+                "Angela, Country: expected Germany, found nothing"
+            );
+        }
+        other => panic!("expected a mismatch, got {:?}", other),
+    }
+
+    let wrong = Puzzle::parse(
+        &source
+            .replace("Leo,Ireland,1979", "Leo,Ireland,1954")
+            .replace("Angela,Germany,1954", "Angela,Germany,1979"),
+    )
+    .unwrap();
+    assert!(matches!(
+        check_solution(&wrong),
+        Outcome::InvalidSolution(_)
+    ));
+
+    let unsolved = Puzzle::parse(&source[..source.find("\n[Solution]").unwrap()]).unwrap();
+    assert!(unsolved.solution().is_none());
+    assert!(matches!(check_solution(&unsolved), Outcome::Skipped));
+}
+
+#[test]
+fn test_contradiction() {
+    let source = std::fs::read_to_string(puzzle_path("simple.txt")).unwrap();
+    let source = source.replace("\n[Solution]", "4,no,United States,1946\n\n[Solution]");
+    let puz = Puzzle::parse(&source).unwrap();
+    let contradiction = try_solve(&puz).unwrap_err();
+    assert_eq!(contradiction.clue, Some(3));
+    assert_eq!(contradiction.rule, "no");
+    let (x, y) = contradiction.cell.unwrap();
+    assert_eq!(
+        (puz.lookup_label(x), puz.lookup_label(y)),
+        ("United States", "1946")
+    );
+    assert_eq!(contradiction.steps[0].rule, "yes");
+    assert!(solve(&puz).is_none());
+}
+
+#[test]
+fn test_distance_rule_bounds() {
+    // The middle of three labels is no distance of 2 from any other, which
+    // the rule used to look for outside the grid.
+    let source = "[Categories]\nA\na1\na2\na3\n\nB\nb1\nb2\nb3\n\n[Clues]\n\
+                  1,distance,a1,B,a2,2\n2,yes,a3,b2\n3,no,a1,b3\n";
+    let puz = Puzzle::parse(source).unwrap();
+    let solution = try_solve(&puz).unwrap();
+    let answer = Answer::from_grid(&solution.grid(solution.steps.len())).unwrap();
+    assert_eq!(answer.table(&puz), "a1,b1\na2,b3\na3,b2\n");
+}
+
+#[test]
+fn test_zero_distance() {
+    let source = "[Categories]\nA\na1\na2\na3\n\nB\nb1\nb2\nb3\n\n[Clues]\n\
+                  1,distance,a1,B,a2,0\n";
+    let diagnostics = match Puzzle::parse(source) {
+        Err(PuzzleError::Parse { diagnostics }) => diagnostics,
+        other => panic!("expected parse errors, got {:?}", other),
+    };
+    assert_eq!(diagnostics.len(), 1);
+    assert!(matches!(
+        diagnostics[0].error,
+        PuzzleError::InvalidDistance { distance: 0 }
+    ));
+}
+
+#[test]
+fn test_budget() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let full = try_solve(&puz).unwrap();
+    assert_eq!(full.status, Status::Solved);
+
+    let budget = Budget {
+        max_steps: Some(5),
+        ..Budget::default()
+    };
+    let partial = try_solve_within(&puz, &budget).unwrap();
+    assert_eq!(partial.status, Status::StepLimit);
+    assert_eq!(partial.steps.len(), 5);
+    let grid = partial.grid(5);
+    let filled = grid
+        .cells()
+        .filter(|&(x, y)| *grid.at(x, y) != Cell::Empty)
+        .count();
+    assert_eq!(filled, 5);
+
+    let budget = Budget {
+        max_passes: Some(1),
+        ..Budget::default()
+    };
+    let partial = try_solve_within(&puz, &budget).unwrap();
+    assert_eq!(partial.status, Status::PassLimit);
+    assert!(partial.steps.len() < full.steps.len());
+
+    let budget = Budget {
+        cancel: Some(Arc::new(AtomicBool::new(true))),
+        ..Budget::default()
+    };
+    let partial = try_solve_within(&puz, &budget).unwrap();
+    assert_eq!(partial.status, Status::Cancelled);
+    assert!(partial.steps.len() < full.steps.len());
+}
+
+#[test]
+fn test_clue_kinds_documented() {
+    let readme = std::fs::read_to_string(puzzle_path("README.adoc")).unwrap();
+    for kind in CLUE_KINDS {
+        assert!(
+            readme.contains(&format!("`{},", kind.keyword)),
+            "{} is not documented",
+            kind.keyword
+        );
+        assert_eq!(clue_kind(kind.keyword).unwrap().keyword, kind.keyword);
+    }
+    let kind = clue_kind("exactlyone").unwrap();
+    assert_eq!(kind.param_kinds(3).len(), 4);
+    assert_eq!(kind.param_kinds(6).len(), 6);
+    assert_eq!(clue_kind("distance").unwrap().params[3].min, 1);
+}
+
+#[test]
+fn test_solution_report() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let budget = Budget {
+        max_steps: Some(4),
+        ..Budget::default()
+    };
+    let solution = try_solve_within(&puz, &budget).unwrap();
+    let report = SolutionReport::new(&solution, Locale::English);
+    assert_eq!(report.version, SCHEMA_VERSION);
+    assert_eq!(report.status, Status::StepLimit);
+    let names = report
+        .categories
+        .iter()
+        .map(|c| &c.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["First Name", "Country", "Year of Birth"]);
+    assert_eq!(report.entities.len(), 3);
+    for entity in &report.entities {
+        assert_eq!(entity.labels.len(), 3);
+        for (label, candidates) in entity.labels.iter().zip(&entity.candidates) {
+            match label {
+                Some(label) => assert_eq!(candidates, std::slice::from_ref(label)),
+                None => assert!(candidates.len() > 1),
+            }
+        }
+    }
+    let indices = report.steps.iter().map(|s| s.index).collect::<Vec<_>>();
+    assert_eq!(indices, [1, 2, 3, 4]);
+
+    let json = serde_json::to_string(&report).unwrap();
+    let again = SolutionReport::new(&try_solve_within(&puz, &budget).unwrap(), Locale::English);
+    assert_eq!(json, serde_json::to_string(&again).unwrap());
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn test_published_schema() {
+    let path = puzzle_path("../schema/solution.schema.json");
+    let published = std::fs::read_to_string(path).unwrap();
+    let schema = serde_json::to_string_pretty(&gridsolve::solution_schema()).unwrap() + "\n";
+    assert!(
+        published == schema,
+        "schema/solution.schema.json is out of date, regenerate it with `gridsolve schema`"
+    );
+}
+
+#[test]
+fn test_owned_solution() {
+    let owned = {
+        let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+        OwnedSolution::from(&solve(&puz).unwrap())
+    };
+    let json = std::thread::spawn(move || serde_json::to_string(&owned).unwrap())
+        .join()
+        .unwrap();
+    let owned: OwnedSolution = serde_json::from_str(&json).unwrap();
+
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let solution = owned.attach(&puz).unwrap();
+    let expected = solve(&puz).unwrap();
+    assert_eq!(solution.status, Status::Solved);
+    assert_eq!(solution.labels, expected.labels);
+    assert_eq!(solution.steps.len(), expected.steps.len());
+    assert_eq!(solution.verify(), Some(vec![]));
+    assert_eq!(
+        Locale::French.describe(&solution.steps[0], &puz),
+        Locale::French.describe(&expected.steps[0], &puz)
+    );
+
+    let mut repeated = owned.clone();
+    repeated.steps.push(owned.steps[0].clone());
+    let error = repeated.attach(&puz).unwrap_err();
+    assert!(
+        matches!(error, PuzzleError::InvalidStep { .. }),
+        "{}",
+        error
+    );
+
+    let mut unknown = owned.clone();
+    unknown.steps[0].label1 = "Nobody".to_string();
+    assert!(unknown.attach(&puz).is_err());
+    unknown = owned;
+    unknown.steps[0].rule = "magic".to_string();
+    assert!(unknown.attach(&puz).is_err());
+}
+
+#[test]
+fn test_history() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let solution = solve(&puz).unwrap();
+    let full = solution.grid(solution.steps.len());
+    let history = solution.history(4);
+    assert_eq!(history.deltas.len(), solution.steps.len());
+    assert_eq!(history.snapshots.len(), solution.steps.len() / 4 + 1);
+    let cells = |grid: &Grid| {
+        grid.cells()
+            .map(|(x, y)| *grid.at(x, y))
+            .collect::<Vec<_>>()
+    };
+    for n in 0..=solution.steps.len() {
+        let expected = solution.grid(n);
+        assert_eq!(history.cells_at(n), cells(&expected), "step {}", n);
+        assert_eq!(cells(&history.grid_at(&puz, n)), cells(&expected));
+        let undone = full.at_step(&history, n);
+        assert_eq!(cells(&undone), cells(&expected));
+        assert_eq!(undone.steps.len(), n);
+    }
+    assert_eq!(history.snapshots[1], Snapshot::new(&solution.grid(4)));
+}
+
+#[test]
+fn test_try_solve_all() {
+    let categories = "[Categories]\nA\na1\na2\na3\n\nB\nb1\nb2\nb3\n\n[Clues]\n";
+    let contradictory = format!("{}1,yes,a1,b1\n2,yes,a1,b2\n", categories);
+    let puzzles = vec![
+        Puzzle::from_file(&puzzle_path("simple.txt")).unwrap(),
+        Puzzle::parse(&contradictory).unwrap(),
+        Puzzle::from_file(&puzzle_path("simple.txt")).unwrap(),
+    ];
+    let steps = |solution: &Solution| {
+        solution
+            .steps
+            .iter()
+            .map(|step| (step.cell, step.yes, step.rule))
+            .collect::<Vec<_>>()
+    };
+
+    let results = try_solve_all(&puzzles, &Budget::default());
+    assert_eq!(results.len(), 3);
+    assert!(results[1].is_err());
+    let first = results[0].as_ref().unwrap();
+    assert_eq!(first.status, Status::Solved);
+    assert_eq!(
+        steps(first),
+        steps(&try_solve(&puzzles[0]).unwrap()),
+        "steps should be the same however the puzzle is solved"
+    );
+    assert_eq!(steps(first), steps(results[2].as_ref().unwrap()));
+}
+
+#[test]
+fn test_parse_partial() {
+    let source = "[Categories]\nA\na1\na2\n\nB\nb1\nb2\n\n[Clues]\n\
+                  1,no,a1,b2\n2,no,a1,b3\n3,exactlyone,a1,b1,a2,b1\n4,distance,a1,B,a2,1\n";
+    let (puz, diagnostics) = Puzzle::parse_partial(source);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].span.line, 12);
+    let descriptions = puz
+        .constraints()
+        .iter()
+        .map(|c| c.describe(&puz, Locale::English))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        [
+            "a1 (A) is not b2 (B).",
+            "Exactly one of these is true: a1 (A) is b1 (B). a2 (A) is b1 (B).",
+            "In B, a1 (A) appears exactly 1 before or after a2 (A).",
+        ]
+    );
+    assert_eq!(
+        puz.constraints()[2].describe(&puz, Locale::French),
+        "Dans B, a1 (A) apparaît exactement 1 avant ou après a2 (A)."
+    );
+
+    let solution = solve(&puz).unwrap();
+    let answer = Answer::from_grid(&solution.grid(solution.steps.len())).unwrap();
+    assert_eq!(answer.table(&puz), "a1,b1\na2,b2\n");
+}
+
+#[test]
+fn test_share_url() {
+    let puz = Puzzle::from_file(&puzzle_path("simple.txt")).unwrap();
+    let shared = SharedPuzzle::from(&puz);
+    assert_eq!(shared.num_labels, 3);
+    assert_eq!(shared.labels[3], "Germany");
+    assert_eq!(shared.clues[1].params, [2, 2, 3]);
+
+    let url = shared.to_url(SHARE_URL);
+    assert!(url.starts_with("https://avp.github.io/gridsolve/#%7B%22categories%22"));
+    assert_eq!(SharedPuzzle::from_url(&url).unwrap(), shared);
+    let parsed = Puzzle::parse_as(&url, PuzzleFormat::Url).unwrap();
+    assert_eq!(parsed.constraints().len(), puz.constraints().len());
+    for (a, b) in parsed.constraints().iter().zip(puz.constraints()) {
+        assert_eq!((&a.name, &a.kind), (&b.name, &b.kind));
+    }
+    assert_eq!(
+        solve(&parsed).unwrap().steps.len(),
+        solve(&puz).unwrap().steps.len()
+    );
+
+    // Older links have their numbers written as strings.
+    let old = "https://avp.github.io/gridsolve/#%7B%22categories%22%3A%5B%22A%22%2C%22B%22%5D\
+               %2C%22labels%22%3A%5B%22a1%22%2C%22a2%22%2C%22b1%22%2C%22b2%22%5D%2C\
+               %22numLabels%22%3A%222%22%2C%22clues%22%3A%5B%7B%22name%22%3A%221%22%2C\
+               %22kind%22%3A%22yes%22%2C%22params%22%3A%5B%220%22%2C%223%22%5D%7D%5D%7D";
+    let puz = Puzzle::parse_as(old, PuzzleFormat::Url).unwrap();
+    assert_eq!(
+        puz.constraints()[0].describe(&puz, Locale::English),
+        "a1 (A) is b2 (B)."
+    );
+
+    // They also write afterexactly as after with four parameters.
+    let after = "https://avp.github.io/gridsolve/#%7B%22categories%22%3A%5B%22A%22%2C%22B%22%5D\
+                 %2C%22labels%22%3A%5B%22a1%22%2C%22a2%22%2C%22b1%22%2C%22b2%22%5D%2C\
+                 %22numLabels%22%3A%222%22%2C%22clues%22%3A%5B%7B%22name%22%3A%221%22%2C\
+                 %22kind%22%3A%22after%22%2C%22params%22%3A%5B%220%22%2C%221%22%2C%221%22\
+                 %2C%221%22%5D%7D%5D%7D";
+    let puz = Puzzle::parse_as(after, PuzzleFormat::Url).unwrap();
+    assert_eq!(
+        puz.constraints()[0].describe(&puz, Locale::English),
+        "In B, a1 (A) appears exactly 1 after a2 (A)."
+    );
+
+    let mut shared = SharedPuzzle::from_url(old).unwrap();
+    shared.clues[0].params[1] = 4;
+    assert!(matches!(
+        shared.to_puzzle(),
+        Err(PuzzleError::InvalidIndex {
+            kind: "label",
+            index: 4
+        })
+    ));
+
+    let mut shared = SharedPuzzle::from_url(after).unwrap();
+    shared.clues[0] = SharedClue {
+        name: "1".to_string(),
+        kind: "distance".to_string(),
+        params: vec![0, 1, 1, 0],
+    };
+    assert!(matches!(
+        shared.to_puzzle(),
+        Err(PuzzleError::InvalidDistance { distance: 0 })
+    ));
+
+    let mut shared = SharedPuzzle::from_url(old).unwrap();
+    shared.num_labels = usize::MAX;
+    assert!(matches!(
+        shared.to_puzzle(),
+        Err(PuzzleError::InvalidLabelCount { found: 4, .. })
+    ));
+    shared.num_labels = 1;
+    match shared.to_puzzle() {
+        Err(PuzzleError::InvalidLabelCount {
+            category,
+            expected: 1,
+            found: 3,
+        }) => assert_eq!(category, "B"),
+        other => panic!("expected an invalid label count, got {:?}", other),
+    }
+    shared.num_labels = 3;
+    match shared.to_puzzle() {
+        Err(PuzzleError::InvalidLabelCount {
+            category,
+            expected: 3,
+            found: 1,
+        }) => assert_eq!(category, "B"),
+        other => panic!("expected an invalid label count, got {:?}", other),
+    }
+    assert!(matches!(
+        SharedPuzzle::from_url("https://avp.github.io/gridsolve/#%7"),
+        Err(PuzzleError::InvalidShareUrl { .. })
+    ));
+}
@@ -1,24 +1,20 @@
 import React, { useState, useEffect } from 'react';
-import KINDS from './ClueKinds';
+import getKinds, { paramKinds } from './ClueKinds';
 
 export default function ClueInput({ categories, labels, onChange }) {
   const [name, setName] = useState('');
-  const [kind, setKind] = useState(KINDS.yes);
-  const [params, setParams] = useState(initParams(KINDS.yes));
+  const [kind, setKind] = useState(getKinds().yes);
+  const [params, setParams] = useState(initParams(getKinds().yes));
   const paramInputs = [];
 
   function initParams(k) {
-    const copy = [];
-    for (const param of k.params) {
-      copy.push(0);
-    }
-    return copy;
+    return paramKinds(k, 0).map((param) => (param.kind === 'number' ? 1 : 0));
   }
 
   function makeClue() {
     return {
       name,
-      kind: kind.name,
+      kind: kind.keyword,
       params,
     };
   }
@@ -33,19 +29,21 @@ export default function ClueInput({ categories, labels, onChange }) {
     setParams(copy);
   }
 
-  function paramSelect(optStrings, paramIdx) {
+  function paramSelect(optStrings, paramIdx, values) {
     let options = [];
     for (let i = 0, n = optStrings.length; i < n; ++i) {
+      const value = values ? values[i] : i;
       options.push(
-        <option key={i} value={i}>
+        <option key={i} value={value}>
           {optStrings[i]}
         </option>
       );
     }
     return (
       <select
+        key={paramIdx}
         onChange={(e) => {
-          setParamAt(paramIdx, e.target.value);
+          setParamAt(paramIdx, Number(e.target.value));
         }}
         value={params[paramIdx]}
       >
@@ -56,7 +54,7 @@ export default function ClueInput({ categories, labels, onChange }) {
 
   function kindSelect() {
     let options = [];
-    for (let name of Object.keys(KINDS)) {
+    for (let name of Object.keys(getKinds())) {
       options.push(
         <option key={name} value={name}>
           {name}
@@ -73,7 +71,7 @@ export default function ClueInput({ categories, labels, onChange }) {
         />
         <select
           onChange={(e) => {
-            let k = KINDS[e.target.value];
+            let k = getKinds()[e.target.value];
             setKind(k);
             setParams(initParams(k));
           }}
@@ -85,79 +83,52 @@ export default function ClueInput({ categories, labels, onChange }) {
     );
   }
 
-  function labelSelect(paramIdx) {
-    return paramSelect(labels, paramIdx);
-  }
-
   function numberSelect(paramIdx) {
+    const numLabels = categories.length ? labels.length / categories.length : 0;
     const nums = [];
-    for (let i = 1; i < categories.length-1; ++i) {
+    for (let i = 1; i < numLabels; ++i) {
       nums.push(i);
     }
-    return paramSelect(nums, paramIdx);
+    return paramSelect(nums, paramIdx, nums);
   }
 
-  function categorySelect(paramIdx) {
-    return paramSelect(categories, paramIdx);
+  function infoText(s, key) {
+    return (
+      <span key={key} className="infoText">
+        {s}
+      </span>
+    );
   }
 
-  function infoText(s) {
-    return <span className="infoText">{s}</span>;
+  paramKinds(kind, params.length).forEach((param, i) => {
+    paramInputs.push(infoText(param.name, 'name' + i));
+    switch (param.kind) {
+      case 'label':
+        paramInputs.push(paramSelect(labels, i));
+        break;
+      case 'category':
+        paramInputs.push(paramSelect(categories, i));
+        break;
+      case 'number':
+        paramInputs.push(numberSelect(i));
+        break;
+    }
+  });
+  if (kind.variadic) {
+    paramInputs.push(
+      <button
+        key="more"
+        type="button"
+        onClick={() =>
+          setParams(params.concat(initParams({ ...kind, variadic: false })))
+        }
+      >
+        +
+      </button>
+    );
   }
+  paramInputs.push(infoText(kind.description, 'description'));
 
-  switch (kind.name) {
-    case 'yes':
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is'));
-      paramInputs.push(labelSelect(1));
-      break;
-    case 'no':
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is not'));
-      paramInputs.push(labelSelect(1));
-      break;
-    case 'after':
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is after'));
-      paramInputs.push(labelSelect(2));
-      paramInputs.push(infoText('in'));
-      paramInputs.push(categorySelect(1));
-      break;
-    case 'afterexactly':
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is exactly'));
-      paramInputs.push(labelSelect(3));
-      paramInputs.push(infoText('spots after'));
-      paramInputs.push(labelSelect(2));
-      paramInputs.push(infoText('in'));
-      paramInputs.push(categorySelect(1));
-      break;
-    case 'or':
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is either'));
-      paramInputs.push(labelSelect(1));
-      paramInputs.push(infoText('or'));
-      paramInputs.push(labelSelect(2));
-      break;
-    case 'xor':
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is exactly one of'));
-      paramInputs.push(labelSelect(1));
-      paramInputs.push(infoText('or'));
-      paramInputs.push(labelSelect(2));
-      break;
-    case 'twobytwo':
-      paramInputs.push(infoText('either'));
-      paramInputs.push(labelSelect(0));
-      paramInputs.push(infoText('is'));
-      paramInputs.push(labelSelect(2));
-      paramInputs.push(infoText('and'));
-      paramInputs.push(labelSelect(1));
-      paramInputs.push(infoText('is'));
-      paramInputs.push(labelSelect(3));
-      paramInputs.push(infoText('or vice versa'));
-      break;
-  }
   return (
     <div className="clueInput">
       {kindSelect()}
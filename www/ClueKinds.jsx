@@ -1,31 +1,27 @@
-const KINDS = {
-  yes: {
-    name: 'yes',
-    params: ['label', 'label'],
-  },
-  no: {
-    name: 'no',
-    params: ['label', 'label'],
-  },
-  after: {
-    name: 'after',
-    params: ['label', 'category', 'label'],
-  },
-  afterexactly: {
-    name: 'after',
-    params: ['label', 'category', 'label', 'number'],
-  },
-  or: {
-    name: 'or',
-    params: ['label', 'label', 'label'],
-  },
-  xor: {
-    name: 'xor',
-    params: ['label', 'label', 'label'],
-  },
-  twobytwo: {
-    name: 'twobytwo',
-    params: ['label', 'label', 'label', 'label'],
-  },
-};
-export default KINDS;
+import { clue_kinds } from './pkg/gridsolve_wasm.js';
+
+let kinds = null;
+
+// The kinds of clue, by keyword, as described by the solver.
+// Only available once the WASM module has been initialized.
+export default function getKinds() {
+  if (!kinds) {
+    kinds = {};
+    for (const kind of clue_kinds()) {
+      kinds[kind.keyword] = kind;
+    }
+  }
+  return kinds;
+}
+
+// The kind of each parameter of `kind` when `count` are given, repeating the
+// parameters of variadic kinds.
+export function paramKinds(kind, count) {
+  const n = kind.params.length;
+  const repeat = kind.variadic ? Math.max(2, Math.ceil(count / n)) : 1;
+  const result = [];
+  for (let i = 0; i < n * repeat; ++i) {
+    result.push(kind.params[i % n]);
+  }
+  return result;
+}
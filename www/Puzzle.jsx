@@ -1,38 +1,7 @@
-import KINDS from './ClueKinds';
+import { shared_puzzle_text } from './pkg/gridsolve_wasm.js';
 
-export function makePuzzleString({ categories, labels, numLabels, clues }) {
-  let puzzleString = '[Categories]\n';
-  let l = 0;
-  for (let c = 0; c < categories.length; ++c) {
-    puzzleString += categories[c] + '\n';
-    for (let i = 0; i < numLabels; ++i) {
-      puzzleString += labels[l] + '\n';
-      ++l;
-    }
-    puzzleString += '\n';
-  }
-  puzzleString += '[Clues]\n';
-  for (const clue of clues) {
-    const kind = KINDS[clue.kind];
-    puzzleString += clue.name + ',' + kind.name;
-    for (let i = 0, e = kind.params.length; i < e; ++i) {
-      puzzleString += ',';
-      switch (kind.params[i]) {
-        case 'label':
-          puzzleString += labels[clue.params[i]];
-          break;
-        case 'category':
-          puzzleString += categories[clue.params[i]];
-          break;
-        case 'number':
-          puzzleString += clue.params[i].toString();
-          break;
-        default:
-          break;
-      }
-    }
-    puzzleString += '\n';
-  }
-
-  return puzzleString;
+// Write the puzzle from the form, as kept in the URL hash, in the text format.
+// Throws a SolveError if it isn't a valid puzzle.
+export function makePuzzleString(puzzle) {
+  return shared_puzzle_text(JSON.stringify(puzzle));
 }
@@ -1,6 +1,5 @@
 import React, { useState } from 'react';
 import ClueInput from './ClueInput';
-import KINDS from './ClueKinds';
 import { makePuzzleString } from './Puzzle';
 import { solve_puzzle as solveWASM } from './pkg/gridsolve_wasm.js';
 
@@ -39,14 +38,15 @@ export default function PuzzleInput({ onSolution }) {
       numLabels,
       clues,
     };
-    const puzzleString = makePuzzleString(puzzle);
-    const solution = JSON.parse(solveWASM(puzzleString));
-
-    if (solution.error) {
-      console.log(solution.error);
-    } else {
-      onSolution(puzzle, solution);
+    let solution;
+    try {
+      solution = solveWASM(makePuzzleString(puzzle), navigator.language);
+    } catch (error) {
+      // A SolveError, see the TypeScript definitions of gridsolve_wasm.
+      console.log(error.message, error);
+      return;
     }
+    onSolution(puzzle, solution);
   }
 
   const categoryInputs = [];
@@ -64,14 +64,24 @@ function SolutionTable({ puzzle, solution }) {
 function SolutionGrid({ puzzle, solution, numSteps }) {
   const rows = [];
 
-  // Table of populated cells in the grid for the first `numSteps`.
+  // Table of populated cells in the grid for the first `numSteps`, rebuilt
+  // from the nearest snapshot instead of every step.
+  const { interval, layout, snapshots, deltas } = solution.history;
+  const snapshot = snapshots[Math.floor(numSteps / interval)];
+  const cells = snapshot.cells.split('');
+  for (let i = snapshot.step; i < numSteps; ++i) {
+    cells[deltas[i].cell] = deltas[i].yes ? 'y' : 'n';
+  }
   const lookup = {};
-  for (let i = 0; i < numSteps; ++i) {
-    const step = solution.steps[i];
-    lookup[step.label1] ??= {};
-    lookup[step.label1][step.label2] = step.yes;
-    lookup[step.label2] ??= {};
-    lookup[step.label2][step.label1] = step.yes;
+  for (let i = 0; i < layout.length; ++i) {
+    if (cells[i] === '.') {
+      continue;
+    }
+    const [label1, label2] = layout[i];
+    lookup[label1] ??= {};
+    lookup[label1][label2] = cells[i] === 'y';
+    lookup[label2] ??= {};
+    lookup[label2][label1] = cells[i] === 'y';
   }
 
   function doLookup(label1, label2) {
@@ -1,6 +1,6 @@
 import React, { useState, useEffect, useHash } from 'react';
 import ReactDOM from 'react-dom/client';
-import initWASM, { solve_puzzle as solveWASM } from './pkg/gridsolve_wasm.js';
+import initWASM, { WasmSolver } from './pkg/gridsolve_wasm.js';
 import PuzzleInput from './PuzzleInput';
 import Solution from './Solution';
 import { makePuzzleString } from './Puzzle';
@@ -53,10 +53,8 @@ function readHash() {
       const puzzle = JSON.parse(
         decodeURIComponent(window.location.hash.substring(1))
       );
-      const solution = JSON.parse(solveWASM(makePuzzleString(puzzle)));
-      if (!solution.error) {
-        return [puzzle, solution];
-      }
+      const solution = solverFor(makePuzzleString(puzzle)).solution();
+      return [puzzle, solution];
     } catch (e) {
       console.error(e);
     }
@@ -64,6 +62,26 @@ function readHash() {
   return [null, null];
 }
 
+// The most steps to make when solving a puzzle from the hash, so that a huge
+// puzzle can't hang the page.
+const MAX_STEPS = 100000;
+
+// The solver of the puzzle last read from the hash, which is reused when the
+// hash changes without changing the puzzle.
+let cached = null;
+
+function solverFor(input) {
+  if (!cached || cached.input !== input) {
+    const solver = new WasmSolver(input, navigator.language, { maxSteps: MAX_STEPS });
+    solver.run_until(MAX_STEPS);
+    if (cached) {
+      cached.solver.free();
+    }
+    cached = { input, solver };
+  }
+  return cached.solver;
+}
+
 let existingPuzzle = null;
 let existingSolution = null;
 const root = ReactDOM.createRoot(document.getElementById('root'));